@@ -0,0 +1,130 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Benchmark in-memory database read performance.
+//! The setup mirrors the RocksDB read benchmark in `kvdb-rocksdb`: `NEEDLES * NEEDLES_TO_HAYSTACK_RATIO`
+//! 32-bytes random keys are written with random values 140 +/- 28 bytes long, and a random
+//! sample of `NEEDLES` keys is used for point lookups.
+//!
+//! In addition to single-threaded reads, the "get key while writing" benchmark measures point
+//! lookups in one column while another thread keeps writing to a different column.
+
+const NEEDLES: usize = 10_000;
+const NEEDLES_TO_HAYSTACK_RATIO: usize = 100;
+
+use std::{
+	io,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	thread,
+};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use kvdb::KeyValueDB;
+use kvdb_memorydb::InMemory;
+use rand::{distributions::Uniform, seq::SliceRandom, Rng};
+
+criterion_group!(benches, get, iter, snapshot);
+criterion_main!(benches);
+
+/// Generate `n` random bytes +/- 20%.
+fn n_random_bytes(n: usize) -> Vec<u8> {
+	let mut rng = rand::thread_rng();
+	let variability: i64 = rng.gen_range(0, (n / 5) as i64);
+	let plus_or_minus: i64 = if variability % 2 == 0 { 1 } else { -1 };
+	let range = Uniform::from(0..u8::MAX);
+	rng.sample_iter(&range).take((n as i64 + plus_or_minus * variability) as usize).collect()
+}
+
+/// Writes `NEEDLES * NEEDLES_TO_HAYSTACK_RATIO` random keys to column 0 of the DB and returns
+/// every `NEEDLES_TO_HAYSTACK_RATIO`th of them.
+fn populate(db: &InMemory) -> io::Result<Vec<[u8; 32]>> {
+	let mut rng = rand::thread_rng();
+	let mut needles = Vec::with_capacity(NEEDLES);
+	let mut batch = db.transaction();
+	for i in 0..NEEDLES * NEEDLES_TO_HAYSTACK_RATIO {
+		let key: [u8; 32] = rng.gen();
+		if i % NEEDLES_TO_HAYSTACK_RATIO == 0 {
+			needles.push(key);
+		}
+		batch.put(0, &key, &n_random_bytes(140));
+	}
+	db.write(batch)?;
+	Ok(needles)
+}
+
+fn get(c: &mut Criterion) {
+	let db = Arc::new(kvdb_memorydb::create(2));
+	let needles = populate(&db).expect("in-memory db works");
+
+	c.bench_function("get key", |b| {
+		b.iter(|| {
+			let needle = needles.choose(&mut rand::thread_rng()).expect("needles is not empty");
+			black_box(db.get(0, needle).unwrap());
+		});
+	});
+
+	c.bench_function("get key by prefix", |b| {
+		b.iter(|| {
+			let needle = needles.choose(&mut rand::thread_rng()).expect("needles is not empty");
+			black_box(db.get_by_prefix(0, &needle[..8]).unwrap());
+		});
+	});
+
+	let stop = Arc::new(AtomicBool::new(false));
+	let writer = {
+		let db = db.clone();
+		let stop = stop.clone();
+		thread::spawn(move || {
+			let mut rng = rand::thread_rng();
+			while !stop.load(Ordering::Relaxed) {
+				let mut batch = db.transaction();
+				batch.put(1, &rng.gen::<[u8; 32]>(), &n_random_bytes(140));
+				db.write(batch).expect("in-memory db works");
+			}
+		})
+	};
+	c.bench_function("get key while writing", |b| {
+		b.iter(|| {
+			let needle = needles.choose(&mut rand::thread_rng()).expect("needles is not empty");
+			black_box(db.get(0, needle).unwrap());
+		});
+	});
+	stop.store(true, Ordering::Relaxed);
+	writer.join().expect("writer thread doesn't panic");
+}
+
+fn iter(c: &mut Criterion) {
+	let db = kvdb_memorydb::create(1);
+	populate(&db).expect("in-memory db works");
+
+	c.bench_function("iterate over 1k keys", |b| {
+		b.iter(|| {
+			black_box(db.iter(0).take(1000).collect::<Vec<_>>());
+		});
+	});
+
+	c.bench_function("single key from iterator", |b| {
+		b.iter(|| {
+			black_box(db.iter(0).next().unwrap());
+		});
+	});
+}
+
+fn snapshot(c: &mut Criterion) {
+	let db = kvdb_memorydb::create(1);
+	populate(&db).expect("in-memory db works");
+
+	c.bench_function("snapshot", |b| {
+		b.iter(|| {
+			black_box(db.snapshot());
+		});
+	});
+}
@@ -6,96 +6,323 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+mod persistence;
+
 use kvdb::{DBOp, DBTransaction, DBValue, KeyValueDB};
-use parity_util_mem::MallocSizeOf;
-use parking_lot::RwLock;
+use parity_util_mem::{MallocSizeOf, MallocSizeOfOps};
+use parking_lot::{Mutex, RwLock};
+use persistence::{Entries, Journal};
 use std::{
-	collections::{BTreeMap, HashMap},
+	collections::{btree_map, BTreeMap, HashMap},
 	io,
+	ops::Bound,
+	path::Path,
+	sync::Arc,
 };
 
 /// A key-value database fulfilling the `KeyValueDB` trait, living in memory.
-/// This is generally intended for tests and is not particularly optimized.
-#[derive(Default, MallocSizeOf)]
+/// This is generally intended for tests and tools.
+///
+/// Every column is guarded by its own lock, so readers and writers of different columns
+/// don't contend. Columns are copy-on-write: [`InMemory::snapshot`] and the iterators only
+/// take a reference to the current content of a column, which is copied by the next write
+/// to that column while the reference is alive.
+///
+/// The content of the database can be saved to a file with [`InMemory::save`] and loaded back
+/// with [`load`]. An optional append-only journal, attached with [`InMemory::with_journal`],
+/// records every write so that it can be replayed when the database is opened again.
+#[derive(Default)]
 pub struct InMemory {
-	columns: RwLock<HashMap<u32, BTreeMap<Vec<u8>, DBValue>>>,
+	columns: Vec<RwLock<Arc<Column>>>,
+	journal: Option<Mutex<Journal>>,
 }
 
 /// Create an in-memory database with the given number of columns.
 /// Columns will be indexable by 0..`num_cols`
 pub fn create(num_cols: u32) -> InMemory {
-	let mut cols = HashMap::new();
+	let columns = (0..num_cols).map(|_| RwLock::new(Arc::new(Column::default()))).collect();
+	InMemory { columns, journal: None }
+}
+
+/// Load an in-memory database from a snapshot previously written with [`InMemory::save`].
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<InMemory> {
+	let columns = persistence::load_snapshot(path.as_ref())?
+		.into_iter()
+		.map(|entries| RwLock::new(Arc::new(Column { entries, write_order: None })))
+		.collect();
+	Ok(InMemory { columns, journal: None })
+}
+
+impl InMemory {
+	/// Attach an append-only journal located at `path` to the database.
+	///
+	/// Transactions already recorded in the journal are replayed on top of the current content
+	/// of the database, and every subsequent write is appended to it before being applied.
+	/// The journal file is created if it doesn't exist.
+	pub fn with_journal<P: AsRef<Path>>(self, path: P) -> io::Result<InMemory> {
+		let mut columns: Vec<_> = self.columns.into_iter().map(RwLock::into_inner).collect();
+		let journal = Journal::open(path.as_ref(), |ops| {
+			for op in ops {
+				if let Some(column) = columns.get_mut(op.col() as usize) {
+					Arc::make_mut(column).apply(op);
+				}
+			}
+		})?;
+		let columns = columns.into_iter().map(RwLock::new).collect();
+		Ok(InMemory { columns, journal: Some(Mutex::new(journal)) })
+	}
+
+	/// Limit the number of entries of every column to `max_entries`.
+	///
+	/// When an insertion makes a column exceed the limit, the least recently written entry of the
+	/// column is evicted. Entries already in the database are considered written in key order.
+	///
+	/// # Panics
+	///
+	/// Panics if `max_entries` is 0.
+	pub fn with_capacity_limit(self, max_entries: usize) -> InMemory {
+		assert!(max_entries > 0, "a column must be able to hold at least one entry");
+		let columns = self
+			.columns
+			.into_iter()
+			.map(|column| {
+				let mut column = column.into_inner();
+				Arc::make_mut(&mut column).set_capacity_limit(max_entries);
+				RwLock::new(column)
+			})
+			.collect();
+		InMemory { columns, journal: self.journal }
+	}
 
-	for idx in 0..num_cols {
-		cols.insert(idx, BTreeMap::new());
+	/// Take a consistent copy of the database.
+	///
+	/// The copy shares its columns with the database until either of them is written to, so this
+	/// is cheap regardless of the size of the database. The copy has no journal attached.
+	pub fn snapshot(&self) -> InMemory {
+		let columns = self.read_columns().into_iter().map(RwLock::new).collect();
+		InMemory { columns, journal: None }
 	}
 
-	InMemory { columns: RwLock::new(cols) }
+	/// Save the content of all columns to a snapshot file at `path`.
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		let columns = self.read_columns();
+		let entries: Vec<_> = columns.iter().map(|column| &column.entries).collect();
+		persistence::save_snapshot(path.as_ref(), &entries)
+	}
+
+	/// Save the content of all columns to a snapshot file at `path` and clear the journal,
+	/// if any. The snapshot then holds every write recorded in the journal so far.
+	pub fn checkpoint<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		// Block writers so that no transaction is lost between the snapshot and the truncation.
+		let columns: Vec<_> = self.columns.iter().map(|column| column.write()).collect();
+		let entries: Vec<_> = columns.iter().map(|column| &column.entries).collect();
+		persistence::save_snapshot(path.as_ref(), &entries)?;
+		if let Some(journal) = &self.journal {
+			journal.lock().clear()?;
+		}
+		Ok(())
+	}
+
+	/// Get a reference to the current content of every column.
+	fn read_columns(&self) -> Vec<Arc<Column>> {
+		// All locks are held at once to get a consistent view across columns. Writers lock columns
+		// in ascending order too, so this can't deadlock.
+		let guards: Vec<_> = self.columns.iter().map(|column| column.read()).collect();
+		guards.iter().map(|column| Arc::clone(column)).collect()
+	}
+
+	fn column(&self, col: u32) -> Option<Arc<Column>> {
+		self.columns.get(col as usize).map(|column| Arc::clone(&column.read()))
+	}
 }
 
-impl KeyValueDB for InMemory {
-	fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
-		let columns = self.columns.read();
-		match columns.get(&col) {
-			None => Err(io::Error::new(io::ErrorKind::Other, format!("No such column family: {:?}", col))),
-			Some(map) => Ok(map.get(key).cloned()),
+impl MallocSizeOf for InMemory {
+	fn size_of(&self, ops: &mut MallocSizeOfOps) -> usize {
+		// Columns shared with snapshots are accounted for in each of them.
+		self.columns.iter().map(|column| column.read().size_of(ops)).sum()
+	}
+}
+
+/// Content of a column.
+#[derive(Clone, Default, MallocSizeOf)]
+struct Column {
+	entries: Entries,
+	/// Write order of the keys, only tracked when the column has a capacity limit.
+	write_order: Option<WriteOrder>,
+}
+
+/// Write order of the keys of a column with a capacity limit.
+#[derive(Clone, Default, MallocSizeOf)]
+struct WriteOrder {
+	max_entries: usize,
+	next_seq: u64,
+	seq_by_key: HashMap<Vec<u8>, u64>,
+	key_by_seq: BTreeMap<u64, Vec<u8>>,
+}
+
+impl WriteOrder {
+	fn touch(&mut self, key: &[u8]) {
+		let seq = self.next_seq;
+		self.next_seq += 1;
+		if let Some(old_seq) = self.seq_by_key.insert(key.to_vec(), seq) {
+			self.key_by_seq.remove(&old_seq);
 		}
+		self.key_by_seq.insert(seq, key.to_vec());
 	}
 
-	fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
-		let columns = self.columns.read();
-		match columns.get(&col) {
-			None => None,
-			Some(map) => {
-				map.iter().find(|&(ref k, _)| k.starts_with(prefix)).map(|(_, v)| v.to_vec().into_boxed_slice())
+	fn forget(&mut self, key: &[u8]) {
+		if let Some(seq) = self.seq_by_key.remove(key) {
+			self.key_by_seq.remove(&seq);
+		}
+	}
+
+	fn pop_oldest(&mut self) -> Option<Vec<u8>> {
+		let seq = *self.key_by_seq.keys().next()?;
+		let key = self.key_by_seq.remove(&seq)?;
+		self.seq_by_key.remove(&key);
+		Some(key)
+	}
+}
+
+impl Column {
+	fn set_capacity_limit(&mut self, max_entries: usize) {
+		let mut write_order = WriteOrder { max_entries, ..Default::default() };
+		for key in self.entries.keys() {
+			write_order.touch(key);
+		}
+		self.write_order = Some(write_order);
+		self.evict();
+	}
+
+	fn evict(&mut self) {
+		if let Some(write_order) = self.write_order.as_mut() {
+			while self.entries.len() > write_order.max_entries {
+				match write_order.pop_oldest() {
+					Some(key) => self.entries.remove(&key),
+					None => break,
+				};
 			}
 		}
 	}
 
-	fn write(&self, transaction: DBTransaction) -> io::Result<()> {
-		let mut columns = self.columns.write();
-		let ops = transaction.ops;
-		for op in ops {
-			match op {
-				DBOp::Insert { col, key, value } => {
-					if let Some(col) = columns.get_mut(&col) {
-						col.insert(key.into_vec(), value);
-					}
+	fn apply(&mut self, op: DBOp) {
+		match op {
+			DBOp::Insert { key, value, .. } => {
+				if let Some(write_order) = self.write_order.as_mut() {
+					write_order.touch(&key);
 				}
-				DBOp::Delete { col, key } => {
-					if let Some(col) = columns.get_mut(&col) {
-						col.remove(&*key);
-					}
+				self.entries.insert(key.into_vec(), value);
+				self.evict();
+			}
+			DBOp::Delete { key, .. } => {
+				if let Some(write_order) = self.write_order.as_mut() {
+					write_order.forget(&key);
 				}
-				DBOp::DeletePrefix { col, prefix } => {
-					if let Some(col) = columns.get_mut(&col) {
-						use std::ops::Bound;
-						if prefix.is_empty() {
-							col.clear();
-						} else {
-							let start_range = Bound::Included(prefix.to_vec());
-							let keys: Vec<_> = if let Some(end_range) = kvdb::end_prefix(&prefix[..]) {
-								col.range((start_range, Bound::Excluded(end_range))).map(|(k, _)| k.clone()).collect()
-							} else {
-								col.range((start_range, Bound::Unbounded)).map(|(k, _)| k.clone()).collect()
-							};
-							for key in keys.into_iter() {
-								col.remove(&key[..]);
-							}
+				self.entries.remove(&*key);
+			}
+			DBOp::DeletePrefix { prefix, .. } => {
+				if prefix.is_empty() {
+					self.entries.clear();
+					if let Some(write_order) = self.write_order.as_mut() {
+						write_order.seq_by_key.clear();
+						write_order.key_by_seq.clear();
+					}
+				} else {
+					let keys: Vec<_> = self.range_with_prefix(&prefix).map(|(k, _)| k.clone()).collect();
+					for key in keys.into_iter() {
+						if let Some(write_order) = self.write_order.as_mut() {
+							write_order.forget(&key);
 						}
+						self.entries.remove(&key[..]);
 					}
 				}
 			}
 		}
+	}
+
+	fn range_with_prefix(&self, prefix: &[u8]) -> btree_map::Range<'_, Vec<u8>, DBValue> {
+		let end = kvdb::end_prefix(prefix);
+		let upper = match end {
+			Some(ref end) => Bound::Excluded(&end[..]),
+			None => Bound::Unbounded,
+		};
+		self.entries.range::<[u8], _>((Bound::Included(prefix), upper))
+	}
+}
+
+/// Iterator over a column that doesn't copy it.
+///
+/// The iterator holds a reference to the content of the column at the time of its creation and
+/// looks up the entry following the last returned key on every step.
+struct ColumnIter {
+	column: Arc<Column>,
+	last: Option<Vec<u8>>,
+	start: Vec<u8>,
+	end: Option<Vec<u8>>,
+}
+
+impl ColumnIter {
+	fn new(column: Arc<Column>, prefix: &[u8]) -> Self {
+		ColumnIter { column, last: None, start: prefix.to_vec(), end: kvdb::end_prefix(prefix) }
+	}
+}
+
+impl Iterator for ColumnIter {
+	type Item = (Box<[u8]>, Box<[u8]>);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let lower = match self.last {
+			Some(ref last) => Bound::Excluded(&last[..]),
+			None => Bound::Included(&self.start[..]),
+		};
+		let upper = match self.end {
+			Some(ref end) => Bound::Excluded(&end[..]),
+			None => Bound::Unbounded,
+		};
+		let (key, value) = self.column.entries.range::<[u8], _>((lower, upper)).next()?;
+		let item = (key.clone().into_boxed_slice(), value.clone().into_boxed_slice());
+		self.last = Some(key.clone());
+		Some(item)
+	}
+}
+
+impl KeyValueDB for InMemory {
+	fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+		match self.columns.get(col as usize) {
+			None => Err(io::Error::new(io::ErrorKind::Other, format!("No such column family: {:?}", col))),
+			Some(column) => Ok(column.read().entries.get(key).cloned()),
+		}
+	}
+
+	fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+		let column = self.columns.get(col as usize)?.read();
+		let mut range = column.entries.range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded));
+		range.next().filter(|(k, _)| k.starts_with(prefix)).map(|(_, v)| v.to_vec().into_boxed_slice())
+	}
+
+	fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+		let mut cols: Vec<_> = transaction.ops.iter().map(DBOp::col).collect();
+		cols.sort();
+		cols.dedup();
+		// Columns are locked in ascending order to avoid deadlocks between concurrent writers.
+		let mut columns: Vec<_> = cols
+			.into_iter()
+			.filter_map(|col| self.columns.get(col as usize).map(|column| (col, column.write())))
+			.collect();
+		if let Some(journal) = &self.journal {
+			journal.lock().append(&transaction.ops)?;
+		}
+		for op in transaction.ops {
+			if let Ok(idx) = columns.binary_search_by_key(&op.col(), |&(col, _)| col) {
+				Arc::make_mut(&mut columns[idx].1).apply(op);
+			}
+		}
 		Ok(())
 	}
 
 	fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
-		match self.columns.read().get(&col) {
-			Some(map) => Box::new(
-				// TODO: worth optimizing at all?
-				map.clone().into_iter().map(|(k, v)| (k.into_boxed_slice(), v.into_boxed_slice())),
-			),
+		match self.column(col) {
+			Some(column) => Box::new(ColumnIter::new(column, &[])),
 			None => Box::new(None.into_iter()),
 		}
 	}
@@ -105,13 +332,8 @@ impl KeyValueDB for InMemory {
 		col: u32,
 		prefix: &'a [u8],
 	) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
-		match self.columns.read().get(&col) {
-			Some(map) => Box::new(
-				map.clone()
-					.into_iter()
-					.filter(move |&(ref k, _)| k.starts_with(prefix))
-					.map(|(k, v)| (k.into_boxed_slice(), v.into_boxed_slice())),
-			),
+		match self.column(col) {
+			Some(column) => Box::new(ColumnIter::new(column, prefix)),
 			None => Box::new(None.into_iter()),
 		}
 	}
@@ -123,9 +345,11 @@ impl KeyValueDB for InMemory {
 
 #[cfg(test)]
 mod tests {
-	use super::create;
+	use super::{create, load};
+	use kvdb::KeyValueDB;
 	use kvdb_shared_tests as st;
-	use std::io;
+	use std::{fs::OpenOptions, io, io::Write};
+	use tempdir::TempDir;
 
 	#[test]
 	fn get_fails_with_non_existing_column() -> io::Result<()> {
@@ -168,4 +392,289 @@ mod tests {
 		let db = create(1);
 		st::test_complex(&db)
 	}
+
+	#[test]
+	fn model_conformance() -> io::Result<()> {
+		st::test_model_conformance(|| Ok(create(st::MODEL_NUM_COLUMNS)), Default::default())
+	}
+
+	#[test]
+	fn concurrent_stress() -> io::Result<()> {
+		let db = create(st::STRESS_NUM_COLUMNS);
+		st::test_concurrent_stress(std::sync::Arc::new(db), &Default::default())
+	}
+
+	#[test]
+	#[should_panic(expected = "keys_per_writer must be non-zero")]
+	fn concurrent_stress_without_keys() {
+		let db = create(st::STRESS_NUM_COLUMNS);
+		let config = st::StressConfig { keys_per_writer: 0, ..Default::default() };
+		let _ = st::test_concurrent_stress(std::sync::Arc::new(db), &config);
+	}
+
+	#[test]
+	fn restore_while_iterating() -> io::Result<()> {
+		let db = create(1);
+		let mut tx = db.transaction();
+		for i in 0..100u8 {
+			tx.put(0, &[i], &[i]);
+		}
+		db.write(tx)?;
+		// Restoring an in-memory database always fails, but must not disturb the iterators.
+		assert!(st::test_restore_while_iterating(std::sync::Arc::new(db), "", &Default::default())?.is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn save_and_load() -> io::Result<()> {
+		let tempdir = TempDir::new("kvdb-memorydb")?;
+		let path = tempdir.path().join("snapshot");
+		let db = create(2);
+		let mut tx = db.transaction();
+		tx.put(0, b"foo", b"bar");
+		tx.put(1, b"baz", b"qux");
+		tx.put(1, b"", b"empty");
+		db.write(tx)?;
+		db.save(&path)?;
+
+		let loaded = load(&path)?;
+		assert_eq!(loaded.get(0, b"foo")?.unwrap(), b"bar");
+		assert_eq!(loaded.get(1, b"baz")?.unwrap(), b"qux");
+		assert_eq!(loaded.get(1, b"")?.unwrap(), b"empty");
+		assert!(loaded.get(2, b"foo").is_err());
+		assert_eq!(loaded.iter(1).count(), 2);
+		Ok(())
+	}
+
+	#[test]
+	fn load_rejects_garbage() -> io::Result<()> {
+		let tempdir = TempDir::new("kvdb-memorydb")?;
+		let path = tempdir.path().join("snapshot");
+		std::fs::write(&path, b"not a snapshot")?;
+		assert_eq!(load(&path).err().unwrap().kind(), io::ErrorKind::InvalidData);
+		Ok(())
+	}
+
+	#[test]
+	fn journal_is_replayed() -> io::Result<()> {
+		let tempdir = TempDir::new("kvdb-memorydb")?;
+		let path = tempdir.path().join("journal");
+		{
+			let db = create(1).with_journal(&path)?;
+			let mut tx = db.transaction();
+			tx.put(0, b"key1", b"horse");
+			tx.put(0, b"key2", b"pig");
+			db.write(tx)?;
+			let mut tx = db.transaction();
+			tx.delete(0, b"key1");
+			tx.put(0, b"key3", b"cat");
+			db.write(tx)?;
+			let mut tx = db.transaction();
+			tx.delete_prefix(0, b"key3");
+			db.write(tx)?;
+		}
+
+		let db = create(1).with_journal(&path)?;
+		assert!(db.get(0, b"key1")?.is_none());
+		assert_eq!(db.get(0, b"key2")?.unwrap(), b"pig");
+		assert!(db.get(0, b"key3")?.is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn torn_journal_record_is_discarded() -> io::Result<()> {
+		let tempdir = TempDir::new("kvdb-memorydb")?;
+		let path = tempdir.path().join("journal");
+		{
+			let db = create(1).with_journal(&path)?;
+			let mut tx = db.transaction();
+			tx.put(0, b"key1", b"horse");
+			db.write(tx)?;
+		}
+		// Simulate a crash in the middle of an append.
+		OpenOptions::new().append(true).open(&path)?.write_all(&[42, 0, 0, 0, 0, 0, 0, 0, 1, 0])?;
+
+		let db = create(1).with_journal(&path)?;
+		assert_eq!(db.get(0, b"key1")?.unwrap(), b"horse");
+		let mut tx = db.transaction();
+		tx.put(0, b"key2", b"pig");
+		db.write(tx)?;
+		drop(db);
+
+		let db = create(1).with_journal(&path)?;
+		assert_eq!(db.get(0, b"key1")?.unwrap(), b"horse");
+		assert_eq!(db.get(0, b"key2")?.unwrap(), b"pig");
+		Ok(())
+	}
+
+	#[test]
+	fn journal_record_with_overflowing_length_is_discarded() -> io::Result<()> {
+		let tempdir = TempDir::new("kvdb-memorydb")?;
+		let path = tempdir.path().join("journal");
+		{
+			let db = create(1).with_journal(&path)?;
+			let mut tx = db.transaction();
+			tx.put(0, b"key1", b"horse");
+			db.write(tx)?;
+		}
+		let valid_len = std::fs::metadata(&path)?.len();
+		OpenOptions::new().append(true).open(&path)?.write_all(&u64::MAX.to_le_bytes())?;
+
+		let db = create(1).with_journal(&path)?;
+		assert_eq!(db.get(0, b"key1")?.unwrap(), b"horse");
+		assert_eq!(std::fs::metadata(&path)?.len(), valid_len);
+		Ok(())
+	}
+
+	#[test]
+	fn checkpoint_clears_journal() -> io::Result<()> {
+		let tempdir = TempDir::new("kvdb-memorydb")?;
+		let snapshot = tempdir.path().join("snapshot");
+		let journal = tempdir.path().join("journal");
+		{
+			let db = create(1).with_journal(&journal)?;
+			let mut tx = db.transaction();
+			tx.put(0, b"key1", b"horse");
+			db.write(tx)?;
+			db.checkpoint(&snapshot)?;
+			assert_eq!(std::fs::metadata(&journal)?.len(), 0);
+			let mut tx = db.transaction();
+			tx.put(0, b"key2", b"pig");
+			db.write(tx)?;
+		}
+
+		let db = load(&snapshot)?.with_journal(&journal)?;
+		assert_eq!(db.get(0, b"key1")?.unwrap(), b"horse");
+		assert_eq!(db.get(0, b"key2")?.unwrap(), b"pig");
+		Ok(())
+	}
+
+	#[test]
+	fn get_by_prefix_uses_ordered_lookup() -> io::Result<()> {
+		let db = create(1);
+		let mut tx = db.transaction();
+		tx.put(0, b"aa", b"1");
+		tx.put(0, b"abc", b"2");
+		tx.put(0, b"abd", b"3");
+		tx.put(0, b"b", b"4");
+		db.write(tx)?;
+		assert_eq!(&*db.get_by_prefix(0, b"ab").unwrap(), b"2");
+		assert_eq!(&*db.get_by_prefix(0, b"b").unwrap(), b"4");
+		assert!(db.get_by_prefix(0, b"ac").is_none());
+		assert!(db.get_by_prefix(0, b"c").is_none());
+		assert!(db.get_by_prefix(1, b"a").is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn snapshot_is_isolated() -> io::Result<()> {
+		let db = create(2);
+		let mut tx = db.transaction();
+		tx.put(0, b"key1", b"horse");
+		tx.put(1, b"key2", b"pig");
+		db.write(tx)?;
+
+		let snapshot = db.snapshot();
+		let mut tx = db.transaction();
+		tx.put(0, b"key1", b"cat");
+		tx.delete(1, b"key2");
+		db.write(tx)?;
+		let mut tx = snapshot.transaction();
+		tx.put(0, b"key3", b"dog");
+		snapshot.write(tx)?;
+
+		assert_eq!(db.get(0, b"key1")?.unwrap(), b"cat");
+		assert!(db.get(1, b"key2")?.is_none());
+		assert!(db.get(0, b"key3")?.is_none());
+		assert_eq!(snapshot.get(0, b"key1")?.unwrap(), b"horse");
+		assert_eq!(snapshot.get(1, b"key2")?.unwrap(), b"pig");
+		assert_eq!(snapshot.get(0, b"key3")?.unwrap(), b"dog");
+		Ok(())
+	}
+
+	#[test]
+	fn iterator_is_not_affected_by_writes() -> io::Result<()> {
+		let db = create(1);
+		let mut tx = db.transaction();
+		tx.put(0, b"key1", b"horse");
+		tx.put(0, b"key2", b"pig");
+		db.write(tx)?;
+
+		let mut iter = db.iter(0);
+		assert_eq!(&*iter.next().unwrap().0, b"key1");
+		let mut tx = db.transaction();
+		tx.delete(0, b"key2");
+		tx.put(0, b"key3", b"cat");
+		db.write(tx)?;
+		assert_eq!(&*iter.next().unwrap().0, b"key2");
+		assert!(iter.next().is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn capacity_limit_evicts_least_recently_written() -> io::Result<()> {
+		let db = create(2).with_capacity_limit(2);
+		let mut tx = db.transaction();
+		tx.put(0, b"key1", b"horse");
+		tx.put(0, b"key2", b"pig");
+		tx.put(1, b"key1", b"horse");
+		db.write(tx)?;
+		let mut tx = db.transaction();
+		tx.put(0, b"key1", b"cat");
+		tx.put(0, b"key3", b"dog");
+		db.write(tx)?;
+
+		assert_eq!(db.get(0, b"key1")?.unwrap(), b"cat");
+		assert!(db.get(0, b"key2")?.is_none());
+		assert_eq!(db.get(0, b"key3")?.unwrap(), b"dog");
+		assert_eq!(db.get(1, b"key1")?.unwrap(), b"horse");
+
+		let mut tx = db.transaction();
+		tx.delete(0, b"key1");
+		tx.put(0, b"key4", b"cow");
+		db.write(tx)?;
+		assert_eq!(db.iter(0).count(), 2);
+		assert_eq!(db.get(0, b"key3")?.unwrap(), b"dog");
+		Ok(())
+	}
+
+	#[test]
+	fn capacity_limit_applies_to_existing_entries() -> io::Result<()> {
+		let db = create(1);
+		let mut tx = db.transaction();
+		tx.put(0, b"key1", b"horse");
+		tx.put(0, b"key2", b"pig");
+		tx.put(0, b"key3", b"cat");
+		db.write(tx)?;
+
+		let db = db.with_capacity_limit(2);
+		assert!(db.get(0, b"key1")?.is_none());
+		assert_eq!(db.iter(0).count(), 2);
+		Ok(())
+	}
+
+	#[test]
+	fn concurrent_writes_to_different_columns() -> io::Result<()> {
+		let db = std::sync::Arc::new(create(4));
+		let handles: Vec<_> = (0..4u32)
+			.map(|col| {
+				let db = db.clone();
+				std::thread::spawn(move || {
+					for i in 0..100u32 {
+						let mut tx = db.transaction();
+						tx.put(col, &i.to_be_bytes(), &col.to_be_bytes());
+						tx.put((col + 1) % 4, b"shared", &i.to_be_bytes());
+						db.write(tx).unwrap();
+					}
+				})
+			})
+			.collect();
+		for handle in handles {
+			handle.join().unwrap();
+		}
+		for col in 0..4 {
+			assert_eq!(db.iter(col).count(), 101);
+		}
+		Ok(())
+	}
 }
@@ -0,0 +1,232 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! On-disk formats used to persist an `InMemory` database.
+//!
+//! A snapshot is a full dump of every column: a magic header followed by the number of
+//! columns and, for each column, its index, its number of entries and the entries themselves.
+//!
+//! A journal is an append-only sequence of records, one per written transaction. Every record
+//! is prefixed with the length of its payload so that a record torn by a crash in the middle of
+//! an append can be detected and discarded on replay.
+//!
+//! All integers are encoded as little endian, keys and values are prefixed with their length.
+
+use kvdb::{DBKey, DBOp, DBValue};
+use std::{
+	collections::BTreeMap,
+	fs::{self, File, OpenOptions},
+	io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+	path::Path,
+};
+
+/// Content of a single column of an in-memory database.
+pub(crate) type Entries = BTreeMap<Vec<u8>, DBValue>;
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"kvdbmem1";
+
+const OP_INSERT: u8 = 0;
+const OP_DELETE: u8 = 1;
+const OP_DELETE_PREFIX: u8 = 2;
+
+fn invalid_data(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_u32<W: Write>(w: &mut W, v: u32) -> io::Result<()> {
+	w.write_all(&v.to_le_bytes())
+}
+
+fn write_u64<W: Write>(w: &mut W, v: u64) -> io::Result<()> {
+	w.write_all(&v.to_le_bytes())
+}
+
+fn write_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> io::Result<()> {
+	write_u32(w, bytes.len() as u32)?;
+	w.write_all(bytes)
+}
+
+fn read_u8<R: Read>(r: &mut R) -> io::Result<u8> {
+	let mut buf = [0u8; 1];
+	r.read_exact(&mut buf)?;
+	Ok(buf[0])
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+	let mut buf = [0u8; 4];
+	r.read_exact(&mut buf)?;
+	Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+	let mut buf = [0u8; 8];
+	r.read_exact(&mut buf)?;
+	Ok(u64::from_le_bytes(buf))
+}
+
+fn read_bytes<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+	let len = read_u32(r)? as usize;
+	let mut buf = vec![0u8; len];
+	r.read_exact(&mut buf)?;
+	Ok(buf)
+}
+
+/// Write a snapshot of `columns` to `path`.
+///
+/// The snapshot is first written to a temporary file next to `path` which is then renamed,
+/// so an existing snapshot is never left half-overwritten.
+pub(crate) fn save_snapshot(path: &Path, columns: &[&Entries]) -> io::Result<()> {
+	let mut tmp_path = path.as_os_str().to_owned();
+	tmp_path.push(".tmp");
+	{
+		let mut w = BufWriter::new(File::create(&tmp_path)?);
+		w.write_all(SNAPSHOT_MAGIC)?;
+		write_u32(&mut w, columns.len() as u32)?;
+		for (col, map) in columns.iter().enumerate() {
+			write_u32(&mut w, col as u32)?;
+			write_u64(&mut w, map.len() as u64)?;
+			for (key, value) in map.iter() {
+				write_bytes(&mut w, key)?;
+				write_bytes(&mut w, value)?;
+			}
+		}
+		w.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+	}
+	fs::rename(&tmp_path, path)
+}
+
+/// Read a snapshot previously written by `save_snapshot`.
+pub(crate) fn load_snapshot(path: &Path) -> io::Result<Vec<Entries>> {
+	let mut r = BufReader::new(File::open(path)?);
+	let mut magic = [0u8; 8];
+	r.read_exact(&mut magic)?;
+	if &magic != SNAPSHOT_MAGIC {
+		return Err(invalid_data("Not an in-memory database snapshot"));
+	}
+	let num_cols = read_u32(&mut r)?;
+	let mut columns = Vec::with_capacity(num_cols as usize);
+	for idx in 0..num_cols {
+		if read_u32(&mut r)? != idx {
+			return Err(invalid_data("Unexpected column index in snapshot"));
+		}
+		let len = read_u64(&mut r)?;
+		let mut map = BTreeMap::new();
+		for _ in 0..len {
+			let key = read_bytes(&mut r)?;
+			let value = read_bytes(&mut r)?;
+			map.insert(key, value);
+		}
+		columns.push(map);
+	}
+	Ok(columns)
+}
+
+fn encode_ops(ops: &[DBOp]) -> io::Result<Vec<u8>> {
+	let mut payload = Vec::new();
+	write_u32(&mut payload, ops.len() as u32)?;
+	for op in ops {
+		match *op {
+			DBOp::Insert { col, ref key, ref value } => {
+				payload.push(OP_INSERT);
+				write_u32(&mut payload, col)?;
+				write_bytes(&mut payload, key)?;
+				write_bytes(&mut payload, value)?;
+			}
+			DBOp::Delete { col, ref key } => {
+				payload.push(OP_DELETE);
+				write_u32(&mut payload, col)?;
+				write_bytes(&mut payload, key)?;
+			}
+			DBOp::DeletePrefix { col, ref prefix } => {
+				payload.push(OP_DELETE_PREFIX);
+				write_u32(&mut payload, col)?;
+				write_bytes(&mut payload, prefix)?;
+			}
+		}
+	}
+	let mut record = Vec::with_capacity(payload.len() + 8);
+	write_u64(&mut record, payload.len() as u64)?;
+	record.extend_from_slice(&payload);
+	Ok(record)
+}
+
+fn decode_ops(mut payload: &[u8]) -> io::Result<Vec<DBOp>> {
+	let r = &mut payload;
+	let len = read_u32(r)?;
+	let mut ops = Vec::with_capacity(len as usize);
+	for _ in 0..len {
+		let tag = read_u8(r)?;
+		let col = read_u32(r)?;
+		let key = DBKey::from_vec(read_bytes(r)?);
+		let op = match tag {
+			OP_INSERT => DBOp::Insert { col, key, value: read_bytes(r)? },
+			OP_DELETE => DBOp::Delete { col, key },
+			OP_DELETE_PREFIX => DBOp::DeletePrefix { col, prefix: key },
+			_ => return Err(invalid_data("Unknown journal operation")),
+		};
+		ops.push(op);
+	}
+	if !r.is_empty() {
+		return Err(invalid_data("Trailing bytes in journal record"));
+	}
+	Ok(ops)
+}
+
+/// Append-only log of the transactions written to an `InMemory` database.
+pub(crate) struct Journal {
+	file: File,
+}
+
+impl Journal {
+	/// Open the journal at `path`, creating it if it doesn't exist.
+	///
+	/// Every complete record already in the journal is passed to `replay` in the order it was
+	/// written. A torn record at the end of the file is discarded and truncated away.
+	pub(crate) fn open<F>(path: &Path, mut replay: F) -> io::Result<Journal>
+	where
+		F: FnMut(Vec<DBOp>),
+	{
+		let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+		let file_len = file.metadata()?.len();
+		let mut valid_len = 0;
+		{
+			let mut r = BufReader::new(&mut file);
+			while valid_len + 8 <= file_len {
+				let len = read_u64(&mut r)?;
+				// a corrupt length may overflow, which is treated as a torn record as well.
+				let end = match (valid_len + 8).checked_add(len) {
+					Some(end) if end <= file_len => end,
+					_ => break,
+				};
+				let mut payload = vec![0u8; len as usize];
+				r.read_exact(&mut payload)?;
+				replay(decode_ops(&payload)?);
+				valid_len = end;
+			}
+		}
+		if valid_len != file_len {
+			file.set_len(valid_len)?;
+		}
+		file.seek(SeekFrom::Start(valid_len))?;
+		Ok(Journal { file })
+	}
+
+	/// Append a transaction to the journal.
+	pub(crate) fn append(&mut self, ops: &[DBOp]) -> io::Result<()> {
+		let record = encode_ops(ops)?;
+		self.file.write_all(&record)?;
+		self.file.flush()
+	}
+
+	/// Remove every record from the journal.
+	pub(crate) fn clear(&mut self) -> io::Result<()> {
+		self.file.set_len(0)?;
+		self.file.seek(SeekFrom::Start(0))?;
+		Ok(())
+	}
+}
@@ -837,6 +837,44 @@ mod tests {
 		st::test_complex(&db)
 	}
 
+	#[test]
+	fn model_conformance() -> io::Result<()> {
+		st::test_model_conformance(|| create(st::MODEL_NUM_COLUMNS), Default::default())
+	}
+
+	#[test]
+	fn concurrent_stress() -> io::Result<()> {
+		let db = create(st::STRESS_NUM_COLUMNS)?;
+		st::test_concurrent_stress(std::sync::Arc::new(db), &Default::default())
+	}
+
+	#[test]
+	fn restore_while_iterating() -> io::Result<()> {
+		let primary = TempDir::new("")?;
+		let backup = TempDir::new("")?;
+		let config = DatabaseConfig::with_columns(1);
+		{
+			let backup_db = Database::open(&config, backup.path().to_str().expect("tempdir path is valid unicode"))?;
+			let mut transaction = backup_db.transaction();
+			transaction.put(0, b"restored", b"horse");
+			backup_db.write(transaction)?;
+		}
+
+		let db = Database::open(&config, primary.path().to_str().expect("tempdir path is valid unicode"))?;
+		let mut transaction = db.transaction();
+		for i in 0..100u8 {
+			transaction.put(0, &[i], &[i]);
+		}
+		db.write(transaction)?;
+
+		let db = std::sync::Arc::new(db);
+		let backup_path = backup.path().to_str().expect("tempdir path is valid unicode");
+		st::test_restore_while_iterating(db.clone(), backup_path, &Default::default())??;
+		assert_eq!(&*db.get(0, b"restored")?.unwrap(), b"horse");
+		assert!(db.get(0, &[0])?.is_none());
+		Ok(())
+	}
+
 	#[test]
 	fn stats() -> io::Result<()> {
 		let db = create(st::IO_STATS_NUM_COLUMNS)?;
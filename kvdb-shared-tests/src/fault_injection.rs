@@ -0,0 +1,414 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A `KeyValueDB` wrapper injecting faults into the operations of another database.
+//!
+//! Faults are drawn from a pseudo-random generator seeded by the caller, so a failing run can
+//! be reproduced exactly by reusing its seed with the same sequence of operations. Latency is
+//! drawn from a separate generator and doesn't affect which operations fail.
+
+use kvdb::{DBTransaction, DBValue, IoStats, IoStatsKind, KeyValueDB};
+use parity_util_mem::{MallocSizeOf, MallocSizeOfOps};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+	io,
+	sync::{
+		atomic::{AtomicBool, AtomicU64, Ordering},
+		Mutex, MutexGuard,
+	},
+	thread,
+	time::Duration,
+};
+
+/// Operations of a `KeyValueDB` that can be made to fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+	/// `KeyValueDB::get`, and `KeyValueDB::has_key` which is built on it.
+	Read,
+	/// `KeyValueDB::write`.
+	Write,
+	/// `KeyValueDB::restore`.
+	Restore,
+}
+
+/// How much of a transaction reaches the database when a crash is simulated during a write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashMode {
+	/// None of the operations of the transaction are applied.
+	Drop,
+	/// A random strict prefix of the operations of the transaction is applied.
+	Truncate,
+}
+
+/// Returns `true` with the given probability, without drawing from `rng` if it's zero.
+fn chance(rng: &mut StdRng, probability: f64) -> bool {
+	probability > 0.0 && rng.gen::<f64>() < probability
+}
+
+/// Returns an IO error with the given message.
+///
+/// Built with `ErrorKind::Other` rather than `io::Error::other` on purpose, to keep building with
+/// the same compilers as the rest of the workspace.
+fn other_error(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, msg)
+}
+
+#[derive(Default)]
+struct Faults {
+	read_error: f64,
+	write_error: f64,
+	restore_error: f64,
+	fail_nth: Vec<(Operation, u64)>,
+	crash: Option<(f64, CrashMode)>,
+	latency: Option<(Duration, Duration)>,
+}
+
+struct State {
+	/// Generator of faults.
+	rng: StdRng,
+	/// Generator of latency, kept apart so that enabling latency doesn't change the faults.
+	latency_rng: StdRng,
+	reads: u64,
+	writes: u64,
+	restores: u64,
+}
+
+/// A `KeyValueDB` forwarding to another database while injecting faults.
+///
+/// Faults are configured with the builder methods:
+///
+/// ```
+/// # use kvdb_shared_tests::{CrashMode, FaultInjectingDB, Operation};
+/// # use std::time::Duration;
+/// # fn wrap<DB: kvdb::KeyValueDB>(inner: DB) -> FaultInjectingDB<DB> {
+/// FaultInjectingDB::new(inner, 42)
+///     .with_error_probability(Operation::Read, 0.01)
+///     .with_nth_failure(Operation::Write, 3)
+///     .with_crash_probability(0.05, CrashMode::Truncate)
+///     .with_latency(Duration::from_millis(1), Duration::from_millis(5))
+/// # }
+/// ```
+///
+/// After a simulated crash every operation fails until [`FaultInjectingDB::recover`] is called,
+/// which models restarting the process on top of whatever reached the underlying database.
+/// Operations which can't report an error (`get_by_prefix` and iteration) return nothing instead.
+pub struct FaultInjectingDB<DB> {
+	inner: DB,
+	faults: Faults,
+	state: Mutex<State>,
+	crashed: AtomicBool,
+	injected: AtomicU64,
+}
+
+impl<DB: KeyValueDB> FaultInjectingDB<DB> {
+	/// Wrap `inner` without injecting any fault yet. `seed` initializes the generator used to
+	/// draw faults.
+	pub fn new(inner: DB, seed: u64) -> Self {
+		FaultInjectingDB {
+			inner,
+			faults: Faults::default(),
+			state: Mutex::new(State {
+				rng: StdRng::seed_from_u64(seed),
+				latency_rng: StdRng::seed_from_u64(!seed),
+				reads: 0,
+				writes: 0,
+				restores: 0,
+			}),
+			crashed: AtomicBool::new(false),
+			injected: AtomicU64::new(0),
+		}
+	}
+
+	/// Make every call to `op` fail with an IO error with the given probability.
+	/// A write failing this way leaves the database untouched.
+	pub fn with_error_probability(mut self, op: Operation, probability: f64) -> Self {
+		match op {
+			Operation::Read => self.faults.read_error = probability,
+			Operation::Write => self.faults.write_error = probability,
+			Operation::Restore => self.faults.restore_error = probability,
+		}
+		self
+	}
+
+	/// Make the `n`th call (counting from 1) to `op` fail with an IO error.
+	pub fn with_nth_failure(mut self, op: Operation, n: u64) -> Self {
+		self.faults.fail_nth.push((op, n));
+		self
+	}
+
+	/// Simulate a crash during a write with the given probability.
+	/// `mode` controls which part of the transaction reaches the underlying database.
+	pub fn with_crash_probability(mut self, probability: f64, mode: CrashMode) -> Self {
+		self.faults.crash = Some((probability, mode));
+		self
+	}
+
+	/// Delay every operation by a duration drawn uniformly in `min..=max`.
+	pub fn with_latency(mut self, min: Duration, max: Duration) -> Self {
+		assert!(min <= max, "minimum latency must not exceed maximum latency");
+		self.faults.latency = Some((min, max));
+		self
+	}
+
+	/// Whether a crash has been simulated since the database was created or last recovered.
+	pub fn is_crashed(&self) -> bool {
+		self.crashed.load(Ordering::SeqCst)
+	}
+
+	/// Resume operations after a simulated crash.
+	pub fn recover(&self) {
+		self.crashed.store(false, Ordering::SeqCst);
+	}
+
+	/// Number of faults (errors and crashes) injected so far.
+	pub fn injected_faults(&self) -> u64 {
+		self.injected.load(Ordering::SeqCst)
+	}
+
+	/// Get a reference to the wrapped database.
+	pub fn inner(&self) -> &DB {
+		&self.inner
+	}
+
+	/// Unwrap the wrapped database.
+	pub fn into_inner(self) -> DB {
+		self.inner
+	}
+
+	fn state(&self) -> MutexGuard<'_, State> {
+		self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn fault(&self, msg: &str) -> io::Error {
+		self.injected.fetch_add(1, Ordering::SeqCst);
+		other_error(msg)
+	}
+
+	fn delay(&self) {
+		if let Some((min, max)) = self.faults.latency {
+			let extra = (max - min).as_micros() as u64;
+			let delay = min + Duration::from_micros(self.state().latency_rng.gen_range(0, extra + 1));
+			thread::sleep(delay);
+		}
+	}
+
+	/// Delay an operation which can't report an error, returning `false` if the database crashed.
+	fn check_infallible(&self) -> bool {
+		self.delay();
+		!self.is_crashed()
+	}
+
+	/// Check whether a call to `op` should fail, counting it.
+	fn check(&self, op: Operation) -> io::Result<()> {
+		self.delay();
+		if self.is_crashed() {
+			return Err(other_error("Injected fault: database crashed"));
+		}
+		let mut state = self.state();
+		let (count, probability) = match op {
+			Operation::Read => (&mut state.reads, self.faults.read_error),
+			Operation::Write => (&mut state.writes, self.faults.write_error),
+			Operation::Restore => (&mut state.restores, self.faults.restore_error),
+		};
+		*count += 1;
+		let n = *count;
+		if self.faults.fail_nth.contains(&(op, n)) || chance(&mut state.rng, probability) {
+			return Err(self.fault("Injected fault: IO error"));
+		}
+		Ok(())
+	}
+}
+
+impl<DB: KeyValueDB> KeyValueDB for FaultInjectingDB<DB> {
+	fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+		self.check(Operation::Read)?;
+		self.inner.get(col, key)
+	}
+
+	fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+		if !self.check_infallible() {
+			return None;
+		}
+		self.inner.get_by_prefix(col, prefix)
+	}
+
+	fn write(&self, mut transaction: DBTransaction) -> io::Result<()> {
+		self.check(Operation::Write)?;
+		if let Some((probability, mode)) = self.faults.crash {
+			let mut state = self.state();
+			if chance(&mut state.rng, probability) {
+				let keep = match mode {
+					CrashMode::Drop => 0,
+					CrashMode::Truncate => state.rng.gen_range(0, transaction.ops.len().max(1)),
+				};
+				drop(state);
+				transaction.ops.truncate(keep);
+				if keep > 0 {
+					self.inner.write(transaction)?;
+				}
+				self.crashed.store(true, Ordering::SeqCst);
+				return Err(self.fault("Injected fault: crashed during write"));
+			}
+		}
+		self.inner.write(transaction)
+	}
+
+	fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+		if !self.check_infallible() {
+			return Box::new(std::iter::empty());
+		}
+		self.inner.iter(col)
+	}
+
+	fn iter_with_prefix<'a>(
+		&'a self,
+		col: u32,
+		prefix: &'a [u8],
+	) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+		if !self.check_infallible() {
+			return Box::new(std::iter::empty());
+		}
+		self.inner.iter_with_prefix(col, prefix)
+	}
+
+	fn restore(&self, new_db: &str) -> io::Result<()> {
+		self.check(Operation::Restore)?;
+		self.inner.restore(new_db)
+	}
+
+	fn io_stats(&self, kind: IoStatsKind) -> IoStats {
+		self.inner.io_stats(kind)
+	}
+}
+
+impl<DB: KeyValueDB> MallocSizeOf for FaultInjectingDB<DB> {
+	fn size_of(&self, ops: &mut MallocSizeOfOps) -> usize {
+		self.inner.size_of(ops)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{CrashMode, FaultInjectingDB, Operation};
+	use kvdb::KeyValueDB;
+	use std::{io, time::Duration};
+
+	fn write_keys(db: &dyn KeyValueDB, n: u8) -> Vec<bool> {
+		(0..n)
+			.map(|i| {
+				let mut tx = db.transaction();
+				tx.put(0, &[i], &[i]);
+				db.write(tx).is_ok()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn no_faults_by_default() -> io::Result<()> {
+		let db = FaultInjectingDB::new(kvdb_memorydb::create(1), 0);
+		assert!(write_keys(&db, 100).into_iter().all(|ok| ok));
+		assert_eq!(db.get(0, &[42])?.unwrap(), vec![42]);
+		assert_eq!(db.injected_faults(), 0);
+		Ok(())
+	}
+
+	#[test]
+	fn nth_failure() -> io::Result<()> {
+		let db = FaultInjectingDB::new(kvdb_memorydb::create(1), 0).with_nth_failure(Operation::Write, 2);
+		assert_eq!(write_keys(&db, 3), vec![true, false, true]);
+		assert!(db.get(0, &[1])?.is_none());
+		assert_eq!(db.injected_faults(), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn errors_are_reproducible() {
+		let outcomes = |seed| {
+			let db =
+				FaultInjectingDB::new(kvdb_memorydb::create(1), seed).with_error_probability(Operation::Write, 0.5);
+			write_keys(&db, 64)
+		};
+		assert_eq!(outcomes(7), outcomes(7));
+		assert_ne!(outcomes(7), outcomes(8));
+		assert!(outcomes(7).contains(&true));
+		assert!(outcomes(7).contains(&false));
+	}
+
+	#[test]
+	fn latency_does_not_change_errors() {
+		let outcomes = |latency| {
+			let mut db =
+				FaultInjectingDB::new(kvdb_memorydb::create(1), 7).with_error_probability(Operation::Write, 0.5);
+			if latency {
+				db = db.with_latency(Duration::from_micros(0), Duration::from_micros(10));
+			}
+			write_keys(&db, 64)
+		};
+		assert_eq!(outcomes(true), outcomes(false));
+	}
+
+	#[test]
+	fn read_errors() {
+		let db = FaultInjectingDB::new(kvdb_memorydb::create(1), 0).with_error_probability(Operation::Read, 1.0);
+		assert!(db.get(0, b"foo").is_err());
+		assert!(db.has_key(0, b"foo").is_err());
+		assert_eq!(db.injected_faults(), 2);
+	}
+
+	#[test]
+	fn crash_drops_transaction() -> io::Result<()> {
+		let db = FaultInjectingDB::new(kvdb_memorydb::create(1), 0).with_crash_probability(1.0, CrashMode::Drop);
+		let mut tx = db.transaction();
+		tx.put(0, b"foo", b"bar");
+		assert!(db.write(tx).is_err());
+		assert!(db.is_crashed());
+		assert!(db.get(0, b"foo").is_err());
+
+		db.recover();
+		assert!(db.get(0, b"foo")?.is_none());
+		Ok(())
+	}
+
+	#[test]
+	fn crash_hides_data_from_infallible_reads() -> io::Result<()> {
+		let db = FaultInjectingDB::new(kvdb_memorydb::create(1), 0).with_crash_probability(1.0, CrashMode::Drop);
+		let mut tx = db.transaction();
+		tx.put(0, b"foo", b"bar");
+		db.inner().write(tx)?;
+		assert!(db.write(db.transaction()).is_err());
+		assert!(db.is_crashed());
+		assert!(db.get_by_prefix(0, b"f").is_none());
+		assert_eq!(db.iter(0).count(), 0);
+		assert_eq!(db.iter_with_prefix(0, b"f").count(), 0);
+
+		db.recover();
+		assert_eq!(db.get_by_prefix(0, b"f").as_deref(), Some(&b"bar"[..]));
+		assert_eq!(db.iter(0).count(), 1);
+		assert_eq!(db.iter_with_prefix(0, b"f").count(), 1);
+		Ok(())
+	}
+
+	#[test]
+	fn crash_truncates_transaction() -> io::Result<()> {
+		let db = FaultInjectingDB::new(kvdb_memorydb::create(1), 3).with_crash_probability(1.0, CrashMode::Truncate);
+		let mut tx = db.transaction();
+		for i in 0..100u8 {
+			tx.put(0, &[i], &[i]);
+		}
+		assert!(db.write(tx).is_err());
+		db.recover();
+
+		let written = db.iter(0).count();
+		assert!(written < 100);
+		// Only a prefix of the transaction made it to the database.
+		for i in 0..100u8 {
+			assert_eq!(db.get(0, &[i])?.is_some(), (i as usize) < written);
+		}
+		Ok(())
+	}
+}
@@ -8,9 +8,17 @@
 
 //! Shared tests for kvdb functionality, to be executed against actual implementations.
 
+mod fault_injection;
+mod model;
+mod stress;
+
 use kvdb::{IoStatsKind, KeyValueDB};
 use std::io;
 
+pub use fault_injection::{CrashMode, FaultInjectingDB, Operation};
+pub use model::{test_model_conformance, ModelTestConfig, Scenario, Step, WriteOp, MODEL_NUM_COLUMNS};
+pub use stress::{test_concurrent_stress, test_restore_while_iterating, StressConfig, STRESS_NUM_COLUMNS};
+
 /// A test for `KeyValueDB::get`.
 pub fn test_put_and_get(db: &dyn KeyValueDB) -> io::Result<()> {
 	let key1 = b"key1";
@@ -0,0 +1,318 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Model-based property test for `KeyValueDB` implementations.
+//!
+//! Random sequences of writes and reads are applied both to a database and to a reference model
+//! made of one `BTreeMap` per column, and every read is checked against the model. When a sequence
+//! fails, it is minimized before being reported.
+
+use kvdb::KeyValueDB;
+use quickcheck::{Arbitrary, Gen, StdGen};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{collections::BTreeMap, fmt, io};
+
+/// The number of columns required to run `test_model_conformance`.
+pub const MODEL_NUM_COLUMNS: u32 = 3;
+
+/// Keys are drawn from a small alphabet so that writes, deletions and prefixes often overlap.
+const KEY_BYTES: [u8; 4] = [0, 1, 2, 255];
+const MAX_KEY_LEN: usize = 3;
+
+/// A single operation of a write transaction.
+#[derive(Clone, Debug)]
+pub enum WriteOp {
+	Put { col: u32, key: Vec<u8>, value: Vec<u8> },
+	Delete { col: u32, key: Vec<u8> },
+	DeletePrefix { col: u32, prefix: Vec<u8> },
+}
+
+/// A step of a generated scenario.
+#[derive(Clone, Debug)]
+pub enum Step {
+	/// Write a transaction made of the given operations.
+	Write(Vec<WriteOp>),
+	/// Check `KeyValueDB::get`.
+	Get { col: u32, key: Vec<u8> },
+	/// Check `KeyValueDB::get_by_prefix`.
+	GetByPrefix { col: u32, prefix: Vec<u8> },
+	/// Check `KeyValueDB::iter`.
+	Iter { col: u32 },
+	/// Check `KeyValueDB::iter_with_prefix`.
+	IterWithPrefix { col: u32, prefix: Vec<u8> },
+}
+
+/// A sequence of steps applied to a fresh database.
+#[derive(Clone)]
+pub struct Scenario(pub Vec<Step>);
+
+impl fmt::Debug for Scenario {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "[")?;
+		for step in &self.0 {
+			writeln!(f, "\t{:?},", step)?;
+		}
+		write!(f, "]")
+	}
+}
+
+fn arbitrary_col<G: Gen>(g: &mut G) -> u32 {
+	g.gen_range(0, MODEL_NUM_COLUMNS)
+}
+
+fn arbitrary_key<G: Gen>(g: &mut G) -> Vec<u8> {
+	let len = g.gen_range(0, MAX_KEY_LEN + 1);
+	(0..len).map(|_| KEY_BYTES[g.gen_range(0, KEY_BYTES.len())]).collect()
+}
+
+fn arbitrary_value<G: Gen>(g: &mut G) -> Vec<u8> {
+	let len = g.gen_range(0, 4);
+	(0..len).map(|_| g.gen()).collect()
+}
+
+impl Arbitrary for WriteOp {
+	fn arbitrary<G: Gen>(g: &mut G) -> Self {
+		let col = arbitrary_col(g);
+		match g.gen_range(0, 10) {
+			0..=5 => WriteOp::Put { col, key: arbitrary_key(g), value: arbitrary_value(g) },
+			6..=8 => WriteOp::Delete { col, key: arbitrary_key(g) },
+			_ => WriteOp::DeletePrefix { col, prefix: arbitrary_key(g) },
+		}
+	}
+}
+
+impl Arbitrary for Step {
+	fn arbitrary<G: Gen>(g: &mut G) -> Self {
+		let col = arbitrary_col(g);
+		match g.gen_range(0, 10) {
+			0..=4 => {
+				let len = g.gen_range(1, 5);
+				Step::Write((0..len).map(|_| WriteOp::arbitrary(g)).collect())
+			}
+			5 | 6 => Step::Get { col, key: arbitrary_key(g) },
+			7 => Step::GetByPrefix { col, prefix: arbitrary_key(g) },
+			8 => Step::Iter { col },
+			_ => Step::IterWithPrefix { col, prefix: arbitrary_key(g) },
+		}
+	}
+
+	fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+		match self {
+			Step::Write(ops) => Box::new(ops.shrink().filter(|ops| !ops.is_empty()).map(Step::Write)),
+			_ => Box::new(std::iter::empty()),
+		}
+	}
+}
+
+impl Arbitrary for Scenario {
+	fn arbitrary<G: Gen>(g: &mut G) -> Self {
+		let len = g.gen_range(0, g.size() + 1);
+		Scenario((0..len).map(|_| Step::arbitrary(g)).collect())
+	}
+
+	fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+		Box::new(self.0.shrink().map(Scenario))
+	}
+}
+
+/// Configuration of `test_model_conformance`.
+#[derive(Clone, Debug)]
+pub struct ModelTestConfig {
+	/// Number of scenarios to run.
+	pub cases: usize,
+	/// Maximum number of steps of a scenario.
+	pub max_steps: usize,
+	/// Seed of the scenario generator. A random seed is used if `None`; it is reported on failure.
+	pub seed: Option<u64>,
+}
+
+impl Default for ModelTestConfig {
+	fn default() -> Self {
+		ModelTestConfig { cases: 100, max_steps: 50, seed: None }
+	}
+}
+
+type Model = Vec<BTreeMap<Vec<u8>, Vec<u8>>>;
+
+fn with_prefix<'a>(
+	map: &'a BTreeMap<Vec<u8>, Vec<u8>>,
+	prefix: &'a [u8],
+) -> impl Iterator<Item = (&'a Vec<u8>, &'a Vec<u8>)> {
+	map.iter().filter(move |(k, _)| k.starts_with(prefix))
+}
+
+fn collect(iter: impl Iterator<Item = (Box<[u8]>, Box<[u8]>)>) -> Vec<(Vec<u8>, Vec<u8>)> {
+	iter.map(|(k, v)| (k.into_vec(), v.into_vec())).collect()
+}
+
+fn owned<'a>(iter: impl Iterator<Item = (&'a Vec<u8>, &'a Vec<u8>)>) -> Vec<(Vec<u8>, Vec<u8>)> {
+	iter.map(|(k, v)| (k.clone(), v.clone())).collect()
+}
+
+/// Apply `scenario` to `db` and the model, returning a description of the first mismatch.
+fn run(db: &dyn KeyValueDB, scenario: &Scenario) -> Result<(), String> {
+	let mut model: Model = vec![BTreeMap::new(); MODEL_NUM_COLUMNS as usize];
+	let io_err = |step: usize, e: io::Error| format!("step {}: unexpected IO error: {}", step, e);
+	for (i, step) in scenario.0.iter().enumerate() {
+		match step {
+			Step::Write(ops) => {
+				let mut transaction = db.transaction();
+				for op in ops {
+					match op {
+						WriteOp::Put { col, key, value } => {
+							transaction.put(*col, key, value);
+							model[*col as usize].insert(key.clone(), value.clone());
+						}
+						WriteOp::Delete { col, key } => {
+							transaction.delete(*col, key);
+							model[*col as usize].remove(key);
+						}
+						WriteOp::DeletePrefix { col, prefix } => {
+							transaction.delete_prefix(*col, prefix);
+							model[*col as usize].retain(|k, _| !k.starts_with(prefix));
+						}
+					}
+				}
+				db.write(transaction).map_err(|e| io_err(i, e))?;
+			}
+			Step::Get { col, key } => {
+				let actual = db.get(*col, key).map_err(|e| io_err(i, e))?;
+				let expected = model[*col as usize].get(key).cloned();
+				if actual != expected {
+					return Err(format!("step {}: get returned {:?}, expected {:?}", i, actual, expected));
+				}
+			}
+			Step::GetByPrefix { col, prefix } => {
+				let actual = db.get_by_prefix(*col, prefix).map(|v| v.into_vec());
+				let expected = with_prefix(&model[*col as usize], prefix).next().map(|(_, v)| v.clone());
+				if actual != expected {
+					return Err(format!("step {}: get_by_prefix returned {:?}, expected {:?}", i, actual, expected));
+				}
+			}
+			Step::Iter { col } => {
+				let actual = collect(db.iter(*col));
+				let expected = owned(model[*col as usize].iter());
+				if actual != expected {
+					return Err(format!("step {}: iter returned {:?}, expected {:?}", i, actual, expected));
+				}
+			}
+			Step::IterWithPrefix { col, prefix } => {
+				let actual = collect(db.iter_with_prefix(*col, prefix));
+				let expected = owned(with_prefix(&model[*col as usize], prefix));
+				if actual != expected {
+					return Err(format!("step {}: iter_with_prefix returned {:?}, expected {:?}", i, actual, expected));
+				}
+			}
+		}
+	}
+	for (col, map) in model.iter().enumerate() {
+		let actual = collect(db.iter(col as u32));
+		if actual != owned(map.iter()) {
+			return Err(format!("final content of column {} differs from the model: {:?}", col, actual));
+		}
+	}
+	Ok(())
+}
+
+/// A model-based property test for `KeyValueDB`.
+///
+/// `create_db` must return a fresh, empty database with at least `MODEL_NUM_COLUMNS` columns; it
+/// is called once for every scenario and every minimization attempt.
+///
+/// Panics with the minimized failing scenario and the seed used if the database diverges from
+/// the model.
+pub fn test_model_conformance<DB, F>(create_db: F, config: ModelTestConfig) -> io::Result<()>
+where
+	DB: KeyValueDB,
+	F: Fn() -> io::Result<DB>,
+{
+	let seed = config.seed.unwrap_or_else(|| rand::thread_rng().gen());
+	let mut gen = StdGen::new(StdRng::seed_from_u64(seed), config.max_steps);
+	let check = |scenario: &Scenario| -> io::Result<Result<(), String>> { Ok(run(&create_db()?, scenario)) };
+
+	for _ in 0..config.cases {
+		let scenario = Scenario::arbitrary(&mut gen);
+		let mut failure = match check(&scenario)? {
+			Ok(()) => continue,
+			Err(failure) => (scenario, failure),
+		};
+		// Greedily take the first smaller scenario that still fails until none does.
+		'minimize: loop {
+			for candidate in failure.0.shrink() {
+				if let Err(error) = check(&candidate)? {
+					failure = (candidate, error);
+					continue 'minimize;
+				}
+			}
+			break;
+		}
+		panic!("KeyValueDB diverged from the model (seed {}): {}\nscenario: {:?}", seed, failure.1, failure.0);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{test_model_conformance, ModelTestConfig, MODEL_NUM_COLUMNS};
+	use kvdb::{DBTransaction, DBValue, KeyValueDB};
+	use parity_util_mem::MallocSizeOf;
+	use std::io;
+
+	/// A database that forgets deletions by prefix.
+	#[derive(MallocSizeOf)]
+	struct NoDeletePrefix(kvdb_memorydb::InMemory);
+
+	impl KeyValueDB for NoDeletePrefix {
+		fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+			self.0.get(col, key)
+		}
+
+		fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> Option<Box<[u8]>> {
+			self.0.get_by_prefix(col, prefix)
+		}
+
+		fn write(&self, mut transaction: DBTransaction) -> io::Result<()> {
+			transaction.ops.retain(|op| !matches!(op, kvdb::DBOp::DeletePrefix { .. }));
+			self.0.write(transaction)
+		}
+
+		fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+			self.0.iter(col)
+		}
+
+		fn iter_with_prefix<'a>(
+			&'a self,
+			col: u32,
+			prefix: &'a [u8],
+		) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+			self.0.iter_with_prefix(col, prefix)
+		}
+
+		fn restore(&self, new_db: &str) -> io::Result<()> {
+			self.0.restore(new_db)
+		}
+	}
+
+	#[test]
+	fn divergence_is_minimized() {
+		let result = std::panic::catch_unwind(|| {
+			let config = ModelTestConfig { seed: Some(1), ..Default::default() };
+			test_model_conformance(|| Ok(NoDeletePrefix(kvdb_memorydb::create(MODEL_NUM_COLUMNS))), config)
+		});
+		let message = *result.unwrap_err().downcast::<String>().unwrap();
+		assert!(message.contains("seed 1"));
+		// The minimized scenario is a put followed by a delete by prefix of the key, after which the
+		// final content of the column differs from the model.
+		let scenario = message.split("scenario: ").nth(1).unwrap();
+		let steps: Vec<_> = scenario.lines().filter(|line| line.starts_with('\t')).collect();
+		assert_eq!(steps.len(), 2, "{}", message);
+		assert!(steps[0].contains("Put"), "{}", message);
+		assert!(steps[1].contains("DeletePrefix"), "{}", message);
+		assert!(message.contains("final content of column"), "{}", message);
+	}
+}
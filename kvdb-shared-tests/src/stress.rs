@@ -0,0 +1,290 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Multi-threaded stress tests for `KeyValueDB` implementations.
+//!
+//! Every writer thread owns a set of keys and repeatedly writes an increasing counter to all of
+//! them, in every column, with one transaction per counter value. Before writing a counter, a
+//! writer publishes it as started; once the write returns, it publishes it as committed. This lets
+//! concurrent readers and iterators check that every value they observe is bounded by what was
+//! committed before the read began and what was started after it ended.
+
+use kvdb::KeyValueDB;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+	any::Any,
+	convert::TryInto,
+	io, panic,
+	sync::{
+		atomic::{AtomicBool, AtomicU64, Ordering},
+		mpsc, Arc,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+/// The number of columns required to run the stress tests.
+pub const STRESS_NUM_COLUMNS: u32 = 2;
+
+/// Parameters of the stress tests.
+#[derive(Clone, Debug)]
+pub struct StressConfig {
+	/// Number of threads writing transactions.
+	pub writers: usize,
+	/// Number of threads reading single keys.
+	pub readers: usize,
+	/// Number of threads holding iterators across writes.
+	pub iterators: usize,
+	/// Number of keys owned by every writer, must be non-zero.
+	pub keys_per_writer: u32,
+	/// How long the threads keep running.
+	pub duration: Duration,
+	/// How long to wait for the threads after `duration` has elapsed before reporting a deadlock.
+	pub deadlock_timeout: Duration,
+}
+
+impl Default for StressConfig {
+	fn default() -> Self {
+		StressConfig {
+			writers: 4,
+			readers: 4,
+			iterators: 2,
+			keys_per_writer: 16,
+			duration: Duration::from_secs(1),
+			deadlock_timeout: Duration::from_secs(30),
+		}
+	}
+}
+
+struct Progress {
+	started: Vec<AtomicU64>,
+	committed: Vec<AtomicU64>,
+	stop: AtomicBool,
+}
+
+fn key(writer: usize, index: u32) -> [u8; 8] {
+	let mut key = [0u8; 8];
+	key[..4].copy_from_slice(&(writer as u32).to_be_bytes());
+	key[4..].copy_from_slice(&index.to_be_bytes());
+	key
+}
+
+fn owner(key: &[u8]) -> usize {
+	assert_eq!(key.len(), 8, "unexpected key {:?}", key);
+	u32::from_be_bytes(key[..4].try_into().expect("length checked above")) as usize
+}
+
+fn counter(value: &[u8]) -> u64 {
+	u64::from_be_bytes(value.try_into().unwrap_or_else(|_| panic!("unexpected value {:?}", value)))
+}
+
+type ThreadResult = thread::Result<io::Result<()>>;
+
+/// Run `f` on a new thread, reporting its outcome, including panics, on `done`.
+fn spawn<F>(done: &mpsc::Sender<ThreadResult>, f: F)
+where
+	F: FnOnce() -> io::Result<()> + Send + 'static,
+{
+	let done = done.clone();
+	thread::spawn(move || {
+		let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+		let _ = done.send(result);
+	});
+}
+
+/// Wait for `count` threads, propagating the first failure and panicking if they don't all finish
+/// before `deadline`.
+fn join(done: mpsc::Receiver<ThreadResult>, count: usize, deadline: Instant) -> io::Result<()> {
+	for finished in 0..count {
+		let timeout = deadline.saturating_duration_since(Instant::now());
+		match done.recv_timeout(timeout) {
+			Ok(Ok(result)) => result?,
+			Ok(Err(panic)) => panic::resume_unwind(panic as Box<dyn Any + Send>),
+			Err(_) => panic!("{} of {} threads are still running: possible deadlock", count - finished, count),
+		}
+	}
+	Ok(())
+}
+
+fn writer(db: Arc<dyn KeyValueDB>, progress: Arc<Progress>, writer: usize, keys: u32) -> io::Result<()> {
+	let mut value = 0u64;
+	while !progress.stop.load(Ordering::SeqCst) {
+		value += 1;
+		let mut transaction = db.transaction();
+		for index in 0..keys {
+			for col in 0..STRESS_NUM_COLUMNS {
+				transaction.put(col, &key(writer, index), &value.to_be_bytes());
+			}
+		}
+		progress.started[writer].store(value, Ordering::SeqCst);
+		db.write(transaction)?;
+		progress.committed[writer].store(value, Ordering::SeqCst);
+	}
+	Ok(())
+}
+
+fn reader(db: Arc<dyn KeyValueDB>, progress: Arc<Progress>, seed: u64, keys: u32) -> io::Result<()> {
+	let writers = progress.committed.len();
+	let mut rng = StdRng::seed_from_u64(seed);
+	let mut last_seen = vec![0u64; writers * keys as usize];
+	while !progress.stop.load(Ordering::SeqCst) {
+		let writer = rng.gen_range(0, writers);
+		let index = rng.gen_range(0, keys);
+		let key = key(writer, index);
+
+		let committed = progress.committed[writer].load(Ordering::SeqCst);
+		let mut values = Vec::with_capacity(STRESS_NUM_COLUMNS as usize);
+		for col in 0..STRESS_NUM_COLUMNS {
+			values.push(db.get(col, &key)?.map_or(0, |v| counter(&v)));
+		}
+		let started = progress.started[writer].load(Ordering::SeqCst);
+
+		let seen = &mut last_seen[writer * keys as usize + index as usize];
+		for (col, &value) in values.iter().enumerate() {
+			assert!(
+				value >= committed,
+				"column {} key {:?}: read {} after {} was committed",
+				col,
+				key,
+				value,
+				committed
+			);
+			assert!(value <= started, "column {} key {:?}: read {} before it was written", col, key, value);
+			assert!(value >= *seen, "column {} key {:?}: read {} after reading {}", col, key, value, *seen);
+			*seen = value;
+		}
+	}
+	Ok(())
+}
+
+fn iterator(db: Arc<dyn KeyValueDB>, progress: Arc<Progress>, keys: u32) -> io::Result<()> {
+	while !progress.stop.load(Ordering::SeqCst) {
+		let committed: Vec<_> = progress.committed.iter().map(|c| c.load(Ordering::SeqCst)).collect();
+		let mut previous: Option<Box<[u8]>> = None;
+		let mut count = 0;
+		for (key, value) in db.iter(0) {
+			let writer = owner(&key);
+			let value = counter(&value);
+			if let Some(previous) = previous.as_ref() {
+				assert!(previous < &key, "iterator returned {:?} after {:?}", key, previous);
+			}
+			assert!(
+				value >= committed[writer],
+				"key {:?}: iterated {} after {} was committed",
+				key,
+				value,
+				committed[writer]
+			);
+			assert!(
+				value <= progress.started[writer].load(Ordering::SeqCst),
+				"key {:?}: iterated {} before it was written",
+				key,
+				value
+			);
+			previous = Some(key);
+			count += 1;
+			// Let writers make progress while the iterator is alive.
+			thread::yield_now();
+		}
+		// Keys are never deleted: every key written before the iterator was created must be seen.
+		let expected = committed.iter().filter(|&&c| c > 0).count() * keys as usize;
+		assert!(count >= expected, "iterator returned {} keys, at least {} were committed", count, expected);
+	}
+	Ok(())
+}
+
+/// A multi-threaded stress test for `KeyValueDB::write`, `KeyValueDB::get` and `KeyValueDB::iter`.
+/// Assumes the `db` has at least `STRESS_NUM_COLUMNS` empty columns.
+///
+/// Checks that:
+/// - writes to a key are linearizable: a read returns a value at least as recent as the last
+///   write committed before it started, never a value whose write started after it ended, and
+///   successive reads of a key never go back in time;
+/// - transactions are atomic: a value read in a column is also visible in the following ones;
+/// - iterators held across writes return keys in order, without duplicates, and don't miss keys
+///   written before their creation;
+/// - no thread is still running `config.deadlock_timeout` after `config.duration` has elapsed.
+pub fn test_concurrent_stress(db: Arc<dyn KeyValueDB>, config: &StressConfig) -> io::Result<()> {
+	assert!(config.keys_per_writer > 0, "StressConfig::keys_per_writer must be non-zero");
+	let progress = Arc::new(Progress {
+		started: (0..config.writers).map(|_| AtomicU64::new(0)).collect(),
+		committed: (0..config.writers).map(|_| AtomicU64::new(0)).collect(),
+		stop: AtomicBool::new(false),
+	});
+	let (done, finished) = mpsc::channel();
+	for w in 0..config.writers {
+		let (db, progress, keys) = (db.clone(), progress.clone(), config.keys_per_writer);
+		spawn(&done, move || writer(db, progress, w, keys));
+	}
+	if config.writers > 0 {
+		for r in 0..config.readers {
+			let (db, progress, keys) = (db.clone(), progress.clone(), config.keys_per_writer);
+			spawn(&done, move || reader(db, progress, r as u64, keys));
+		}
+	}
+	for _ in 0..config.iterators {
+		let (db, progress, keys) = (db.clone(), progress.clone(), config.keys_per_writer);
+		spawn(&done, move || iterator(db, progress, keys));
+	}
+	drop(done);
+
+	thread::sleep(config.duration);
+	progress.stop.store(true, Ordering::SeqCst);
+	let threads = config.writers + if config.writers > 0 { config.readers } else { 0 } + config.iterators;
+	join(finished, threads, Instant::now() + config.deadlock_timeout)
+}
+
+/// A test for `KeyValueDB::restore` called while other threads iterate over the database.
+///
+/// `config.iterators` threads keep iterating over column 0 while `restore` is called with
+/// `new_db`. Checks that the iterators keep returning ordered keys and that no thread deadlocks;
+/// the outcome of `restore` is returned for the caller to check.
+pub fn test_restore_while_iterating(
+	db: Arc<dyn KeyValueDB>,
+	new_db: &str,
+	config: &StressConfig,
+) -> io::Result<io::Result<()>> {
+	let stop = Arc::new(AtomicBool::new(false));
+	let (done, finished) = mpsc::channel();
+	for _ in 0..config.iterators {
+		let (db, stop) = (db.clone(), stop.clone());
+		spawn(&done, move || {
+			while !stop.load(Ordering::SeqCst) {
+				let mut previous: Option<Box<[u8]>> = None;
+				for (key, _) in db.iter(0) {
+					if let Some(previous) = previous.as_ref() {
+						assert!(previous < &key, "iterator returned {:?} after {:?}", key, previous);
+					}
+					previous = Some(key);
+					thread::yield_now();
+				}
+			}
+			Ok(())
+		});
+	}
+	drop(done);
+
+	// Give the iterators some time to start before restoring.
+	thread::sleep(config.duration / 2);
+	let (restored_tx, restored_rx) = mpsc::channel();
+	{
+		let (db, new_db) = (db.clone(), new_db.to_owned());
+		thread::spawn(move || {
+			let _ = restored_tx.send(db.restore(&new_db));
+		});
+	}
+	let restored = restored_rx
+		.recv_timeout(config.duration / 2 + config.deadlock_timeout)
+		.unwrap_or_else(|_| panic!("restore didn't return: possible deadlock"));
+	stop.store(true, Ordering::SeqCst);
+	join(finished, config.iterators, Instant::now() + config.deadlock_timeout)?;
+
+	// The database must still be usable.
+	db.get(0, b"")?;
+	Ok(restored)
+}
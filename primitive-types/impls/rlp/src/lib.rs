@@ -27,6 +27,13 @@ macro_rules! impl_uint_rlp {
 				self.to_big_endian(&mut buffer);
 				s.encoder().encode_value(&buffer[leading_empty_bytes..]);
 			}
+
+			fn rlp_len(&self) -> usize {
+				let leading_empty_bytes = $size * 8 - (self.bits() + 7) / 8;
+				let mut buffer = [0u8; $size * 8];
+				self.to_big_endian(&mut buffer);
+				$crate::rlp::value_rlp_len(&buffer[leading_empty_bytes..])
+			}
 		}
 
 		impl $crate::rlp::Decodable for $name {
@@ -53,6 +60,10 @@ macro_rules! impl_fixed_hash_rlp {
 			fn rlp_append(&self, s: &mut $crate::rlp::RlpStream) {
 				s.encoder().encode_value(self.as_ref());
 			}
+
+			fn rlp_len(&self) -> usize {
+				$crate::rlp::value_rlp_len(self.as_ref())
+			}
 		}
 
 		impl $crate::rlp::Decodable for $name {
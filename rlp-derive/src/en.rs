@@ -17,6 +17,7 @@ pub fn impl_encodable(ast: &syn::DeriveInput) -> TokenStream {
 	};
 
 	let stmts: Vec<_> = body.fields.iter().enumerate().map(|(i, field)| encodable_field(i, field)).collect();
+	let lens: Vec<_> = body.fields.iter().enumerate().map(|(i, field)| encodable_field_len(i, field)).collect();
 	let name = &ast.ident;
 
 	let stmts_len = stmts.len();
@@ -27,6 +28,10 @@ pub fn impl_encodable(ast: &syn::DeriveInput) -> TokenStream {
 				stream.begin_list(#stmts_len);
 				#(#stmts)*
 			}
+
+			fn rlp_len(&self) -> usize {
+				rlp::list_rlp_len(0 #(+ #lens)*)
+			}
 		}
 	};
 
@@ -45,11 +50,11 @@ pub fn impl_encodable_wrapper(ast: &syn::DeriveInput) -> TokenStream {
 		panic!("#[derive(RlpEncodableWrapper)] is only defined for structs.");
 	};
 
-	let stmt = {
+	let (stmt, len) = {
 		let fields: Vec<_> = body.fields.iter().collect();
 		if fields.len() == 1 {
 			let field = fields.first().expect("fields.len() == 1; qed");
-			encodable_field(0, field)
+			(encodable_field(0, field), encodable_field_len(0, field))
 		} else {
 			panic!("#[derive(RlpEncodableWrapper)] is only defined for structs with one field.")
 		}
@@ -62,6 +67,10 @@ pub fn impl_encodable_wrapper(ast: &syn::DeriveInput) -> TokenStream {
 			fn rlp_append(&self, stream: &mut rlp::RlpStream) {
 				#stmt
 			}
+
+			fn rlp_len(&self) -> usize {
+				#len
+			}
 		}
 	};
 
@@ -74,6 +83,26 @@ pub fn impl_encodable_wrapper(ast: &syn::DeriveInput) -> TokenStream {
 }
 
 fn encodable_field(index: usize, field: &syn::Field) -> TokenStream {
+	let id = field_id(index, field);
+
+	vec_inner_ident(field).map_or_else(
+		|| quote! { stream.append(&#id); },
+		|inner_ident| quote! { stream.append_list::<#inner_ident, _>(&#id); },
+	)
+}
+
+fn encodable_field_len(index: usize, field: &syn::Field) -> TokenStream {
+	let id = field_id(index, field);
+
+	vec_inner_ident(field).map_or_else(
+		|| quote! { rlp::Encodable::rlp_len(&#id) },
+		|inner_ident| {
+			quote! { rlp::list_rlp_len(#id.iter().map(|item: &#inner_ident| rlp::Encodable::rlp_len(item)).sum()) }
+		},
+	)
+}
+
+fn field_id(index: usize, field: &syn::Field) -> TokenStream {
 	let ident = if let Some(ident) = &field.ident {
 		quote! { #ident }
 	} else {
@@ -81,28 +110,28 @@ fn encodable_field(index: usize, field: &syn::Field) -> TokenStream {
 		quote! { #index }
 	};
 
-	let id = quote! { self.#ident };
+	quote! { self.#ident }
+}
 
+/// Returns the type of items of a `Vec` field, which is encoded as a list.
+fn vec_inner_ident(field: &syn::Field) -> Option<&syn::Ident> {
 	if let syn::Type::Path(path) = &field.ty {
 		let top_segment = path.path.segments.first().expect("there must be at least 1 segment");
 		let ident = &top_segment.ident;
 		if ident == "Vec" {
-			let inner_ident = {
-				if let syn::PathArguments::AngleBracketed(angle) = &top_segment.arguments {
-					if let syn::GenericArgument::Type(syn::Type::Path(path)) =
-						angle.args.first().expect("Vec has only one angle bracketed type; qed")
-					{
-						&path.path.segments.first().expect("there must be at least 1 segment").ident
-					} else {
-						panic!("rlp_derive not supported");
-					}
+			if let syn::PathArguments::AngleBracketed(angle) = &top_segment.arguments {
+				if let syn::GenericArgument::Type(syn::Type::Path(path)) =
+					angle.args.first().expect("Vec has only one angle bracketed type; qed")
+				{
+					Some(&path.path.segments.first().expect("there must be at least 1 segment").ident)
 				} else {
-					unreachable!("Vec has only one angle bracketed type; qed")
+					panic!("rlp_derive not supported");
 				}
-			};
-			quote! { stream.append_list::<#inner_ident, _>(&#id); }
+			} else {
+				unreachable!("Vec has only one angle bracketed type; qed")
+			}
 		} else {
-			quote! { stream.append(&#id); }
+			None
 		}
 	} else {
 		panic!("rlp_derive not supported");
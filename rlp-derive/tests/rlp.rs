@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use rlp::{decode, encode};
+use rlp::{decode, encode, Encodable};
 use rlp_derive::{RlpDecodable, RlpDecodableWrapper, RlpEncodable, RlpEncodableWrapper};
 
 #[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
@@ -26,6 +26,7 @@ fn test_encode_item() {
 	let expected = vec![0xc4, 0x83, b'c', b'a', b't'];
 	let out = encode(&item);
 	assert_eq!(out, expected);
+	assert_eq!(item.rlp_len(), expected.len());
 
 	let decoded = decode(&expected).expect("decode failure");
 	assert_eq!(item, decoded);
@@ -38,6 +39,7 @@ fn test_encode_item_wrapper() {
 	let expected = vec![0x83, b'c', b'a', b't'];
 	let out = encode(&item);
 	assert_eq!(out, expected);
+	assert_eq!(item.rlp_len(), expected.len());
 
 	let decoded = decode(&expected).expect("decode failure");
 	assert_eq!(item, decoded);
@@ -69,3 +71,21 @@ fn test_encode_item_default() {
 	let out = encode(&item_some);
 	assert_eq!(decode(&out), Ok(item_some));
 }
+
+#[test]
+fn test_encode_item_len() {
+	#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+	struct ItemList {
+		a: Vec<String>,
+		b: u64,
+		c: Option<Item>,
+	}
+
+	let item =
+		ItemList { a: (0..20).map(|i| format!("item {}", i)).collect(), b: 1024, c: Some(Item { a: "cat".into() }) };
+	assert_eq!(item.rlp_len(), encode(&item).len());
+	assert_eq!(decode(&encode(&item)), Ok(item));
+
+	let item = ItemList { a: vec![], b: 0, c: None };
+	assert_eq!(item.rlp_len(), encode(&item).len());
+}
@@ -0,0 +1,274 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Incremental decoding of RLP without buffering the whole input.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::error::DecoderError;
+use crate::rlpin::PayloadInfo;
+
+/// An item of RLP encountered by the streaming decoder.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Event {
+	/// Beginning of a list, followed by events of its items and `ListEnd`.
+	ListBegin(PayloadInfo),
+	/// End of the innermost list.
+	ListEnd,
+	/// A data item along with its value.
+	Data(PayloadInfo, Vec<u8>),
+}
+
+/// Limits enforced by the streaming decoder.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Limits {
+	/// Maximal nesting of lists.
+	pub max_depth: usize,
+	/// Maximal number of items in a list.
+	pub max_list_len: usize,
+	/// Maximal length of a data item value, which is buffered as a whole.
+	pub max_data_len: usize,
+	/// Maximal length of a top-level item including its header.
+	pub max_total_len: usize,
+}
+
+impl Default for Limits {
+	fn default() -> Self {
+		Limits { max_depth: 64, max_list_len: usize::MAX, max_data_len: 16 * 1024 * 1024, max_total_len: usize::MAX }
+	}
+}
+
+/// Pull decoder fed with chunks of RLP bytes.
+///
+/// Input is appended with `feed` and events are pulled with `next_event`, which returns `None`
+/// when more input is needed. Only the data item being decoded is buffered, so arbitrarily large
+/// lists can be decoded in bounded memory. Consecutive top-level items are decoded one after another.
+///
+/// ```
+/// use rlp::{Event, Limits, PayloadInfo, StreamDecoder};
+///
+/// let mut decoder = StreamDecoder::new(Limits::default());
+/// decoder.feed(&[0xc8, 0x83, b'c', b'a']);
+/// assert_eq!(decoder.next_event(), Ok(Some(Event::ListBegin(PayloadInfo { header_len: 1, value_len: 8 }))));
+/// assert_eq!(decoder.next_event(), Ok(None));
+/// decoder.feed(&[b't', 0x83, b'd', b'o', b'g']);
+/// let info = PayloadInfo { header_len: 1, value_len: 3 };
+/// assert_eq!(decoder.next_event(), Ok(Some(Event::Data(info, b"cat".to_vec()))));
+/// assert_eq!(decoder.next_event(), Ok(Some(Event::Data(info, b"dog".to_vec()))));
+/// assert_eq!(decoder.next_event(), Ok(Some(Event::ListEnd)));
+/// assert!(decoder.is_idle());
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamDecoder {
+	limits: Limits,
+	buffer: Vec<u8>,
+	position: usize,
+	lists: Vec<OpenList>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OpenList {
+	/// Remaining payload length.
+	remaining: usize,
+	/// Number of items begun so far.
+	items: usize,
+}
+
+impl StreamDecoder {
+	/// Creates a new decoder enforcing `limits`.
+	pub fn new(limits: Limits) -> Self {
+		StreamDecoder { limits, buffer: Vec::new(), position: 0, lists: Vec::new() }
+	}
+
+	/// Appends input bytes.
+	pub fn feed(&mut self, bytes: &[u8]) {
+		// drop consumed bytes before growing the buffer.
+		if self.position > 0 && self.position >= self.buffer.len() / 2 {
+			self.buffer.drain(..self.position);
+			self.position = 0;
+		}
+		self.buffer.extend_from_slice(bytes);
+	}
+
+	/// Returns true if there are no unfinished items, i.e. the input may end here.
+	pub fn is_idle(&self) -> bool {
+		self.lists.is_empty() && self.position == self.buffer.len()
+	}
+
+	/// Returns the current nesting of lists.
+	pub fn depth(&self) -> usize {
+		self.lists.len()
+	}
+
+	/// Returns the input which has been fed but not decoded yet.
+	pub fn buffered(&self) -> &[u8] {
+		&self.buffer[self.position..]
+	}
+
+	/// Decodes the next event or returns `None` if more input is needed.
+	///
+	/// After an error the state of the decoder is unspecified.
+	pub fn next_event(&mut self) -> Result<Option<Event>, DecoderError> {
+		if let Some(OpenList { remaining: 0, .. }) = self.lists.last() {
+			self.lists.pop();
+			return Ok(Some(Event::ListEnd));
+		}
+
+		let available = &self.buffer[self.position..];
+		let first = match available.first() {
+			Some(&first) => first,
+			None => return Ok(None),
+		};
+		let header_len = match first {
+			0xb8..=0xbf => 1 + first as usize - 0xb7,
+			0xf8..=0xff => 1 + first as usize - 0xf7,
+			_ => 1,
+		};
+		if available.len() < header_len {
+			return Ok(None);
+		}
+		let info = PayloadInfo::from(&available[..header_len])?;
+		let total = info.header_len.checked_add(info.value_len).ok_or(DecoderError::RlpInvalidLength)?;
+
+		match self.lists.last() {
+			Some(list) if list.remaining < total => return Err(DecoderError::RlpInconsistentLengthAndData),
+			Some(list) if list.items >= self.limits.max_list_len => {
+				return Err(DecoderError::Custom("RLP list has too many items"))
+			}
+			Some(_) => (),
+			None if total > self.limits.max_total_len => return Err(DecoderError::Custom("RLP item is too long")),
+			None => (),
+		}
+
+		if first >= 0xc0 {
+			if self.lists.len() >= self.limits.max_depth {
+				return Err(DecoderError::Custom("RLP lists are nested too deep"));
+			}
+			self.consume(info.header_len, total);
+			self.lists.push(OpenList { remaining: info.value_len, items: 0 });
+			return Ok(Some(Event::ListBegin(info)));
+		}
+
+		if info.value_len > self.limits.max_data_len {
+			return Err(DecoderError::Custom("RLP data item is too long"));
+		}
+		if available.len() < total {
+			return Ok(None);
+		}
+		let value = available[info.header_len..total].to_vec();
+		if first == 0x81 && value[0] < 0x80 {
+			return Err(DecoderError::RlpInvalidIndirection);
+		}
+		self.consume(total, total);
+		Ok(Some(Event::Data(info, value)))
+	}
+
+	/// Consumes `len` bytes of input belonging to an item of `total` length.
+	fn consume(&mut self, len: usize, total: usize) {
+		self.position += len;
+		if let Some(list) = self.lists.last_mut() {
+			list.remaining -= total;
+			list.items += 1;
+		}
+	}
+}
+
+/// Pull decoder reading RLP from an `io::Read`.
+///
+/// Yields the events of `StreamDecoder`. Decoding errors are reported as `io::ErrorKind::InvalidData`
+/// and input ending within an item as `io::ErrorKind::UnexpectedEof`.
+///
+/// ```
+/// use rlp::{Event, Limits, StreamReader};
+///
+/// let input: &[u8] = &[0xc4, 0x83, b'c', b'a', b't'];
+/// let events = StreamReader::new(input, Limits::default()).collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(events.len(), 3);
+/// assert_eq!(events[2], Event::ListEnd);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct StreamReader<R> {
+	reader: R,
+	decoder: StreamDecoder,
+	/// Buffer for reading from `reader`, allocated once.
+	chunk: Vec<u8>,
+	done: bool,
+}
+
+/// Number of bytes read from the underlying reader at once.
+#[cfg(feature = "std")]
+const CHUNK_LEN: usize = 8 * 1024;
+
+#[cfg(feature = "std")]
+impl<R: io::Read> StreamReader<R> {
+	/// Creates a new reader enforcing `limits`.
+	pub fn new(reader: R, limits: Limits) -> Self {
+		StreamReader { reader, decoder: StreamDecoder::new(limits), chunk: vec![0u8; CHUNK_LEN], done: false }
+	}
+
+	/// Returns the current nesting of lists.
+	pub fn depth(&self) -> usize {
+		self.decoder.depth()
+	}
+
+	/// Returns the bytes read ahead from the underlying reader which haven't been decoded yet.
+	pub fn buffered(&self) -> &[u8] {
+		self.decoder.buffered()
+	}
+
+	/// Returns the underlying reader.
+	///
+	/// Bytes read ahead past the last event are dropped, use `buffered` to get them first.
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+
+	fn next_event(&mut self) -> io::Result<Option<Event>> {
+		loop {
+			if let Some(event) =
+				self.decoder.next_event().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+			{
+				return Ok(Some(event));
+			}
+
+			match self.reader.read(&mut self.chunk) {
+				Ok(0) if self.decoder.is_idle() => return Ok(None),
+				Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+				Ok(read) => self.decoder.feed(&self.chunk[..read]),
+				Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+				Err(err) => return Err(err),
+			}
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> Iterator for StreamReader<R> {
+	type Item = io::Result<Event>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+		match self.next_event() {
+			Ok(Some(event)) => Some(Ok(event)),
+			Ok(None) => {
+				self.done = true;
+				None
+			}
+			Err(err) => {
+				self.done = true;
+				Some(Err(err))
+			}
+		}
+	}
+}
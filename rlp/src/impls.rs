@@ -13,7 +13,7 @@ use core::{mem, str};
 
 use crate::error::DecoderError;
 use crate::rlpin::Rlp;
-use crate::stream::RlpStream;
+use crate::stream::{list_rlp_len, value_rlp_len, RlpStream};
 use crate::traits::{Decodable, Encodable};
 
 pub fn decode_usize(bytes: &[u8]) -> Result<usize, DecoderError> {
@@ -37,6 +37,10 @@ impl Encodable for bool {
 	fn rlp_append(&self, s: &mut RlpStream) {
 		s.encoder().encode_iter(once(if *self { 1u8 } else { 0 }));
 	}
+
+	fn rlp_len(&self) -> usize {
+		1
+	}
 }
 
 impl Decodable for bool {
@@ -53,12 +57,20 @@ impl<'a> Encodable for &'a [u8] {
 	fn rlp_append(&self, s: &mut RlpStream) {
 		s.encoder().encode_value(self);
 	}
+
+	fn rlp_len(&self) -> usize {
+		value_rlp_len(self)
+	}
 }
 
 impl Encodable for Vec<u8> {
 	fn rlp_append(&self, s: &mut RlpStream) {
 		s.encoder().encode_value(self);
 	}
+
+	fn rlp_len(&self) -> usize {
+		value_rlp_len(self)
+	}
 }
 
 impl Decodable for Vec<u8> {
@@ -82,6 +94,13 @@ where
 			}
 		}
 	}
+
+	fn rlp_len(&self) -> usize {
+		match *self {
+			None => list_rlp_len(0),
+			Some(ref value) => list_rlp_len(value.rlp_len()),
+		}
+	}
 }
 
 impl<T> Decodable for Option<T>
@@ -106,6 +125,10 @@ impl Encodable for u8 {
 			s.encoder().encode_iter(empty());
 		}
 	}
+
+	fn rlp_len(&self) -> usize {
+		value_rlp_len(&[*self])
+	}
 }
 
 impl Decodable for u8 {
@@ -127,6 +150,12 @@ macro_rules! impl_encodable_for_u {
 				let buffer = self.to_be_bytes();
 				s.encoder().encode_value(&buffer[leading_empty_bytes..]);
 			}
+
+			fn rlp_len(&self) -> usize {
+				let leading_empty_bytes = self.leading_zeros() as usize / 8;
+				let buffer = self.to_be_bytes();
+				value_rlp_len(&buffer[leading_empty_bytes..])
+			}
 		}
 	};
 }
@@ -167,6 +196,10 @@ impl Encodable for usize {
 	fn rlp_append(&self, s: &mut RlpStream) {
 		(*self as u64).rlp_append(s);
 	}
+
+	fn rlp_len(&self) -> usize {
+		(*self as u64).rlp_len()
+	}
 }
 
 impl Decodable for usize {
@@ -179,12 +212,20 @@ impl<'a> Encodable for &'a str {
 	fn rlp_append(&self, s: &mut RlpStream) {
 		s.encoder().encode_value(self.as_bytes());
 	}
+
+	fn rlp_len(&self) -> usize {
+		value_rlp_len(self.as_bytes())
+	}
 }
 
 impl Encodable for String {
 	fn rlp_append(&self, s: &mut RlpStream) {
 		s.encoder().encode_value(self.as_bytes());
 	}
+
+	fn rlp_len(&self) -> usize {
+		value_rlp_len(self.as_bytes())
+	}
 }
 
 impl Decodable for String {
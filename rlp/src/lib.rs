@@ -31,26 +31,48 @@
 //! * You are working on input data.
 //! * You want to get view onto rlp-slice.
 //! * You don't want to decode whole rlp at once.
+//!
+//!### Use `StreamReader` or `StreamDecoder` when:
+//! * The input doesn't fit in memory or arrives in chunks (e.g. from a socket).
+//! * You want to bound the nesting and size of the input.
+//!
+//!### Use `validate` before `Rlp` or `decode` when:
+//! * The input is untrusted and must be rejected unless it's canonical (e.g. in consensus code).
+//! * You need the offset of the first invalid byte.
+//!
+//!### Use `StreamWriter` when:
+//! * The output doesn't fit in memory and is written to a file or a socket.
+//! * You know the lengths of lists up front or can encode their items twice.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
 #[cfg(not(feature = "std"))]
 extern crate alloc;
 
+mod decoder;
 mod error;
 mod impls;
 mod rlpin;
 mod stream;
+mod strict;
 mod traits;
+#[cfg(feature = "std")]
+mod writer;
 
 #[cfg(not(feature = "std"))]
 use alloc::vec::Vec;
 use core::borrow::Borrow;
 
+#[cfg(feature = "std")]
+pub use self::decoder::StreamReader;
+pub use self::decoder::{Event, Limits, StreamDecoder};
 pub use self::error::DecoderError;
 pub use self::rlpin::{PayloadInfo, Prototype, Rlp, RlpIterator};
-pub use self::stream::RlpStream;
+pub use self::stream::{list_rlp_len, value_rlp_len, RlpStream};
+pub use self::strict::{validate, ValidationError};
 pub use self::traits::{Decodable, Encodable};
+#[cfg(feature = "std")]
+pub use self::writer::StreamWriter;
 
 /// The RLP encoded empty data (used to mean "null value").
 pub const NULL_RLP: [u8; 1] = [0x80; 1];
@@ -42,7 +42,7 @@ pub enum Prototype {
 }
 
 /// Stores basic information about item
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct PayloadInfo {
 	/// Header length in bytes
 	pub header_len: usize,
@@ -25,6 +25,42 @@ impl ListInfo {
 	}
 }
 
+/// Returns the length of the RLP encoding of a data item with the given value.
+///
+/// ```
+/// assert_eq!(rlp::value_rlp_len(b"cat"), rlp::encode(&"cat").len());
+/// ```
+pub fn value_rlp_len(value: &[u8]) -> usize {
+	match value {
+		[byte] if *byte < 0x80 => 1,
+		_ => header_len(value.len()) + value.len(),
+	}
+}
+
+/// Returns the length of the RLP encoding of a list with `payload_len` bytes of encoded items.
+///
+/// ```
+/// let animals = vec!["cat", "dog"];
+/// let payload_len = animals.iter().map(rlp::Encodable::rlp_len).sum();
+/// assert_eq!(rlp::list_rlp_len(payload_len), rlp::encode_list::<&str, _>(&animals).len());
+/// ```
+pub fn list_rlp_len(payload_len: usize) -> usize {
+	header_len(payload_len) + payload_len
+}
+
+/// Returns the length of the header of an item with `len` bytes of payload.
+fn header_len(len: usize) -> usize {
+	match len {
+		0..=55 => 1,
+		_ => 1 + size_len(len),
+	}
+}
+
+/// Returns the number of bytes needed to encode `len` in a header.
+pub(crate) fn size_len(len: usize) -> usize {
+	8 - (len as u64).leading_zeros() as usize / 8
+}
+
 /// Appendable rlp encoder.
 pub struct RlpStream {
 	unfinished_lists: Vec<ListInfo>,
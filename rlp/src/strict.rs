@@ -0,0 +1,129 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Strict validation of untrusted RLP.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error as StdError;
+
+use crate::decoder::Limits;
+use crate::error::DecoderError;
+use crate::rlpin::PayloadInfo;
+
+/// Error found by `validate` along with its position.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ValidationError {
+	/// Offset of the offending item (or of the first trailing byte) in the input.
+	pub offset: usize,
+	/// The error.
+	pub error: DecoderError,
+}
+
+#[cfg(feature = "std")]
+impl StdError for ValidationError {}
+
+impl fmt::Display for ValidationError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at offset {}", self.error, self.offset)
+	}
+}
+
+struct OpenList {
+	/// Offset of the end of the list.
+	end: usize,
+	/// Number of items in the list so far.
+	items: usize,
+}
+
+/// Validates that `bytes` are exactly one canonically encoded RLP item within `limits`.
+///
+/// Unlike `Rlp`, which checks only the items being accessed, the whole input is walked once
+/// and the first problem is reported with its offset:
+/// * truncated input or items overrunning their list,
+/// * bytes following the item,
+/// * non-canonical headers (single bytes below `0x80` encoded as data, long headers of short items,
+///   lengths with leading zeros),
+/// * items exceeding `limits`, reported as `DecoderError::Custom`.
+///
+/// Canonical encoding of the values themselves (e.g. integers without leading zeros) is still
+/// checked by their `Decodable` implementations.
+///
+/// ```
+/// use rlp::{DecoderError, Limits, ValidationError};
+///
+/// assert_eq!(rlp::validate(&[0xc4, 0x83, b'c', b'a', b't'], Limits::default()), Ok(()));
+/// let error = ValidationError { offset: 1, error: DecoderError::RlpInvalidIndirection };
+/// assert_eq!(rlp::validate(&[0xc2, 0x81, 0x01], Limits::default()), Err(error));
+/// ```
+pub fn validate(bytes: &[u8], limits: Limits) -> Result<(), ValidationError> {
+	let fail = |offset, error| Err(ValidationError { offset, error });
+	let mut lists: Vec<OpenList> = Vec::new();
+	let mut offset = 0;
+
+	loop {
+		let first = match bytes.get(offset) {
+			Some(&first) => first,
+			None => return fail(offset, DecoderError::RlpIsTooShort),
+		};
+		let info = match PayloadInfo::from(&bytes[offset..]) {
+			Ok(info) => info,
+			Err(DecoderError::RlpDataLenWithZeroPrefix) if first >= 0xc0 => {
+				return fail(offset, DecoderError::RlpListLenWithZeroPrefix)
+			}
+			Err(err) => return fail(offset, err),
+		};
+		let end = match info.header_len.checked_add(info.value_len).and_then(|total| total.checked_add(offset)) {
+			Some(end) => end,
+			None => return fail(offset, DecoderError::RlpInvalidLength),
+		};
+
+		match lists.last_mut() {
+			Some(list) if end > list.end => return fail(offset, DecoderError::RlpInconsistentLengthAndData),
+			Some(list) if list.items >= limits.max_list_len => {
+				return fail(offset, DecoderError::Custom("RLP list has too many items"))
+			}
+			Some(list) => list.items += 1,
+			None if end > limits.max_total_len => return fail(offset, DecoderError::Custom("RLP item is too long")),
+			None => (),
+		}
+		if end > bytes.len() {
+			return fail(offset, DecoderError::RlpIsTooShort);
+		}
+
+		if first >= 0xc0 {
+			if lists.len() >= limits.max_depth {
+				return fail(offset, DecoderError::Custom("RLP lists are nested too deep"));
+			}
+			lists.push(OpenList { end, items: 0 });
+			offset += info.header_len;
+		} else {
+			if info.value_len > limits.max_data_len {
+				return fail(offset, DecoderError::Custom("RLP data item is too long"));
+			}
+			if first == 0x81 && bytes[offset + 1] < 0x80 {
+				return fail(offset, DecoderError::RlpInvalidIndirection);
+			}
+			offset = end;
+		}
+
+		while lists.last().map(|list| list.end) == Some(offset) {
+			lists.pop();
+		}
+		if lists.is_empty() {
+			break;
+		}
+	}
+
+	if offset < bytes.len() {
+		return fail(offset, DecoderError::RlpIsTooBig);
+	}
+	Ok(())
+}
@@ -31,4 +31,12 @@ pub trait Encodable {
 		self.rlp_append(&mut s);
 		s.drain()
 	}
+
+	/// Get the length of rlp-encoded bytes for this instance
+	///
+	/// The default implementation encodes the value, implementations should override it
+	/// to compute the length without allocating.
+	fn rlp_len(&self) -> usize {
+		self.rlp_bytes().len()
+	}
 }
@@ -0,0 +1,164 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Encoding of RLP directly to an `io::Write`.
+
+use std::borrow::Borrow;
+use std::io::{self, Write};
+
+use crate::stream::{list_rlp_len, size_len, value_rlp_len};
+use crate::traits::Encodable;
+
+/// Returns the total length of encodings of given items.
+fn payload_len<E, I>(items: I) -> usize
+where
+	E: Encodable,
+	I: IntoIterator,
+	I::Item: Borrow<E>,
+{
+	items.into_iter().map(|item| item.borrow().rlp_len()).sum()
+}
+
+/// RLP encoder writing directly to an `io::Write`.
+///
+/// Unlike `RlpStream` the output isn't buffered, so the payload length of every list has to be
+/// known when the list begins. It's either declared with `begin_list` or computed by
+/// `append_list` and `append_iter` in a first pass over the items, so that memory use is bounded
+/// by the size of a single item.
+///
+/// ```
+/// use rlp::StreamWriter;
+///
+/// let mut writer = StreamWriter::new(Vec::new());
+/// writer.begin_list(8).unwrap();
+/// writer.append(&"cat").unwrap().append(&"dog").unwrap();
+/// let out = writer.finish().unwrap();
+/// assert_eq!(out, vec![0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']);
+/// ```
+#[derive(Debug)]
+pub struct StreamWriter<W> {
+	writer: W,
+	/// Remaining payload length of open lists.
+	lists: Vec<usize>,
+}
+
+impl<W: Write> StreamWriter<W> {
+	/// Creates a new writer.
+	pub fn new(writer: W) -> Self {
+		StreamWriter { writer, lists: Vec::new() }
+	}
+
+	/// Begins a list with `payload_len` bytes of encoded items, which have to be appended next.
+	pub fn begin_list(&mut self, payload_len: usize) -> io::Result<&mut Self> {
+		self.note_written(list_rlp_len(payload_len))?;
+		self.write_header(0xc0, payload_len)?;
+		self.lists.push(payload_len);
+		self.close_finished_lists();
+		Ok(self)
+	}
+
+	/// Appends an encoded value.
+	pub fn append<E: Encodable + ?Sized>(&mut self, value: &E) -> io::Result<&mut Self> {
+		self.append_raw(&value.rlp_bytes())
+	}
+
+	/// Appends a list of values, computing its length in a first pass over `values`.
+	pub fn append_list<E, K>(&mut self, values: &[K]) -> io::Result<&mut Self>
+	where
+		E: Encodable,
+		K: Borrow<E>,
+	{
+		self.append_iter::<E, _>(values.iter().map(Borrow::borrow))
+	}
+
+	/// Appends a list of values, computing its length in a first pass over a clone of `values`.
+	///
+	/// Values are encoded twice, but only one at a time is kept in memory.
+	pub fn append_iter<E, I>(&mut self, values: I) -> io::Result<&mut Self>
+	where
+		E: Encodable,
+		I: IntoIterator + Clone,
+		I::Item: Borrow<E>,
+	{
+		self.begin_list(payload_len::<E, _>(values.clone()))?;
+		for value in values {
+			self.append(value.borrow())?;
+		}
+		Ok(self)
+	}
+
+	/// Appends a data item with the given value, without copying it.
+	pub fn append_data(&mut self, value: &[u8]) -> io::Result<&mut Self> {
+		match value {
+			[byte] if *byte < 0x80 => self.append_raw(value),
+			_ => {
+				self.note_written(value_rlp_len(value))?;
+				self.write_header(0x80, value.len())?;
+				self.writer.write_all(value)?;
+				self.close_finished_lists();
+				Ok(self)
+			}
+		}
+	}
+
+	/// Appends raw (pre-serialised) RLP data. Use with caution.
+	pub fn append_raw(&mut self, bytes: &[u8]) -> io::Result<&mut Self> {
+		self.note_written(bytes.len())?;
+		self.writer.write_all(bytes)?;
+		self.close_finished_lists();
+		Ok(self)
+	}
+
+	/// Returns true if all lists have been completed.
+	pub fn is_finished(&self) -> bool {
+		self.lists.is_empty()
+	}
+
+	/// Flushes and returns the underlying writer.
+	///
+	/// Fails if some list is not complete.
+	pub fn finish(mut self) -> io::Result<W> {
+		if !self.is_finished() {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "RLP list is not complete"));
+		}
+		self.writer.flush()?;
+		Ok(self.writer)
+	}
+
+	fn write_header(&mut self, prefix: u8, len: usize) -> io::Result<()> {
+		match len {
+			0..=55 => self.writer.write_all(&[prefix + len as u8]),
+			_ => {
+				let size_len = size_len(len);
+				let size = (len as u64).to_be_bytes();
+				self.writer.write_all(&[prefix + 55 + size_len as u8])?;
+				self.writer.write_all(&size[size.len() - size_len..])
+			}
+		}
+	}
+
+	/// Accounts `len` bytes about to be written to the innermost list.
+	fn note_written(&mut self, len: usize) -> io::Result<()> {
+		match self.lists.last_mut() {
+			Some(remaining) if *remaining < len => {
+				Err(io::Error::new(io::ErrorKind::InvalidInput, "RLP list payload is longer than declared"))
+			}
+			Some(remaining) => {
+				*remaining -= len;
+				Ok(())
+			}
+			None => Ok(()),
+		}
+	}
+
+	fn close_finished_lists(&mut self) {
+		while let Some(&0) = self.lists.last() {
+			self.lists.pop();
+		}
+	}
+}
@@ -10,7 +10,9 @@ use core::{cmp, fmt};
 
 use hex_literal::hex;
 use primitive_types::{H160, U256};
-use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use rlp::{Decodable, DecoderError, Encodable, Limits, Rlp, RlpStream, ValidationError};
+#[cfg(feature = "std")]
+use rlp::{Event, PayloadInfo, StreamDecoder, StreamReader, StreamWriter};
 
 #[test]
 fn test_rlp_display() {
@@ -609,3 +611,242 @@ fn test_list_at() {
 	let rlp2 = rlp.at(2).unwrap();
 	assert_eq!(rlp2.val_at::<u16>(2).unwrap(), 33338);
 }
+
+#[cfg(feature = "std")]
+fn stream_events(bytes: &[u8], limits: Limits) -> std::io::Result<Vec<Event>> {
+	StreamReader::new(bytes, limits).collect()
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_stream_reader_events() {
+	let data = vec![0xc7, 0xc0, 0x01, 0x80, 0x83, b'c', b'a', b't', 0xc0];
+	let events = stream_events(&data, Limits::default()).unwrap();
+
+	let list = |value_len| Event::ListBegin(PayloadInfo { header_len: 1, value_len });
+	let data =
+		|header_len, value: &[u8]| Event::Data(PayloadInfo { header_len, value_len: value.len() }, value.to_vec());
+	assert_eq!(
+		events,
+		vec![
+			list(7),
+			list(0),
+			Event::ListEnd,
+			data(0, &[0x01]),
+			data(1, &[]),
+			data(1, b"cat"),
+			Event::ListEnd,
+			list(0),
+			Event::ListEnd,
+		]
+	);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_stream_reader_buffered() {
+	let input: &[u8] = &[0xc4, 0x83, b'c', b'a', b't', 0x01, 0x02];
+	let mut reader = StreamReader::new(input, Limits::default());
+	assert_eq!(reader.next().unwrap().unwrap(), Event::ListBegin(PayloadInfo { header_len: 1, value_len: 4 }));
+	assert_eq!(reader.buffered(), &[0x83, b'c', b'a', b't', 0x01, 0x02]);
+	reader.next().unwrap().unwrap();
+	reader.next().unwrap().unwrap();
+	assert_eq!(reader.buffered(), &[0x01, 0x02]);
+	assert!(reader.into_inner().is_empty());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_stream_decoder_byte_by_byte() {
+	let mut stream = RlpStream::new_list(3);
+	stream.append(&vec![0u8; 300]).append_list(&[1u64, 1024, 1 << 40]).append(&"dog");
+	let data = stream.out();
+	let expected = stream_events(&data, Limits::default()).unwrap();
+
+	let mut decoder = StreamDecoder::new(Limits::default());
+	let mut events = Vec::new();
+	for byte in &data {
+		decoder.feed(&[*byte]);
+		while let Some(event) = decoder.next_event().unwrap() {
+			events.push(event);
+		}
+	}
+
+	assert!(decoder.is_idle());
+	assert_eq!(events, expected);
+	assert_eq!(events[1], Event::Data(PayloadInfo { header_len: 3, value_len: 300 }, vec![0u8; 300]));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_stream_reader_limits() {
+	let nested = vec![0xc3, 0xc2, 0xc1, 0xc0];
+	assert!(stream_events(&nested, Limits { max_depth: 4, ..Default::default() }).is_ok());
+	let err = stream_events(&nested, Limits { max_depth: 3, ..Default::default() }).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+	let data = vec![0x83, b'c', b'a', b't'];
+	assert!(stream_events(&data, Limits { max_data_len: 3, ..Default::default() }).is_ok());
+	assert!(stream_events(&data, Limits { max_data_len: 2, ..Default::default() }).is_err());
+	assert!(stream_events(&data, Limits { max_total_len: 4, ..Default::default() }).is_ok());
+	assert!(stream_events(&data, Limits { max_total_len: 3, ..Default::default() }).is_err());
+
+	let list = vec![0xc3, 0x01, 0xc1, 0x02];
+	assert!(stream_events(&list, Limits { max_list_len: 2, ..Default::default() }).is_ok());
+	assert!(stream_events(&list, Limits { max_list_len: 1, ..Default::default() }).is_err());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_stream_reader_invalid_input() {
+	// truncated
+	let err = stream_events(&[0xc4, 0x83, b'c', b'a'], Limits::default()).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+	// item longer than the list
+	let err = stream_events(&[0xc2, 0x83, b'c', b'a', b't'], Limits::default()).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	// non-canonical single byte
+	let err = stream_events(&[0x81, 0x01], Limits::default()).unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+	// consecutive top-level items
+	assert_eq!(stream_events(&[0x01, 0xc0], Limits::default()).unwrap().len(), 3);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_stream_writer_matches_rlp_stream() {
+	let long = vec![0xabu8; 1024];
+	let items = vec![vec![1u64, 2, 3], vec![], (0..100).collect()];
+
+	let mut stream = RlpStream::new_list(4);
+	stream.append(&"cat").append(&long).append_list::<u64, u64>(&[]);
+	stream.begin_list(items.len());
+	for item in &items {
+		stream.append_list(item);
+	}
+
+	let mut writer = StreamWriter::new(Vec::new());
+	let items_len: usize = items.iter().map(|item| rlp::encode_list(item).len()).sum();
+	let payload_len = rlp::encode(&"cat").len() + rlp::encode(&long).len() + 1 + 2 + items_len; // empty list, header of items
+	writer.begin_list(payload_len).unwrap();
+	writer.append(&"cat").unwrap().append_data(&long).unwrap().append_list::<u64, u64>(&[]).unwrap();
+	writer.begin_list(items_len).unwrap();
+	for item in &items {
+		writer.append_iter::<u64, _>(item.iter()).unwrap();
+	}
+	assert!(writer.is_finished());
+	assert_eq!(writer.finish().unwrap(), stream.out().to_vec());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_stream_writer_data() {
+	for value in [&b""[..], &[0x7f], &[0x80], &[0u8; 55], &[0u8; 56]].iter() {
+		let mut writer = StreamWriter::new(Vec::new());
+		writer.append_data(value).unwrap();
+		assert_eq!(writer.finish().unwrap(), rlp::encode(&value.to_vec()));
+	}
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_stream_writer_invalid_lengths() {
+	let mut writer = StreamWriter::new(Vec::new());
+	writer.begin_list(3).unwrap();
+	let err = writer.append(&"cat").unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+	let mut writer = StreamWriter::new(Vec::new());
+	writer.begin_list(5).unwrap().append(&"cat").unwrap();
+	assert!(!writer.is_finished());
+	let err = writer.finish().unwrap_err();
+	assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_rlp_len() {
+	fn check<E: Encodable>(value: E) {
+		assert_eq!(value.rlp_len(), rlp::encode(&value).len());
+	}
+
+	for &value in &[0u8, 1, 0x7f, 0x80, 0xff] {
+		check(value);
+	}
+	for &value in &[0u64, 0x7f, 0x80, 0x100, u64::MAX] {
+		check(value);
+		check(value as usize);
+		check(value as u32);
+		check(value as u16);
+	}
+	check(true);
+	check(false);
+	for len in &[0, 1, 55, 56, 1024, 70_000] {
+		let value = vec![0x80u8; *len];
+		check(value.clone());
+		check(&value[..]);
+		check(String::from_utf8(vec![b'a'; *len]).unwrap());
+		check(Some(value));
+	}
+	check("a");
+	check(None::<u64>);
+	check(Some(Some(0x80u8)));
+	check(U256::from(0x7f));
+	check(U256::from(0x80));
+	check(U256::MAX);
+	check(U256::zero());
+	check(H160::repeat_byte(0x01));
+}
+
+#[test]
+fn test_validate_canonical() {
+	let valid: Vec<Vec<u8>> = vec![
+		vec![0x00],
+		vec![0x80],
+		vec![0x81, 0x80],
+		vec![0xc0],
+		vec![0xc4, 0x83, b'c', b'a', b't'],
+		vec![0xc3, 0xc2, 0xc1, 0xc0],
+		rlp::encode(&vec![0u8; 56]),
+		rlp::encode_list::<Vec<u8>, _>(&[vec![0u8; 1024], vec![]]),
+	];
+	for bytes in &valid {
+		assert_eq!(rlp::validate(bytes, Limits::default()), Ok(()), "{:?}", bytes);
+	}
+
+	let invalid = vec![
+		(vec![], 0, DecoderError::RlpIsTooShort),
+		(vec![0x83, b'c', b'a'], 0, DecoderError::RlpIsTooShort),
+		(vec![0xc4, 0x83, b'c', b'a'], 0, DecoderError::RlpIsTooShort),
+		(vec![0x01, 0x02], 1, DecoderError::RlpIsTooBig),
+		(vec![0xc0, 0xc0], 1, DecoderError::RlpIsTooBig),
+		(vec![0xc2, 0x83, b'c', b'a', b't'], 1, DecoderError::RlpInconsistentLengthAndData),
+		(vec![0xc3, 0xc0, 0x81, 0x01], 2, DecoderError::RlpInvalidIndirection),
+		(vec![0xb8, 0x01, 0xff], 0, DecoderError::RlpInvalidIndirection),
+		(vec![0xc2, 0xb9, 0x00, 0x38], 1, DecoderError::RlpDataLenWithZeroPrefix),
+		(vec![0xf9, 0x00, 0x38], 0, DecoderError::RlpListLenWithZeroPrefix),
+	];
+	for (bytes, offset, error) in invalid {
+		assert_eq!(rlp::validate(&bytes, Limits::default()), Err(ValidationError { offset, error }), "{:?}", bytes);
+	}
+}
+
+#[test]
+fn test_validate_limits() {
+	let nested = vec![0xc3, 0xc2, 0xc1, 0xc0];
+	assert!(rlp::validate(&nested, Limits { max_depth: 4, ..Default::default() }).is_ok());
+	let err = rlp::validate(&nested, Limits { max_depth: 3, ..Default::default() }).unwrap_err();
+	assert_eq!(err.offset, 3);
+
+	let list = vec![0xc4, 0x01, 0xc2, 0x02, 0x03];
+	assert!(rlp::validate(&list, Limits { max_list_len: 2, ..Default::default() }).is_ok());
+	let err = rlp::validate(&list, Limits { max_list_len: 1, ..Default::default() }).unwrap_err();
+	assert_eq!(err.offset, 2);
+
+	let data = vec![0xc5, 0x01, 0x83, b'c', b'a', b't'];
+	assert!(rlp::validate(&data, Limits { max_data_len: 3, max_total_len: 6, ..Default::default() }).is_ok());
+	let err = rlp::validate(&data, Limits { max_data_len: 2, ..Default::default() }).unwrap_err();
+	assert_eq!(err.offset, 2);
+	let err = rlp::validate(&data, Limits { max_total_len: 5, ..Default::default() }).unwrap_err();
+	assert_eq!(err.offset, 0);
+	assert_eq!(err.to_string(), "Custom(\"RLP item is too long\") at offset 0");
+}
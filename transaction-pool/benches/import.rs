@@ -0,0 +1,159 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Benchmarks of single and batch imports and removals.
+
+use std::cmp;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use transaction_pool::{scoring, Options, Pool, ReplaceTransaction, Scoring, ShouldReplace, VerifiedTransaction};
+
+#[derive(Debug)]
+struct Transaction {
+	hash: u64,
+	sender: u64,
+	nonce: u64,
+	gas_price: u64,
+}
+
+impl VerifiedTransaction for Transaction {
+	type Hash = u64;
+	type Sender = u64;
+
+	fn hash(&self) -> &u64 {
+		&self.hash
+	}
+
+	fn mem_usage(&self) -> usize {
+		0
+	}
+
+	fn sender(&self) -> &u64 {
+		&self.sender
+	}
+}
+
+#[derive(Debug, Default)]
+struct GasPrice;
+
+impl Scoring<Transaction> for GasPrice {
+	type Score = u64;
+	type Event = ();
+
+	fn compare(&self, old: &Transaction, other: &Transaction) -> cmp::Ordering {
+		old.nonce.cmp(&other.nonce)
+	}
+
+	fn choose(&self, old: &Transaction, new: &Transaction) -> scoring::Choice {
+		if old.nonce != new.nonce {
+			scoring::Choice::InsertNew
+		} else if new.gas_price > old.gas_price {
+			scoring::Choice::ReplaceOld
+		} else {
+			scoring::Choice::RejectNew
+		}
+	}
+
+	fn update_scores(
+		&self,
+		txs: &[transaction_pool::Transaction<Transaction>],
+		scores: &mut [u64],
+		_change: scoring::Change,
+	) {
+		for (score, tx) in scores.iter_mut().zip(txs) {
+			*score = tx.gas_price;
+		}
+	}
+}
+
+impl ShouldReplace<Transaction> for GasPrice {
+	fn should_replace(
+		&self,
+		old: &ReplaceTransaction<'_, Transaction>,
+		new: &ReplaceTransaction<'_, Transaction>,
+	) -> scoring::Choice {
+		if new.gas_price > old.gas_price {
+			scoring::Choice::ReplaceOld
+		} else {
+			scoring::Choice::RejectNew
+		}
+	}
+}
+
+const SENDERS: u64 = 100;
+const PER_SENDER: u64 = 16;
+
+fn transactions() -> Vec<Transaction> {
+	(0..SENDERS * PER_SENDER)
+		.map(|i| Transaction { hash: i, sender: i % SENDERS, nonce: i / SENDERS, gas_price: i % 97 })
+		.collect()
+}
+
+fn pool() -> Pool<Transaction, GasPrice> {
+	Pool::with_scoring(GasPrice, Options { max_count: 4096, max_per_sender: PER_SENDER as usize, ..Default::default() })
+}
+
+fn bench_import(c: &mut Criterion) {
+	c.bench_function("import_1600_single", |b| {
+		b.iter_batched(
+			|| (pool(), transactions()),
+			|(mut pool, txs)| {
+				for tx in txs {
+					pool.import(tx, &GasPrice).unwrap();
+				}
+				pool
+			},
+			BatchSize::SmallInput,
+		)
+	});
+	c.bench_function("import_1600_many", |b| {
+		b.iter_batched(
+			|| (pool(), transactions()),
+			|(mut pool, txs)| {
+				pool.import_many(txs, &GasPrice);
+				pool
+			},
+			BatchSize::SmallInput,
+		)
+	});
+}
+
+fn bench_remove(c: &mut Criterion) {
+	let full_pool = || {
+		let mut pool = pool();
+		pool.import_many(transactions(), &GasPrice);
+		pool
+	};
+	let hashes = (0..SENDERS * PER_SENDER).rev().collect::<Vec<_>>();
+
+	c.bench_function("remove_1600_single", |b| {
+		b.iter_batched(
+			full_pool,
+			|mut pool| {
+				for hash in &hashes {
+					pool.remove(hash, false);
+				}
+				pool
+			},
+			BatchSize::SmallInput,
+		)
+	});
+	c.bench_function("remove_1600_many", |b| {
+		b.iter_batched(
+			full_pool,
+			|mut pool| {
+				pool.remove_many(&hashes, false);
+				pool
+			},
+			BatchSize::SmallInput,
+		)
+	});
+}
+
+criterion_group!(benches, bench_import, bench_remove);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Selection of transactions for a block within a resource budget.
+
+use std::sync::Arc;
+
+use crate::VerifiedTransaction;
+
+/// Resources consumed by a transaction when it's included in a block.
+pub trait BlockResources: VerifiedTransaction {
+	/// Gas consumed by the transaction.
+	fn gas(&self) -> u64;
+
+	/// Size of the transaction in the block (in bytes).
+	fn size(&self) -> usize;
+
+	/// Custom, implementation-defined weight of the transaction.
+	fn weight(&self) -> u64 {
+		0
+	}
+}
+
+/// Resource budget of a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockLimits {
+	/// Maximal gas used by all transactions.
+	pub gas: u64,
+	/// Maximal size of all transactions (in bytes).
+	pub size: usize,
+	/// Maximal total weight of all transactions.
+	pub weight: u64,
+}
+
+impl Default for BlockLimits {
+	fn default() -> Self {
+		BlockLimits { gas: u64::MAX, size: usize::MAX, weight: u64::MAX }
+	}
+}
+
+/// Transactions selected for a block by `Pool::block_template`.
+#[derive(Debug)]
+pub struct BlockTemplate<T> {
+	/// Selected transactions in the order they should be included.
+	pub transactions: Vec<Arc<T>>,
+	/// Total gas used by selected transactions.
+	pub gas: u64,
+	/// Total size of selected transactions.
+	pub size: usize,
+	/// Total weight of selected transactions.
+	pub weight: u64,
+}
+
+impl<T> Default for BlockTemplate<T> {
+	fn default() -> Self {
+		BlockTemplate { transactions: Vec::new(), gas: 0, size: 0, weight: 0 }
+	}
+}
+
+impl<T: BlockResources> BlockTemplate<T> {
+	/// Adds `tx` to the template if it fits within `limits`, returns `false` otherwise.
+	pub(crate) fn push(&mut self, tx: &Arc<T>, limits: &BlockLimits) -> bool {
+		let gas = self.gas.checked_add(tx.gas()).filter(|gas| *gas <= limits.gas);
+		let size = self.size.checked_add(tx.size()).filter(|size| *size <= limits.size);
+		let weight = self.weight.checked_add(tx.weight()).filter(|weight| *weight <= limits.weight);
+
+		match (gas, size, weight) {
+			(Some(gas), Some(size), Some(weight)) => {
+				self.gas = gas;
+				self.size = size;
+				self.weight = weight;
+				self.transactions.push(tx.clone());
+				true
+			}
+			_ => false,
+		}
+	}
+}
@@ -0,0 +1,28 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::time::Instant;
+
+/// A source of time used to record insertion time of transactions and expire them.
+///
+/// Can be replaced to control the time in tests.
+pub trait Clock: fmt::Debug + Send + Sync {
+	/// Returns the current time.
+	fn now(&self) -> Instant;
+}
+
+/// A `Clock` returning the system time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
+}
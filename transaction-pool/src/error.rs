@@ -9,47 +9,111 @@
 use std::{error, fmt, result};
 
 /// Transaction Pool Error
+///
+/// `Score` is the score type of the pool's `Scoring`, so that callers get the actual
+/// threshold a rejected transaction would have to beat.
 #[derive(Debug)]
-pub enum Error<Hash: fmt::Debug + fmt::LowerHex> {
+pub enum Error<Hash: fmt::Debug + fmt::LowerHex, Score: fmt::Debug + fmt::LowerHex> {
 	/// Transaction is already imported
 	AlreadyImported(Hash),
-	/// Transaction is too cheap to enter the queue
-	TooCheapToEnter(Hash, String),
+	/// Transaction is too cheap to enter the queue when the count limit is reached.
+	TooCheapToEnter(Hash, Score),
+	/// Transaction is too cheap to enter the queue when the memory limit is reached.
+	MemoryLimitReached(Hash, Score),
+	/// Transaction is too cheap to enter the queue when the per-sender limit is reached.
+	SenderLimitReached(Hash, Score),
+	/// The pool is full and none of its transactions can be evicted.
+	PoolFull(Hash),
 	/// Transaction is too cheap to replace existing transaction that occupies the same slot.
 	TooCheapToReplace(Hash, Hash),
+	/// Transaction doesn't bump the value enough to replace or evict existing transaction.
+	InsufficientScoreBump(Hash, Hash),
+	/// Transaction sender is banned.
+	SenderBanned(Hash),
+	/// Transaction sender replaced too many transactions recently.
+	TooManyReplacements(Hash),
+	/// Transaction didn't pass verification.
+	Verification(String),
+}
+
+impl<H: fmt::Debug + fmt::LowerHex, S: fmt::Debug + fmt::LowerHex> Error<H, S> {
+	/// Returns a machine-readable code of the error.
+	pub fn code(&self) -> &'static str {
+		match self {
+			Error::AlreadyImported(_) => "already_imported",
+			Error::TooCheapToEnter(..) => "too_cheap_to_enter",
+			Error::MemoryLimitReached(..) => "memory_limit_reached",
+			Error::SenderLimitReached(..) => "sender_limit_reached",
+			Error::PoolFull(_) => "pool_full",
+			Error::TooCheapToReplace(..) => "too_cheap_to_replace",
+			Error::InsufficientScoreBump(..) => "insufficient_score_bump",
+			Error::SenderBanned(_) => "sender_banned",
+			Error::TooManyReplacements(_) => "too_many_replacements",
+			Error::Verification(_) => "verification_failed",
+		}
+	}
+
+	/// Returns the minimal score required to enter the pool, if the transaction was rejected because of a limit.
+	pub fn min_score(&self) -> Option<&S> {
+		match self {
+			Error::TooCheapToEnter(_, score)
+			| Error::MemoryLimitReached(_, score)
+			| Error::SenderLimitReached(_, score) => Some(score),
+			_ => None,
+		}
+	}
 }
 
 /// Transaction Pool Result
-pub type Result<T, H> = result::Result<T, Error<H>>;
+pub type Result<T, H, S> = result::Result<T, Error<H, S>>;
 
-impl<H: fmt::Debug + fmt::LowerHex> fmt::Display for Error<H> {
+impl<H: fmt::Debug + fmt::LowerHex, S: fmt::Debug + fmt::LowerHex> fmt::Display for Error<H, S> {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		match self {
 			Error::AlreadyImported(h) => write!(f, "[{:?}] already imported", h),
 			Error::TooCheapToEnter(hash, min_score) => {
-				write!(f, "[{:x}] too cheap to enter the pool. Min score: {}", hash, min_score)
+				write!(f, "[{:x}] too cheap to enter the pool. Min score: {:#x}", hash, min_score)
+			}
+			Error::MemoryLimitReached(hash, min_score) => {
+				write!(f, "[{:x}] too cheap to enter the pool at the memory limit. Min score: {:#x}", hash, min_score)
 			}
+			Error::SenderLimitReached(hash, min_score) => {
+				write!(f, "[{:x}] too cheap to exceed the sender limit. Min score: {:#x}", hash, min_score)
+			}
+			Error::PoolFull(hash) => write!(f, "[{:x}] the pool is full and no transaction can be evicted", hash),
 			Error::TooCheapToReplace(old_hash, hash) => write!(f, "[{:x}] too cheap to replace: {:x}", hash, old_hash),
+			Error::InsufficientScoreBump(old_hash, hash) => {
+				write!(f, "[{:x}] does not bump the score enough to replace: {:x}", hash, old_hash)
+			}
+			Error::SenderBanned(hash) => write!(f, "[{:x}] sender is banned", hash),
+			Error::TooManyReplacements(hash) => write!(f, "[{:x}] sender replaced too many transactions", hash),
+			Error::Verification(err) => write!(f, "verification failed: {}", err),
 		}
 	}
 }
 
-impl<H: fmt::Debug + fmt::LowerHex> error::Error for Error<H> {}
+impl<H: fmt::Debug + fmt::LowerHex, S: fmt::Debug + fmt::LowerHex> error::Error for Error<H, S> {}
 
 #[cfg(test)]
-impl<H: fmt::Debug + fmt::LowerHex> PartialEq for Error<H>
+impl<H: fmt::Debug + fmt::LowerHex, S: fmt::Debug + fmt::LowerHex> PartialEq for Error<H, S>
 where
 	H: PartialEq,
+	S: PartialEq,
 {
 	fn eq(&self, other: &Self) -> bool {
 		use self::Error::*;
 
 		match (self, other) {
-			(&AlreadyImported(ref h1), &AlreadyImported(ref h2)) => h1 == h2,
-			(&TooCheapToEnter(ref h1, ref s1), &TooCheapToEnter(ref h2, ref s2)) => h1 == h2 && s1 == s2,
-			(&TooCheapToReplace(ref old1, ref new1), &TooCheapToReplace(ref old2, ref new2)) => {
-				old1 == old2 && new1 == new2
-			}
+			(AlreadyImported(h1), AlreadyImported(h2)) => h1 == h2,
+			(TooCheapToEnter(h1, s1), TooCheapToEnter(h2, s2)) => h1 == h2 && s1 == s2,
+			(MemoryLimitReached(h1, s1), MemoryLimitReached(h2, s2)) => h1 == h2 && s1 == s2,
+			(SenderLimitReached(h1, s1), SenderLimitReached(h2, s2)) => h1 == h2 && s1 == s2,
+			(PoolFull(h1), PoolFull(h2)) => h1 == h2,
+			(TooCheapToReplace(old1, new1), TooCheapToReplace(old2, new2)) => old1 == old2 && new1 == new2,
+			(InsufficientScoreBump(old1, new1), InsufficientScoreBump(old2, new2)) => old1 == old2 && new1 == new2,
+			(SenderBanned(h1), SenderBanned(h2)) => h1 == h2,
+			(TooManyReplacements(h1), TooManyReplacements(h2)) => h1 == h2,
+			(Verification(e1), Verification(e2)) => e1 == e2,
 			_ => false,
 		}
 	}
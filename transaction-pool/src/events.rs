@@ -0,0 +1,349 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A `Listener` publishing pool events to asynchronous subscribers.
+//!
+//! Every subscriber has its own bounded buffer. When a subscriber doesn't keep up the oldest
+//! buffered events are discarded and the subscriber is told how many events it missed,
+//! so a slow subscriber never blocks the pool or other subscribers.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Debug, LowerHex};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+use parking_lot::Mutex;
+
+use crate::{error::Error, listener::Listener, VerifiedTransaction};
+
+/// An event of the pool.
+pub enum Event<T: VerifiedTransaction> {
+	/// The transaction has been added to the pool.
+	Added(Arc<T>),
+	/// The transaction `new` took place of `old` transaction from the same sender.
+	Replaced {
+		/// Transaction removed from the pool.
+		old: Arc<T>,
+		/// Transaction added to the pool.
+		new: Arc<T>,
+	},
+	/// The transaction was pushed out from the pool, `by` is the hash of the transaction
+	/// which took its place (if any).
+	Dropped {
+		/// Transaction removed from the pool.
+		transaction: Arc<T>,
+		/// Hash of the transaction which pushed it out.
+		by: Option<T::Hash>,
+	},
+	/// The transaction was rejected from the pool.
+	Rejected {
+		/// Rejected transaction.
+		transaction: Arc<T>,
+		/// Machine-readable reason of the rejection (see `Error::code`).
+		code: &'static str,
+		/// Description of the rejection.
+		reason: String,
+	},
+	/// The transaction was marked as invalid.
+	Invalid(Arc<T>),
+	/// The transaction has been canceled.
+	Canceled(Arc<T>),
+	/// The transaction has been culled from the pool.
+	Culled(Arc<T>),
+	/// The transaction has been in the pool for too long and was removed.
+	Expired(Arc<T>),
+	/// The transaction has been included in a block and removed from the pool.
+	Mined(Arc<T>),
+}
+
+impl<T: VerifiedTransaction> Event<T> {
+	/// Returns the transaction this event is about.
+	///
+	/// For `Replaced` it's the new transaction.
+	pub fn transaction(&self) -> &Arc<T> {
+		match self {
+			Event::Added(tx)
+			| Event::Replaced { new: tx, .. }
+			| Event::Dropped { transaction: tx, .. }
+			| Event::Rejected { transaction: tx, .. }
+			| Event::Invalid(tx)
+			| Event::Canceled(tx)
+			| Event::Culled(tx)
+			| Event::Expired(tx)
+			| Event::Mined(tx) => tx,
+		}
+	}
+
+	/// Returns true if the event is relevant to the transaction with given `hash`.
+	fn concerns(&self, hash: &T::Hash) -> bool {
+		match self {
+			Event::Replaced { old, new } => old.hash() == hash || new.hash() == hash,
+			event => event.transaction().hash() == hash,
+		}
+	}
+}
+
+impl<T: VerifiedTransaction> Clone for Event<T> {
+	fn clone(&self) -> Self {
+		match self {
+			Event::Added(tx) => Event::Added(tx.clone()),
+			Event::Replaced { old, new } => Event::Replaced { old: old.clone(), new: new.clone() },
+			Event::Dropped { transaction, by } => Event::Dropped { transaction: transaction.clone(), by: by.clone() },
+			Event::Rejected { transaction, code, reason } => {
+				Event::Rejected { transaction: transaction.clone(), code, reason: reason.clone() }
+			}
+			Event::Invalid(tx) => Event::Invalid(tx.clone()),
+			Event::Canceled(tx) => Event::Canceled(tx.clone()),
+			Event::Culled(tx) => Event::Culled(tx.clone()),
+			Event::Expired(tx) => Event::Expired(tx.clone()),
+			Event::Mined(tx) => Event::Mined(tx.clone()),
+		}
+	}
+}
+
+impl<T: VerifiedTransaction> fmt::Debug for Event<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Event::Added(tx) => write!(f, "Added({:?})", tx.hash()),
+			Event::Replaced { old, new } => write!(f, "Replaced({:?} -> {:?})", old.hash(), new.hash()),
+			Event::Dropped { transaction, by } => write!(f, "Dropped({:?}, by: {:?})", transaction.hash(), by),
+			Event::Rejected { transaction, reason, .. } => write!(f, "Rejected({:?}: {})", transaction.hash(), reason),
+			Event::Invalid(tx) => write!(f, "Invalid({:?})", tx.hash()),
+			Event::Canceled(tx) => write!(f, "Canceled({:?})", tx.hash()),
+			Event::Culled(tx) => write!(f, "Culled({:?})", tx.hash()),
+			Event::Expired(tx) => write!(f, "Expired({:?})", tx.hash()),
+			Event::Mined(tx) => write!(f, "Mined({:?})", tx.hash()),
+		}
+	}
+}
+
+/// The subscriber didn't keep up and the given number of events were discarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lagged(pub u64);
+
+impl fmt::Display for Lagged {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "subscriber lagged behind by {} events", self.0)
+	}
+}
+
+impl std::error::Error for Lagged {}
+
+struct Slot<T: VerifiedTransaction> {
+	filter: Option<T::Hash>,
+	queue: VecDeque<Event<T>>,
+	lagged: u64,
+	waker: Option<Waker>,
+}
+
+struct Hub<T: VerifiedTransaction> {
+	capacity: usize,
+	closed: bool,
+	next_id: u64,
+	slots: HashMap<u64, Slot<T>>,
+}
+
+/// A `Listener` publishing pool events to any number of `Subscription`s.
+///
+/// Subscribers are closed (their `recv` resolves to `None` once the buffer is drained)
+/// when the `EventStream` is dropped along with the pool.
+pub struct EventStream<T: VerifiedTransaction> {
+	hub: Arc<Mutex<Hub<T>>>,
+}
+
+impl<T: VerifiedTransaction> EventStream<T> {
+	/// Creates a new stream buffering at most `capacity` events per subscriber.
+	pub fn new(capacity: usize) -> Self {
+		let hub = Hub { capacity: capacity.max(1), closed: false, next_id: 0, slots: HashMap::new() };
+		EventStream { hub: Arc::new(Mutex::new(hub)) }
+	}
+
+	/// Subscribes to all events of the pool.
+	pub fn subscribe(&self) -> Subscription<T> {
+		self.add_slot(None)
+	}
+
+	/// Subscribes to the events of the transaction with given `hash`.
+	///
+	/// `Replaced` events are delivered both to the subscribers of the old and the new transaction.
+	pub fn subscribe_hash(&self, hash: T::Hash) -> Subscription<T> {
+		self.add_slot(Some(hash))
+	}
+
+	/// Returns the number of active subscriptions.
+	pub fn subscribers(&self) -> usize {
+		self.hub.lock().slots.len()
+	}
+
+	fn add_slot(&self, filter: Option<T::Hash>) -> Subscription<T> {
+		let mut hub = self.hub.lock();
+		let id = hub.next_id;
+		hub.next_id += 1;
+		hub.slots.insert(id, Slot { filter, queue: VecDeque::new(), lagged: 0, waker: None });
+		Subscription { id, hub: self.hub.clone() }
+	}
+
+	fn publish(&mut self, event: Event<T>) {
+		let mut hub = self.hub.lock();
+		let capacity = hub.capacity;
+		for slot in hub.slots.values_mut() {
+			if let Some(ref hash) = slot.filter {
+				if !event.concerns(hash) {
+					continue;
+				}
+			}
+			if slot.queue.len() >= capacity {
+				slot.queue.pop_front();
+				slot.lagged += 1;
+			}
+			slot.queue.push_back(event.clone());
+			if let Some(waker) = slot.waker.take() {
+				waker.wake();
+			}
+		}
+	}
+}
+
+impl<T: VerifiedTransaction> fmt::Debug for EventStream<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("EventStream").field("subscribers", &self.subscribers()).finish()
+	}
+}
+
+impl<T: VerifiedTransaction> Drop for EventStream<T> {
+	fn drop(&mut self) {
+		let mut hub = self.hub.lock();
+		hub.closed = true;
+		for slot in hub.slots.values_mut() {
+			if let Some(waker) = slot.waker.take() {
+				waker.wake();
+			}
+		}
+	}
+}
+
+impl<T: VerifiedTransaction> Listener<T> for EventStream<T> {
+	fn added(&mut self, tx: &Arc<T>, old: Option<&Arc<T>>) {
+		let event = match old {
+			Some(old) => Event::Replaced { old: old.clone(), new: tx.clone() },
+			None => Event::Added(tx.clone()),
+		};
+		self.publish(event);
+	}
+
+	fn rejected<H: Debug + LowerHex, S: Debug + LowerHex>(&mut self, tx: &Arc<T>, reason: &Error<H, S>) {
+		self.publish(Event::Rejected { transaction: tx.clone(), code: reason.code(), reason: reason.to_string() });
+	}
+
+	fn dropped(&mut self, tx: &Arc<T>, by: Option<&T>) {
+		self.publish(Event::Dropped { transaction: tx.clone(), by: by.map(|by| by.hash().clone()) });
+	}
+
+	fn invalid(&mut self, tx: &Arc<T>) {
+		self.publish(Event::Invalid(tx.clone()));
+	}
+
+	fn canceled(&mut self, tx: &Arc<T>) {
+		self.publish(Event::Canceled(tx.clone()));
+	}
+
+	fn culled(&mut self, tx: &Arc<T>) {
+		self.publish(Event::Culled(tx.clone()));
+	}
+
+	fn expired(&mut self, tx: &Arc<T>) {
+		self.publish(Event::Expired(tx.clone()));
+	}
+
+	fn mined(&mut self, tx: &Arc<T>) {
+		self.publish(Event::Mined(tx.clone()));
+	}
+}
+
+/// A subscription to the events of an `EventStream`.
+pub struct Subscription<T: VerifiedTransaction> {
+	id: u64,
+	hub: Arc<Mutex<Hub<T>>>,
+}
+
+impl<T: VerifiedTransaction> Subscription<T> {
+	/// Returns a future resolving to the next event.
+	///
+	/// Resolves to `Some(Err(Lagged(n)))` if `n` events were discarded since the last call,
+	/// and to `None` once the stream is closed and all buffered events were received.
+	pub fn recv(&mut self) -> Recv<'_, T> {
+		Recv { subscription: self }
+	}
+
+	/// Returns the next buffered event without waiting.
+	///
+	/// Returns `None` if there are no buffered events.
+	pub fn try_next(&mut self) -> Option<Result<Event<T>, Lagged>> {
+		let mut hub = self.hub.lock();
+		let slot = hub.slots.get_mut(&self.id).expect("Slot is removed only when subscription is dropped; qed");
+		Self::take(slot)
+	}
+
+	/// Returns true if the stream was dropped.
+	pub fn is_closed(&self) -> bool {
+		self.hub.lock().closed
+	}
+
+	fn take(slot: &mut Slot<T>) -> Option<Result<Event<T>, Lagged>> {
+		if slot.lagged > 0 {
+			let lagged = slot.lagged;
+			slot.lagged = 0;
+			return Some(Err(Lagged(lagged)));
+		}
+		slot.queue.pop_front().map(Ok)
+	}
+}
+
+impl<T: VerifiedTransaction> fmt::Debug for Subscription<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Subscription").field("id", &self.id).finish()
+	}
+}
+
+impl<T: VerifiedTransaction> Drop for Subscription<T> {
+	fn drop(&mut self) {
+		self.hub.lock().slots.remove(&self.id);
+	}
+}
+
+/// A future returned by `Subscription::recv`.
+pub struct Recv<'a, T: VerifiedTransaction> {
+	subscription: &'a mut Subscription<T>,
+}
+
+impl<'a, T: VerifiedTransaction> fmt::Debug for Recv<'a, T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Recv").field("subscription", &self.subscription).finish()
+	}
+}
+
+impl<'a, T: VerifiedTransaction> Future for Recv<'a, T> {
+	type Output = Option<Result<Event<T>, Lagged>>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let id = self.subscription.id;
+		let mut hub = self.subscription.hub.lock();
+		let closed = hub.closed;
+		let slot = hub.slots.get_mut(&id).expect("Slot is removed only when subscription is dropped; qed");
+		match Subscription::take(slot) {
+			Some(next) => Poll::Ready(Some(next)),
+			None if closed => Poll::Ready(None),
+			None => {
+				slot.waker = Some(cx.waker().clone());
+				Poll::Pending
+			}
+		}
+	}
+}
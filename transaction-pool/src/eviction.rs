@@ -0,0 +1,126 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Policies choosing which transaction to evict when the pool is full.
+//!
+//! The pool can only evict the worst transaction of a sender (so that the remaining
+//! transactions of the sender stay valid), the policy decides which sender loses it.
+//! Policies are set separately for the count and the memory limit, see
+//! `Pool::with_count_eviction` and `Pool::with_memory_eviction`.
+
+use std::fmt;
+
+use crate::{pool::Transaction, VerifiedTransaction};
+
+/// A transaction which may be evicted from the pool.
+#[derive(Debug)]
+pub struct Candidate<'a, T, S> {
+	/// The transaction, which is the worst transaction of its sender.
+	pub transaction: &'a Transaction<T>,
+	/// Score of the transaction.
+	pub score: &'a S,
+	/// Number of transactions of the sender in the pool.
+	pub sender_count: usize,
+}
+
+impl<'a, T, S> Clone for Candidate<'a, T, S> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<'a, T, S> Copy for Candidate<'a, T, S> {}
+
+/// Chooses the transaction to evict when a limit of the pool is reached.
+pub trait EvictionPolicy<T, S>: fmt::Debug + Send + Sync {
+	/// Selects the transaction to evict.
+	///
+	/// `candidates` yields the worst transaction of every sender ordered from the worst score
+	/// to the best one, local transactions are never yielded.
+	/// Returning `None` rejects the new transaction as there is nothing to evict.
+	///
+	/// The selected transaction is still subject to `ShouldReplace`.
+	fn select<'a>(&self, candidates: &mut dyn Iterator<Item = Candidate<'a, T, S>>) -> Option<Candidate<'a, T, S>>;
+}
+
+/// Evicts the transaction with the worst score.
+///
+/// The default policy for both limits.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WorstScore;
+
+impl<T, S> EvictionPolicy<T, S> for WorstScore {
+	fn select<'a>(&self, candidates: &mut dyn Iterator<Item = Candidate<'a, T, S>>) -> Option<Candidate<'a, T, S>> {
+		candidates.next()
+	}
+}
+
+/// Evicts the transaction which was inserted to the pool first.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OldestFirst;
+
+impl<T, S> EvictionPolicy<T, S> for OldestFirst {
+	fn select<'a>(&self, candidates: &mut dyn Iterator<Item = Candidate<'a, T, S>>) -> Option<Candidate<'a, T, S>> {
+		candidates.min_by_key(|candidate| candidate.transaction.insertion_id)
+	}
+}
+
+type ScoreValue<S> = Box<dyn Fn(&S) -> u128 + Send + Sync>;
+
+/// Evicts the transaction with the largest memory usage per unit of score.
+///
+/// Suits the memory limit, where a single large and cheap transaction may take the place
+/// of many valuable ones. Ties are broken by the worst score.
+pub struct LargestMemoryPerScore<S> {
+	/// Returns the numeric value of a score.
+	pub value: ScoreValue<S>,
+}
+
+impl<S> LargestMemoryPerScore<S> {
+	/// Creates a new policy using `value` to convert scores to numbers.
+	pub fn new<F: Fn(&S) -> u128 + Send + Sync + 'static>(value: F) -> Self {
+		LargestMemoryPerScore { value: Box::new(value) }
+	}
+}
+
+impl<S> fmt::Debug for LargestMemoryPerScore<S> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("LargestMemoryPerScore").finish()
+	}
+}
+
+impl<T: VerifiedTransaction, S> EvictionPolicy<T, S> for LargestMemoryPerScore<S> {
+	fn select<'a>(&self, candidates: &mut dyn Iterator<Item = Candidate<'a, T, S>>) -> Option<Candidate<'a, T, S>> {
+		let mem = |candidate: &Candidate<'a, T, S>| candidate.transaction.mem_usage() as u128;
+		candidates.fold(None, |selected, candidate| match selected {
+			// compare `mem / value` without dividing, a zero value means infinitely large ratio.
+			Some(selected)
+				if mem(&selected).saturating_mul((self.value)(candidate.score))
+					>= mem(&candidate).saturating_mul((self.value)(selected.score)) =>
+			{
+				Some(selected)
+			}
+			_ => Some(candidate),
+		})
+	}
+}
+
+/// Evicts the worst transaction of the sender with the most transactions in the pool.
+///
+/// Ties are broken by the worst score.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FairShare;
+
+impl<T, S> EvictionPolicy<T, S> for FairShare {
+	fn select<'a>(&self, candidates: &mut dyn Iterator<Item = Candidate<'a, T, S>>) -> Option<Candidate<'a, T, S>> {
+		candidates.fold(None, |selected, candidate| match selected {
+			Some(selected) if selected.sender_count >= candidate.sender_count => Some(selected),
+			_ => Some(candidate),
+		})
+	}
+}
@@ -0,0 +1,142 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Fee market aware scoring of transactions paying a base fee and a tip (EIP-1559).
+
+use std::{cmp, fmt};
+
+use crate::{
+	pool::Transaction,
+	replace::{ReplaceTransaction, ShouldReplace},
+	scoring::{Change, Choice, ParametrizedScoring, Scoring},
+};
+
+/// A transaction paying fees in a market with a base fee.
+///
+/// Legacy transactions can return their gas price as both fees.
+pub trait FeeMarketTransaction {
+	/// Nonce of the transaction, used to order transactions of a sender.
+	fn nonce(&self) -> u64;
+
+	/// Maximal fee per gas the sender is willing to pay, including the base fee.
+	fn max_fee_per_gas(&self) -> u128;
+
+	/// Maximal fee per gas paid to the block producer on top of the base fee.
+	fn max_priority_fee_per_gas(&self) -> u128;
+}
+
+/// Score of a transaction in the fee market.
+///
+/// Transactions are ordered by the effective tip at the current base fee first and
+/// by the maximal fee second, as transactions with a higher maximal fee survive base fee rises.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FeeScore {
+	/// Fee per gas paid to the block producer at the current base fee.
+	pub effective_tip: u128,
+	/// Maximal fee per gas of the transaction.
+	pub max_fee: u128,
+}
+
+impl fmt::LowerHex for FeeScore {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::LowerHex::fmt(&self.effective_tip, f)
+	}
+}
+
+/// Scoring of `FeeMarketTransaction`s by their effective tip at the current base fee.
+///
+/// The base fee is set with `Pool::set_scoring_parameter`. Transactions which can't pay the base fee
+/// have no effective tip and are the first to be evicted.
+///
+/// A transaction replaces another one with the same nonce only if both of its fees are higher.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FeeMarketScoring {
+	base_fee: u128,
+}
+
+impl FeeMarketScoring {
+	/// Creates a new scoring with the given base fee.
+	pub fn new(base_fee: u128) -> Self {
+		FeeMarketScoring { base_fee }
+	}
+
+	/// Returns the current base fee.
+	pub fn base_fee(&self) -> u128 {
+		self.base_fee
+	}
+
+	/// Computes the score of a transaction at the current base fee.
+	pub fn score<T: FeeMarketTransaction>(&self, tx: &T) -> FeeScore {
+		let max_fee = tx.max_fee_per_gas();
+		FeeScore {
+			effective_tip: cmp::min(max_fee.saturating_sub(self.base_fee), tx.max_priority_fee_per_gas()),
+			max_fee,
+		}
+	}
+}
+
+impl<T: FeeMarketTransaction + fmt::Debug> Scoring<T> for FeeMarketScoring {
+	type Score = FeeScore;
+	type Event = ();
+
+	fn compare(&self, old: &T, other: &T) -> cmp::Ordering {
+		old.nonce().cmp(&other.nonce())
+	}
+
+	fn choose(&self, old: &T, new: &T) -> Choice {
+		if old.nonce() != new.nonce() {
+			Choice::InsertNew
+		} else if new.max_fee_per_gas() > old.max_fee_per_gas()
+			&& new.max_priority_fee_per_gas() > old.max_priority_fee_per_gas()
+		{
+			Choice::ReplaceOld
+		} else {
+			Choice::RejectNew
+		}
+	}
+
+	fn update_scores(&self, txs: &[Transaction<T>], scores: &mut [FeeScore], change: Change) {
+		match change {
+			Change::InsertedAt(i) | Change::ReplacedAt(i) => scores[i] = self.score(&*txs[i].transaction),
+			Change::Event(()) => {
+				self.rescore(txs, scores);
+			}
+			Change::RemovedAt(_) | Change::Culled(_) => {}
+		}
+	}
+}
+
+impl<T: FeeMarketTransaction + fmt::Debug> ParametrizedScoring<T> for FeeMarketScoring {
+	type Parameter = u128;
+
+	fn set_parameter(&mut self, base_fee: u128) -> bool {
+		let changed = self.base_fee != base_fee;
+		self.base_fee = base_fee;
+		changed
+	}
+
+	fn rescore(&self, txs: &[Transaction<T>], scores: &mut [FeeScore]) -> bool {
+		let mut changed = false;
+		for (score, tx) in scores.iter_mut().zip(txs) {
+			let new = self.score(&*tx.transaction);
+			changed |= *score != new;
+			*score = new;
+		}
+		changed
+	}
+}
+
+impl<T: FeeMarketTransaction> ShouldReplace<T> for FeeMarketScoring {
+	fn should_replace(&self, old: &ReplaceTransaction<'_, T>, new: &ReplaceTransaction<'_, T>) -> Choice {
+		if self.score(&*new.transaction.transaction) > self.score(&*old.transaction.transaction) {
+			Choice::ReplaceOld
+		} else {
+			Choice::RejectNew
+		}
+	}
+}
@@ -0,0 +1,88 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Versioned history of the pool contents.
+
+use std::collections::{hash_map, HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::hash::Hash;
+
+/// Transactions added to and removed from the pool since a version.
+///
+/// See `Pool::changes_since`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Changes<H> {
+	/// Current version of the pool, to be passed to the next `changes_since` call.
+	pub version: u64,
+	/// Transactions added since the version and still in the pool, oldest first.
+	pub added: Vec<H>,
+	/// Transactions which were in the pool at the version and are not anymore, oldest first.
+	pub removed: Vec<H>,
+}
+
+/// Most recent additions and removals of transactions.
+#[derive(Debug)]
+pub(crate) struct History<H> {
+	version: u64,
+	window: usize,
+	/// Hashes along with `true` if added, the last entry is at `version`.
+	entries: VecDeque<(H, bool)>,
+}
+
+impl<H: Clone + Eq + Hash> History<H> {
+	pub(crate) fn new(window: usize) -> Self {
+		History { version: 0, window, entries: VecDeque::with_capacity(window) }
+	}
+
+	pub(crate) fn version(&self) -> u64 {
+		self.version
+	}
+
+	pub(crate) fn record(&mut self, hash: &H, added: bool) {
+		self.version += 1;
+		if self.window == 0 {
+			return;
+		}
+		if self.entries.len() == self.window {
+			self.entries.pop_front();
+		}
+		self.entries.push_back((hash.clone(), added));
+	}
+
+	/// Returns the net changes since `version` or `None` if it's outside of the window.
+	pub(crate) fn changes_since(&self, version: u64) -> Option<Changes<H>> {
+		let count = usize::try_from(self.version.checked_sub(version)?).ok()?;
+		if count > self.entries.len() {
+			return None;
+		}
+
+		// first and last operation of every hash, in order of the first one.
+		let mut order = Vec::new();
+		let mut net = HashMap::<&H, (bool, bool)>::new();
+		for (hash, added) in self.entries.iter().skip(self.entries.len() - count) {
+			match net.entry(hash) {
+				hash_map::Entry::Occupied(mut entry) => entry.get_mut().1 = *added,
+				hash_map::Entry::Vacant(entry) => {
+					order.push(hash);
+					entry.insert((*added, *added));
+				}
+			}
+		}
+
+		let mut changes = Changes { version: self.version, added: Vec::new(), removed: Vec::new() };
+		for hash in order {
+			match net[hash] {
+				(true, true) => changes.added.push(hash.clone()),
+				(false, false) => changes.removed.push(hash.clone()),
+				// removed and added back or added and removed again
+				_ => {}
+			}
+		}
+		Some(changes)
+	}
+}
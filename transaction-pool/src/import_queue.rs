@@ -0,0 +1,301 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Asynchronous import of unverified transactions into a `SharedPool`.
+//!
+//! Transactions submitted to the queue are picked up by a background thread in batches.
+//! A batch is verified without holding the pool lock and the verified transactions
+//! are then imported at once, publishing a single new view of the pool.
+//!
+//! The result of every submitted transaction can be awaited with its `ImportHandle`.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{mpsc, Arc};
+use std::task::{Context, Poll, Waker};
+use std::{fmt, thread};
+
+use log::warn;
+use parking_lot::{Condvar, Mutex};
+
+use crate::{
+	error, listener::Listener, replace::ShouldReplace, scoring::Scoring, shared::SharedPool, verifier::Verifier,
+	VerifiedTransaction,
+};
+
+/// Import queue options.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportQueueOptions {
+	/// Maximal number of transactions verified and imported together.
+	pub max_batch_size: usize,
+}
+
+impl Default for ImportQueueOptions {
+	fn default() -> Self {
+		ImportQueueOptions { max_batch_size: 256 }
+	}
+}
+
+/// An error of a transaction submitted to the `ImportQueue`.
+#[derive(Debug)]
+pub enum ImportError<E, H: fmt::Debug + fmt::LowerHex, S: fmt::Debug + fmt::LowerHex> {
+	/// The transaction didn't pass verification.
+	Verification(E),
+	/// The transaction was verified but rejected by the pool.
+	Pool(error::Error<H, S>),
+	/// The queue was shut down before the transaction was imported.
+	Canceled,
+}
+
+impl<E, H, S> fmt::Display for ImportError<E, H, S>
+where
+	E: fmt::Display,
+	H: fmt::Debug + fmt::LowerHex,
+	S: fmt::Debug + fmt::LowerHex,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ImportError::Verification(err) => write!(f, "verification failed: {}", err),
+			ImportError::Pool(err) => write!(f, "{}", err),
+			ImportError::Canceled => write!(f, "import queue shut down"),
+		}
+	}
+}
+
+impl<E, H, S> std::error::Error for ImportError<E, H, S>
+where
+	E: fmt::Debug + fmt::Display,
+	H: fmt::Debug + fmt::LowerHex,
+	S: fmt::Debug + fmt::LowerHex,
+{
+}
+
+type ImportResult<T, E, S> = Result<Arc<T>, ImportError<E, <T as VerifiedTransaction>::Hash, S>>;
+
+struct Slot<R> {
+	result: Option<R>,
+	/// Set once the result is sent or the request is dropped.
+	closed: bool,
+	waker: Option<Waker>,
+}
+
+/// State shared by an `ImportHandle` and the `Responder` of its request.
+struct Shared<R> {
+	slot: Mutex<Slot<R>>,
+	ready: Condvar,
+}
+
+impl<R> Shared<R> {
+	fn close(&self, result: Option<R>) {
+		let mut slot = self.slot.lock();
+		if slot.closed {
+			return;
+		}
+		slot.result = result;
+		slot.closed = true;
+		if let Some(waker) = slot.waker.take() {
+			waker.wake();
+		}
+		self.ready.notify_all();
+	}
+}
+
+/// Sends the result of a request to its `ImportHandle`.
+///
+/// Dropping the responder without sending a result cancels the request.
+struct Responder<R> {
+	shared: Arc<Shared<R>>,
+}
+
+impl<R> Responder<R> {
+	fn send(self, result: R) {
+		self.shared.close(Some(result));
+	}
+}
+
+impl<R> Drop for Responder<R> {
+	fn drop(&mut self) {
+		self.shared.close(None);
+	}
+}
+
+/// A pending result of a transaction submitted to the `ImportQueue`.
+///
+/// The handle is a `Future` resolving once the transaction is imported (or rejected),
+/// `wait` and `try_wait` are provided for synchronous callers.
+pub struct ImportHandle<T: VerifiedTransaction, E, S: fmt::Debug + fmt::LowerHex> {
+	shared: Arc<Shared<ImportResult<T, E, S>>>,
+}
+
+impl<T: VerifiedTransaction, E, S: fmt::Debug + fmt::LowerHex> ImportHandle<T, E, S> {
+	fn new() -> (Self, Responder<ImportResult<T, E, S>>) {
+		let slot = Slot { result: None, closed: false, waker: None };
+		let shared = Arc::new(Shared { slot: Mutex::new(slot), ready: Condvar::new() });
+		(ImportHandle { shared: shared.clone() }, Responder { shared })
+	}
+
+	/// Blocks until the transaction is imported (or rejected).
+	pub fn wait(self) -> ImportResult<T, E, S> {
+		let mut slot = self.shared.slot.lock();
+		while !slot.closed {
+			self.shared.ready.wait(&mut slot);
+		}
+		slot.result.take().unwrap_or(Err(ImportError::Canceled))
+	}
+
+	/// Returns the result if the transaction was already processed, `None` otherwise.
+	pub fn try_wait(&self) -> Option<ImportResult<T, E, S>> {
+		let mut slot = self.shared.slot.lock();
+		if slot.closed {
+			Some(slot.result.take().unwrap_or(Err(ImportError::Canceled)))
+		} else {
+			None
+		}
+	}
+}
+
+impl<T: VerifiedTransaction, E, S: fmt::Debug + fmt::LowerHex> fmt::Debug for ImportHandle<T, E, S> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ImportHandle").field("closed", &self.shared.slot.lock().closed).finish()
+	}
+}
+
+impl<T: VerifiedTransaction, E, S: fmt::Debug + fmt::LowerHex> Future for ImportHandle<T, E, S> {
+	type Output = ImportResult<T, E, S>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let mut slot = self.shared.slot.lock();
+		if slot.closed {
+			Poll::Ready(slot.result.take().unwrap_or(Err(ImportError::Canceled)))
+		} else {
+			slot.waker = Some(cx.waker().clone());
+			Poll::Pending
+		}
+	}
+}
+
+type Request<U, V, S> =
+	(U, Responder<ImportResult<<V as Verifier<U>>::VerifiedTransaction, <V as Verifier<U>>::Error, S>>);
+
+/// A queue verifying and importing transactions into a `SharedPool` on a background thread.
+///
+/// `Score` is the score type of the pool's `Scoring`.
+///
+/// Dropping the queue waits for all the already submitted transactions to be processed.
+pub struct ImportQueue<U, V: Verifier<U>, Score: fmt::Debug + fmt::LowerHex> {
+	requests: Option<mpsc::Sender<Request<U, V, Score>>>,
+	worker: Option<thread::JoinHandle<()>>,
+}
+
+impl<U, V, Score> ImportQueue<U, V, Score>
+where
+	U: Send + 'static,
+	V: Verifier<U> + Send + 'static,
+	V::Error: Send + 'static,
+	V::VerifiedTransaction: Send + Sync + 'static,
+	<V::VerifiedTransaction as VerifiedTransaction>::Hash: Send,
+	Score: fmt::Debug + fmt::LowerHex + Send + 'static,
+{
+	/// Creates a new queue importing transactions verified by `verifier` into `pool`.
+	///
+	/// `replace` decides about evictions when the pool is full (see `Pool::import`).
+	pub fn new<S, L, R>(
+		pool: Arc<SharedPool<V::VerifiedTransaction, S, L>>,
+		verifier: V,
+		replace: R,
+		options: ImportQueueOptions,
+	) -> Self
+	where
+		S: Scoring<V::VerifiedTransaction, Score = Score> + Send + 'static,
+		S::Score: Sync,
+		L: Listener<V::VerifiedTransaction> + Send + 'static,
+		R: ShouldReplace<V::VerifiedTransaction> + Send + 'static,
+		<V::VerifiedTransaction as VerifiedTransaction>::Hash: Sync,
+		<V::VerifiedTransaction as VerifiedTransaction>::Sender: Sync,
+	{
+		let (requests, incoming) = mpsc::channel();
+		let max_batch_size = options.max_batch_size.max(1);
+		let worker = thread::Builder::new()
+			.name("tx-import-queue".into())
+			.spawn(move || run(incoming, &pool, &verifier, &replace, max_batch_size))
+			.expect("Failed to spawn import queue thread");
+
+		ImportQueue { requests: Some(requests), worker: Some(worker) }
+	}
+
+	/// Submits a transaction for verification and import, returning a handle to its result.
+	pub fn submit(&self, transaction: U) -> ImportHandle<V::VerifiedTransaction, V::Error, Score> {
+		let (handle, responder) = ImportHandle::new();
+		if let Some(requests) = self.requests.as_ref() {
+			// If the worker is gone the responder is dropped and the transaction reported as canceled.
+			let _ = requests.send((transaction, responder));
+		}
+		handle
+	}
+}
+
+impl<U, V: Verifier<U>, Score: fmt::Debug + fmt::LowerHex> fmt::Debug for ImportQueue<U, V, Score> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ImportQueue").finish()
+	}
+}
+
+impl<U, V: Verifier<U>, Score: fmt::Debug + fmt::LowerHex> Drop for ImportQueue<U, V, Score> {
+	fn drop(&mut self) {
+		self.requests.take();
+		if let Some(worker) = self.worker.take() {
+			if worker.join().is_err() {
+				warn!("Import queue thread panicked.");
+			}
+		}
+	}
+}
+
+fn run<U, V, S, L, R>(
+	incoming: mpsc::Receiver<Request<U, V, S::Score>>,
+	pool: &SharedPool<V::VerifiedTransaction, S, L>,
+	verifier: &V,
+	replace: &R,
+	max_batch_size: usize,
+) where
+	V: Verifier<U>,
+	S: Scoring<V::VerifiedTransaction>,
+	L: Listener<V::VerifiedTransaction>,
+	R: ShouldReplace<V::VerifiedTransaction>,
+{
+	while let Ok(first) = incoming.recv() {
+		let mut batch = vec![first];
+		while batch.len() < max_batch_size {
+			match incoming.try_recv() {
+				Ok(request) => batch.push(request),
+				Err(_) => break,
+			}
+		}
+
+		// Verify the whole batch before touching the pool.
+		let mut verified = Vec::with_capacity(batch.len());
+		for (transaction, result) in batch {
+			match verifier.verify_transaction(transaction) {
+				Ok(transaction) => verified.push((transaction, result)),
+				Err(err) => {
+					result.send(Err(ImportError::Verification(err)));
+				}
+			}
+		}
+
+		if verified.is_empty() {
+			continue;
+		}
+
+		let (transactions, results): (Vec<_>, Vec<_>) = verified.into_iter().unzip();
+		let imported = pool.import_batch(transactions, replace);
+		for (imported, result) in imported.into_iter().zip(results) {
+			result.send(imported.map_err(ImportError::Pool));
+		}
+	}
+}
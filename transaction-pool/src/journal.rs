@@ -0,0 +1,294 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Persistence of pooled transactions across restarts.
+//!
+//! The `Journal` serializes every transaction in the pool with a user-supplied `Codec` and
+//! writes the records to a `JournalStore`. On startup the records are decoded, verified with
+//! a `Verifier` and imported back into the pool. Transactions from a single sender are saved
+//! in the pool's order, so they can be re-imported one after another.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, warn};
+
+use crate::{
+	listener::Listener,
+	pool::Pool,
+	ready::Readiness,
+	replace::ShouldReplace,
+	scoring::Scoring,
+	shared::{PoolView, SharedPool},
+	verifier::Verifier,
+	VerifiedTransaction,
+};
+
+/// Serialization of pooled transactions.
+pub trait Codec<T> {
+	/// Transaction decoded from the journal, verified before it's imported to the pool.
+	type Unverified;
+	/// Decoding error.
+	type Error: fmt::Debug;
+
+	/// Encodes a pooled transaction.
+	fn encode(&self, tx: &T) -> Vec<u8>;
+
+	/// Decodes a transaction previously encoded with `encode`.
+	fn decode(&self, bytes: &[u8]) -> Result<Self::Unverified, Self::Error>;
+}
+
+/// A storage for journal records.
+pub trait JournalStore {
+	/// Replaces previously saved records with `records`.
+	fn save(&self, records: &[Vec<u8>]) -> io::Result<()>;
+
+	/// Loads the records in the order they were saved.
+	fn load(&self) -> io::Result<Vec<Vec<u8>>>;
+}
+
+/// Stores the journal in a file.
+///
+/// Every record is prefixed with its length. The file is replaced atomically on save.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+	path: PathBuf,
+}
+
+impl FileStore {
+	/// Creates a new store writing to the file at `path`.
+	pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+		FileStore { path: path.into() }
+	}
+}
+
+impl JournalStore for FileStore {
+	fn save(&self, records: &[Vec<u8>]) -> io::Result<()> {
+		let mut tmp_path = self.path.as_os_str().to_owned();
+		tmp_path.push(".tmp");
+		{
+			let mut w = BufWriter::new(File::create(&tmp_path)?);
+			for record in records {
+				w.write_all(&(record.len() as u32).to_le_bytes())?;
+				w.write_all(record)?;
+			}
+			w.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+		}
+		fs::rename(&tmp_path, &self.path)
+	}
+
+	fn load(&self) -> io::Result<Vec<Vec<u8>>> {
+		let mut r = match File::open(&self.path) {
+			Ok(file) => BufReader::new(file),
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(e),
+		};
+		let mut records = Vec::new();
+		loop {
+			let mut len = [0u8; 4];
+			match r.read_exact(&mut len) {
+				Ok(()) => {}
+				Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+				Err(e) => return Err(e),
+			}
+			let mut record = vec![0u8; u32::from_le_bytes(len) as usize];
+			r.read_exact(&mut record)?;
+			records.push(record);
+		}
+		Ok(records)
+	}
+}
+
+/// Stores the journal in a column of a `KeyValueDB`.
+///
+/// The column should be dedicated to the journal, since it's cleared on every save.
+#[cfg(feature = "kvdb")]
+#[derive(Clone)]
+pub struct DatabaseStore {
+	db: Arc<dyn kvdb::KeyValueDB>,
+	col: u32,
+}
+
+#[cfg(feature = "kvdb")]
+impl DatabaseStore {
+	/// Creates a new store writing to column `col` of `db`.
+	pub fn new(db: Arc<dyn kvdb::KeyValueDB>, col: u32) -> Self {
+		DatabaseStore { db, col }
+	}
+}
+
+#[cfg(feature = "kvdb")]
+impl fmt::Debug for DatabaseStore {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("DatabaseStore").field("col", &self.col).finish()
+	}
+}
+
+#[cfg(feature = "kvdb")]
+impl JournalStore for DatabaseStore {
+	fn save(&self, records: &[Vec<u8>]) -> io::Result<()> {
+		let mut transaction = self.db.transaction();
+		transaction.delete_prefix(self.col, &[]);
+		for (index, record) in records.iter().enumerate() {
+			transaction.put(self.col, &(index as u64).to_be_bytes(), record);
+		}
+		self.db.write(transaction)
+	}
+
+	fn load(&self) -> io::Result<Vec<Vec<u8>>> {
+		Ok(self.db.iter(self.col).map(|(_, value)| value.into_vec()).collect())
+	}
+}
+
+/// Saves and restores pooled transactions.
+#[derive(Debug)]
+pub struct Journal<C, St> {
+	codec: C,
+	store: St,
+}
+
+impl<C, St: JournalStore> Journal<C, St> {
+	/// Creates a new journal encoding transactions with `codec` and writing them to `store`.
+	pub fn new(codec: C, store: St) -> Self {
+		Journal { codec, store }
+	}
+
+	/// Saves all transactions currently in the `pool`.
+	pub fn save<T, S, L>(&self, pool: &Pool<T, S, L>) -> io::Result<usize>
+	where
+		T: VerifiedTransaction,
+		S: Scoring<T>,
+		L: Listener<T>,
+		C: Codec<T>,
+	{
+		let records = pool.unordered_pending(|_: &T| Readiness::Ready).map(|tx| self.codec.encode(&tx)).collect();
+		self.write(records)
+	}
+
+	/// Saves all transactions in a `PoolView`.
+	pub fn save_view<T, S>(&self, view: &PoolView<T, S>) -> io::Result<usize>
+	where
+		T: VerifiedTransaction,
+		S: Scoring<T>,
+		C: Codec<T>,
+	{
+		let records = view.pending(|_: &T| Readiness::Ready).map(|tx| self.codec.encode(&tx)).collect();
+		self.write(records)
+	}
+
+	fn write(&self, records: Vec<Vec<u8>>) -> io::Result<usize> {
+		self.store.save(&records)?;
+		debug!("Saved {} transactions to the journal.", records.len());
+		Ok(records.len())
+	}
+
+	/// Re-imports saved transactions into the `pool`, returning the number of imported transactions.
+	///
+	/// Every transaction is decoded, verified with `verifier` and imported with `Pool::import`.
+	/// Transactions which fail to decode or verify are reported through `Listener::restore_failed`,
+	/// transactions rejected by the pool are reported through `Listener::rejected` as usual.
+	pub fn restore<T, S, L, V>(
+		&self,
+		pool: &mut Pool<T, S, L>,
+		verifier: &V,
+		replace: &dyn ShouldReplace<T>,
+	) -> io::Result<usize>
+	where
+		T: VerifiedTransaction,
+		S: Scoring<T>,
+		L: Listener<T>,
+		C: Codec<T>,
+		V: Verifier<C::Unverified, VerifiedTransaction = T>,
+		V::Error: fmt::Debug,
+	{
+		let mut imported = 0;
+		for record in self.store.load()? {
+			let transaction = match self.codec.decode(&record) {
+				Ok(transaction) => transaction,
+				Err(err) => {
+					pool.listener_mut().restore_failed(&err);
+					continue;
+				}
+			};
+			let transaction = match verifier.verify_transaction(transaction) {
+				Ok(transaction) => transaction,
+				Err(err) => {
+					pool.listener_mut().restore_failed(&err);
+					continue;
+				}
+			};
+			if pool.import(transaction, replace).is_ok() {
+				imported += 1;
+			}
+		}
+		debug!("Restored {} transactions from the journal.", imported);
+		Ok(imported)
+	}
+}
+
+/// Periodically saves the transactions of a `SharedPool` to a `Journal`.
+///
+/// The pool is saved on a background thread every `interval` and one last time when
+/// the `JournalWriter` is dropped.
+pub struct JournalWriter {
+	stop: Option<mpsc::Sender<()>>,
+	worker: Option<thread::JoinHandle<()>>,
+}
+
+impl JournalWriter {
+	/// Starts saving `pool` to `journal` every `interval`.
+	pub fn start<C, St, T, S, L>(journal: Journal<C, St>, pool: Arc<SharedPool<T, S, L>>, interval: Duration) -> Self
+	where
+		C: Codec<T> + Send + 'static,
+		St: JournalStore + Send + 'static,
+		T: VerifiedTransaction + Send + Sync + 'static,
+		T::Hash: Send + Sync,
+		T::Sender: Sync,
+		S: Scoring<T> + Send + 'static,
+		S::Score: Sync,
+		L: Listener<T> + Send + 'static,
+	{
+		let (stop, stopped) = mpsc::channel();
+		let worker = thread::Builder::new()
+			.name("tx-pool-journal".into())
+			.spawn(move || loop {
+				let shutdown = !matches!(stopped.recv_timeout(interval), Err(mpsc::RecvTimeoutError::Timeout));
+				if let Err(err) = journal.save_view(&pool.view()) {
+					warn!("Failed to save transaction pool journal: {}", err);
+				}
+				if shutdown {
+					break;
+				}
+			})
+			.expect("Failed to spawn journal thread");
+
+		JournalWriter { stop: Some(stop), worker: Some(worker) }
+	}
+}
+
+impl fmt::Debug for JournalWriter {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("JournalWriter").finish()
+	}
+}
+
+impl Drop for JournalWriter {
+	fn drop(&mut self) {
+		self.stop.take();
+		if let Some(worker) = self.worker.take() {
+			if worker.join().is_err() {
+				warn!("Journal thread panicked.");
+			}
+		}
+	}
+}
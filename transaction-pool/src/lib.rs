@@ -63,26 +63,55 @@
 #[cfg(test)]
 mod tests;
 
+mod block;
+mod clock;
 mod error;
+mod events;
+mod eviction;
+mod fee_market;
+mod history;
+mod import_queue;
+mod journal;
 mod listener;
+mod metrics;
 mod options;
 mod pool;
 mod ready;
 mod replace;
+mod shared;
+mod spam;
 mod status;
+mod tags;
+mod tracking;
 mod transactions;
 mod verifier;
 
 pub mod scoring;
+#[cfg(any(test, feature = "simulation"))]
+pub mod simulation;
 
+pub use self::block::{BlockLimits, BlockResources, BlockTemplate};
+pub use self::clock::{Clock, SystemClock};
 pub use self::error::Error;
+pub use self::events::{Event, EventStream, Lagged, Recv, Subscription};
+pub use self::eviction::{Candidate, EvictionPolicy, FairShare, LargestMemoryPerScore, OldestFirst, WorstScore};
+pub use self::fee_market::{FeeMarketScoring, FeeMarketTransaction, FeeScore};
+pub use self::history::Changes;
+pub use self::import_queue::{ImportError, ImportHandle, ImportQueue, ImportQueueOptions};
+#[cfg(feature = "kvdb")]
+pub use self::journal::DatabaseStore;
+pub use self::journal::{Codec, FileStore, Journal, JournalStore, JournalWriter};
 pub use self::listener::{Listener, NoopListener};
+pub use self::metrics::{Counters, Metrics, MetricsListener};
 pub use self::options::Options;
-pub use self::pool::{PendingIterator, Pool, Transaction, UnorderedIterator};
+pub use self::pool::{PendingIterator, Pool, Reinserted, Transaction, UnorderedIterator};
 pub use self::ready::{Readiness, Ready};
 pub use self::replace::{ReplaceTransaction, ShouldReplace};
-pub use self::scoring::Scoring;
+pub use self::scoring::{ParametrizedScoring, Scoring};
+pub use self::shared::{PoolView, SharedPool, ViewPendingIterator};
+pub use self::spam::{AntiSpam, ReplacementLimit, ScoreBump};
 pub use self::status::{LightStatus, Status};
+pub use self::tags::{Tagged, TaggedPool};
 pub use self::verifier::Verifier;
 
 use std::fmt;
@@ -23,8 +23,9 @@ pub trait Listener<T> {
 	fn added(&mut self, _tx: &Arc<T>, _old: Option<&Arc<T>>) {}
 
 	/// The transaction was rejected from the pool.
-	/// It means that it was too cheap to replace any transaction already in the pool.
-	fn rejected<H: Debug + LowerHex>(&mut self, _tx: &Arc<T>, _reason: &Error<H>) {}
+	/// It means that it was too cheap to replace any transaction already in the pool,
+	/// `reason` tells which limit prevented the import (see `Error::code`).
+	fn rejected<H: Debug + LowerHex, S: Debug + LowerHex>(&mut self, _tx: &Arc<T>, _reason: &Error<H, S>) {}
 
 	/// The transaction was pushed out from the pool because of the limit.
 	fn dropped(&mut self, _tx: &Arc<T>, _by: Option<&T>) {}
@@ -37,6 +38,15 @@ pub trait Listener<T> {
 
 	/// The transaction has been culled from the pool.
 	fn culled(&mut self, _tx: &Arc<T>) {}
+
+	/// The transaction has been included in a block and removed from the pool.
+	fn mined(&mut self, _tx: &Arc<T>) {}
+
+	/// The transaction has been in the pool for longer than `Options::max_age` and was removed.
+	fn expired(&mut self, _tx: &Arc<T>) {}
+
+	/// A transaction saved in the journal couldn't be decoded or verified on restore.
+	fn restore_failed(&mut self, _reason: &dyn Debug) {}
 }
 
 /// A no-op implementation of `Listener`.
@@ -54,7 +64,7 @@ where
 		self.1.added(tx, old);
 	}
 
-	fn rejected<H: Debug + LowerHex>(&mut self, tx: &Arc<T>, reason: &Error<H>) {
+	fn rejected<H: Debug + LowerHex, S: Debug + LowerHex>(&mut self, tx: &Arc<T>, reason: &Error<H, S>) {
 		self.0.rejected(tx, reason);
 		self.1.rejected(tx, reason);
 	}
@@ -78,4 +88,19 @@ where
 		self.0.culled(tx);
 		self.1.culled(tx);
 	}
+
+	fn mined(&mut self, tx: &Arc<T>) {
+		self.0.mined(tx);
+		self.1.mined(tx);
+	}
+
+	fn expired(&mut self, tx: &Arc<T>) {
+		self.0.expired(tx);
+		self.1.expired(tx);
+	}
+
+	fn restore_failed(&mut self, reason: &dyn Debug) {
+		self.0.restore_failed(reason);
+		self.1.restore_failed(reason);
+	}
 }
@@ -0,0 +1,138 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Metrics and introspection of the pool.
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, LowerHex};
+use std::sync::Arc;
+
+use crate::{error::Error, listener::Listener, status::LightStatus};
+
+/// Counters of pool events, collected by `MetricsListener`.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Counters {
+	/// Number of transactions imported without replacing any other transaction.
+	pub imported: u64,
+	/// Number of transactions imported in place of another transaction from the same sender.
+	pub replaced: u64,
+	/// Number of rejected transactions by reason (see `Error::code`).
+	pub rejected: BTreeMap<&'static str, u64>,
+	/// Number of transactions evicted to make room for better transactions.
+	pub evicted: u64,
+	/// Number of transactions dropped when the pool was cleared.
+	pub cleared: u64,
+	/// Number of transactions removed as invalid.
+	pub invalid: u64,
+	/// Number of canceled transactions.
+	pub canceled: u64,
+	/// Number of culled transactions.
+	pub culled: u64,
+	/// Number of expired transactions.
+	pub expired: u64,
+	/// Number of mined transactions.
+	pub mined: u64,
+	/// Number of journal records which failed to restore.
+	pub restore_failed: u64,
+}
+
+impl Counters {
+	/// Returns the total number of rejected transactions.
+	pub fn rejected_total(&self) -> u64 {
+		self.rejected.values().sum()
+	}
+}
+
+/// A `Listener` counting pool events.
+#[derive(Default, Debug)]
+pub struct MetricsListener {
+	counters: Counters,
+}
+
+impl MetricsListener {
+	/// Returns the counters collected so far.
+	pub fn counters(&self) -> &Counters {
+		&self.counters
+	}
+
+	/// Returns the counters collected so far and resets them.
+	pub fn take_counters(&mut self) -> Counters {
+		std::mem::take(&mut self.counters)
+	}
+}
+
+impl<T> Listener<T> for MetricsListener {
+	fn added(&mut self, _tx: &Arc<T>, old: Option<&Arc<T>>) {
+		match old {
+			Some(_) => self.counters.replaced += 1,
+			None => self.counters.imported += 1,
+		}
+	}
+
+	fn rejected<H: Debug + LowerHex, S: Debug + LowerHex>(&mut self, _tx: &Arc<T>, reason: &Error<H, S>) {
+		*self.counters.rejected.entry(reason.code()).or_insert(0) += 1;
+	}
+
+	fn dropped(&mut self, _tx: &Arc<T>, by: Option<&T>) {
+		match by {
+			Some(_) => self.counters.evicted += 1,
+			None => self.counters.cleared += 1,
+		}
+	}
+
+	fn invalid(&mut self, _tx: &Arc<T>) {
+		self.counters.invalid += 1;
+	}
+
+	fn canceled(&mut self, _tx: &Arc<T>) {
+		self.counters.canceled += 1;
+	}
+
+	fn culled(&mut self, _tx: &Arc<T>) {
+		self.counters.culled += 1;
+	}
+
+	fn expired(&mut self, _tx: &Arc<T>) {
+		self.counters.expired += 1;
+	}
+
+	fn mined(&mut self, _tx: &Arc<T>) {
+		self.counters.mined += 1;
+	}
+
+	fn restore_failed(&mut self, _reason: &dyn Debug) {
+		self.counters.restore_failed += 1;
+	}
+}
+
+/// A snapshot of the pool state.
+///
+/// See `Pool::metrics`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metrics<Sender, Score> {
+	/// Light status of the pool.
+	pub status: LightStatus,
+	/// Number of transactions per score bucket.
+	///
+	/// For `n` bucket bounds there are `n + 1` buckets: the first one counts transactions
+	/// scored below the first bound, the `i`-th one transactions scored within
+	/// `bounds[i - 1]..bounds[i]` and the last one transactions scored at or above the last bound.
+	pub score_histogram: Vec<usize>,
+	/// Senders with the most transactions in the pool along with the number of their transactions,
+	/// deepest queue first.
+	pub top_senders: Vec<(Sender, usize)>,
+	/// Score of the transaction which would be evicted first when the pool is full.
+	pub eviction_threshold: Option<Score>,
+	/// Whether the pool is at its capacity.
+	pub is_full: bool,
+}
+
+/// Returns the index of the histogram bucket of `score`.
+pub(crate) fn bucket<S: Ord>(bounds: &[S], score: &S) -> usize {
+	bounds.iter().take_while(|bound| *bound <= score).count()
+}
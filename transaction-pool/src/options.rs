@@ -6,6 +6,8 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::time::Duration;
+
 /// Transaction Pool options.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Options {
@@ -15,10 +17,25 @@ pub struct Options {
 	pub max_per_sender: usize,
 	/// Maximal memory usage.
 	pub max_mem_usage: usize,
+	/// Maximal time a transaction can spend in the pool before it's removed by `Pool::expire`.
+	pub max_age: Option<Duration>,
+	/// Number of transactions above `max_count` the pool may hold when re-inserting transactions
+	/// from retracted blocks with `Pool::reinsert_retracted`.
+	pub max_retracted_overflow: usize,
+	/// Number of most recent additions and removals of transactions kept to answer `Pool::changes_since`.
+	/// Zero disables the history.
+	pub history_window: usize,
 }
 
 impl Default for Options {
 	fn default() -> Self {
-		Options { max_count: 1024, max_per_sender: 16, max_mem_usage: 8 * 1024 * 1024 }
+		Options {
+			max_count: 1024,
+			max_per_sender: 16,
+			max_mem_usage: 8 * 1024 * 1024,
+			max_age: None,
+			max_retracted_overflow: 128,
+			history_window: 0,
+		}
 	}
 }
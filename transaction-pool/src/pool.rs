@@ -7,19 +7,29 @@
 // except according to those terms.
 
 use log::{trace, warn};
-use std::collections::{hash_map, BTreeSet, HashMap};
+use std::collections::{hash_map, BTreeSet, HashMap, HashSet};
 use std::slice;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{cmp, fmt, mem};
 
 use crate::{
+	block::{BlockLimits, BlockResources, BlockTemplate},
+	clock::{Clock, SystemClock},
 	error,
+	eviction::{Candidate, EvictionPolicy, WorstScore},
+	history::{Changes, History},
 	listener::{Listener, NoopListener},
+	metrics::{self, Metrics},
 	options::Options,
 	ready::{Readiness, Ready},
 	replace::{ReplaceTransaction, ShouldReplace},
-	scoring::{self, ScoreWithRef, Scoring},
+	scoring::{self, ParametrizedScoring, ScoreWithRef, Scoring},
+	spam::{AntiSpam, SpamGuard},
 	status::{LightStatus, Status},
-	transactions::{AddResult, Transactions},
+	tracking::ReadinessTracker,
+	transactions::{AddResult, Rescore, Transactions},
+	verifier::Verifier,
 	VerifiedTransaction,
 };
 
@@ -32,13 +42,19 @@ use crate::{
 pub struct Transaction<T> {
 	/// Sequential id of the transaction
 	pub insertion_id: u64,
+	/// Time the transaction was inserted to the pool
+	pub inserted_at: Instant,
 	/// Shared transaction
 	pub transaction: Arc<T>,
 }
 
 impl<T> Clone for Transaction<T> {
 	fn clone(&self) -> Self {
-		Transaction { insertion_id: self.insertion_id, transaction: self.transaction.clone() }
+		Transaction {
+			insertion_id: self.insertion_id,
+			inserted_at: self.inserted_at,
+			transaction: self.transaction.clone(),
+		}
 	}
 }
 
@@ -56,6 +72,7 @@ pub struct Pool<T: VerifiedTransaction, S: Scoring<T>, L = NoopListener> {
 	listener: L,
 	scoring: S,
 	options: Options,
+	clock: Box<dyn Clock>,
 	mem_usage: usize,
 
 	transactions: HashMap<T::Sender, Transactions<T, S>>,
@@ -65,6 +82,67 @@ pub struct Pool<T: VerifiedTransaction, S: Scoring<T>, L = NoopListener> {
 	worst_transactions: BTreeSet<ScoreWithRef<T, S::Score>>,
 
 	insertion_id: u64,
+
+	/// Senders whose transactions changed since the last `take_changed_senders`,
+	/// `None` unless change tracking was enabled.
+	changed_senders: Option<HashSet<T::Sender>>,
+
+	/// Readiness of transactions, `None` unless enabled with `with_readiness`.
+	readiness: Option<ReadinessTracker<T>>,
+
+	spam: SpamGuard<T>,
+
+	/// Eviction policy used when the count limit is reached.
+	count_eviction: Box<dyn EvictionPolicy<T, S::Score>>,
+	/// Eviction policy used when the memory limit is reached.
+	memory_eviction: Box<dyn EvictionPolicy<T, S::Score>>,
+
+	/// Recent additions and removals of transactions.
+	history: History<T::Hash>,
+
+	/// Senders modified by a batch.
+	deferred: HashMap<T::Sender, Deferred<T, S::Score>>,
+
+	/// Rescoring deferred by `set_scoring_parameter` until the pool is modified.
+	rescore: Option<Rescore<T, S>>,
+}
+
+type WorstAndBest<T, S> = Option<((S, Transaction<T>), (S, Transaction<T>))>;
+
+/// A sender modified by a batch of imports or removals.
+#[derive(Debug)]
+struct Deferred<T, S> {
+	/// Worst and best transactions of the sender before the batch.
+	prev: WorstAndBest<T, S>,
+	/// Whether any transaction of the sender changed.
+	changed: bool,
+}
+
+/// Scores computed on the fly while rescoring is deferred.
+struct Rescored<T: VerifiedTransaction, S> {
+	scores: HashMap<T::Hash, S>,
+	best: BTreeSet<ScoreWithRef<T, S>>,
+	worst: BTreeSet<ScoreWithRef<T, S>>,
+}
+
+/// A limit of the pool requiring an eviction.
+#[derive(Debug, Clone, Copy)]
+enum Limit {
+	Count,
+	Memory,
+}
+
+impl Limit {
+	fn error<H: fmt::Debug + fmt::LowerHex, S: fmt::Debug + fmt::LowerHex>(
+		self,
+		hash: H,
+		score: S,
+	) -> error::Error<H, S> {
+		match self {
+			Limit::Count => error::Error::TooCheapToEnter(hash, score),
+			Limit::Memory => error::Error::MemoryLimitReached(hash, score),
+		}
+	}
 }
 
 impl<T: VerifiedTransaction, S: Scoring<T> + Default> Default for Pool<T, S> {
@@ -100,18 +178,94 @@ where
 	pub fn new(listener: L, scoring: S, options: Options) -> Self {
 		let transactions = HashMap::with_capacity(INITIAL_NUMBER_OF_SENDERS);
 		let by_hash = HashMap::with_capacity(options.max_count / 16);
+		let history = History::new(options.history_window);
 
 		Pool {
 			listener,
 			scoring,
 			options,
+			clock: Box::new(SystemClock),
 			mem_usage: 0,
 			transactions,
 			by_hash,
 			best_transactions: Default::default(),
 			worst_transactions: Default::default(),
 			insertion_id: 0,
+			changed_senders: None,
+			readiness: None,
+			spam: SpamGuard::default(),
+			count_eviction: Box::new(WorstScore),
+			memory_eviction: Box::new(WorstScore),
+			history,
+			deferred: HashMap::new(),
+			rescore: None,
+		}
+	}
+
+	/// Replaces the `Clock` used to record insertion time of transactions.
+	pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+		self.clock = Box::new(clock);
+		self
+	}
+
+	/// Sets the anti-spam policy of the pool.
+	pub fn with_anti_spam(mut self, policy: AntiSpam<T>) -> Self {
+		self.spam.set_policy(policy);
+		self
+	}
+
+	/// Sets the policy choosing which transaction to evict when the count limit is reached.
+	///
+	/// Defaults to `WorstScore`.
+	pub fn with_count_eviction<P: EvictionPolicy<T, S::Score> + 'static>(mut self, policy: P) -> Self {
+		self.count_eviction = Box::new(policy);
+		self
+	}
+
+	/// Sets the policy choosing which transaction to evict when the memory limit is reached.
+	///
+	/// Defaults to `WorstScore`.
+	pub fn with_memory_eviction<P: EvictionPolicy<T, S::Score> + 'static>(mut self, policy: P) -> Self {
+		self.memory_eviction = Box::new(policy);
+		self
+	}
+
+	/// Rejects all transactions from `sender` for the given `timeout`.
+	///
+	/// NOTE: Transactions from that sender which are already in the pool are not removed.
+	pub fn ban(&mut self, sender: T::Sender, timeout: Duration) {
+		let now = self.clock.now();
+		self.spam.ban(sender, now + timeout, now);
+	}
+
+	/// Lifts the ban of `sender`, returns `false` if the sender was not banned.
+	pub fn unban(&mut self, sender: &T::Sender) -> bool {
+		self.spam.unban(sender)
+	}
+
+	/// Returns true if transactions from `sender` are currently rejected.
+	pub fn is_banned(&mut self, sender: &T::Sender) -> bool {
+		let now = self.clock.now();
+		self.spam.is_banned(sender, now)
+	}
+
+	/// Enables incremental tracking of ready and future transactions.
+	///
+	/// `ready` is called to create a fresh `Ready` whenever readiness of a sender's transactions
+	/// needs to be checked: on import, removal and cull of the sender's transactions, and on
+	/// `notify_state_change`. This makes `tracked_pending` and `tracked_status` cheap,
+	/// since they don't need to check readiness of every transaction again.
+	pub fn with_readiness<F, R>(mut self, ready: F) -> Self
+	where
+		F: Fn() -> R + Send + 'static,
+		R: Ready<T> + 'static,
+	{
+		self.readiness = Some(ReadinessTracker::new(ready));
+		let senders = self.transactions.keys().cloned().collect::<Vec<_>>();
+		for sender in senders {
+			self.mark_changed(sender);
 		}
+		self
 	}
 
 	/// Attempts to import new transaction to the pool, returns a `Arc<T>` or an `Error`.
@@ -126,21 +280,131 @@ where
 	/// If any limit is reached the transaction with the lowest `Score` will be compared with the
 	/// new transaction via the supplied `ShouldReplace` implementation and may be evicted.
 	///
+	/// Transactions from banned senders and replacements above the anti-spam limits
+	/// are rejected (see `with_anti_spam`).
+	///
 	/// The `Listener` will be informed on any drops or rejections.
-	pub fn import(&mut self, transaction: T, replace: &dyn ShouldReplace<T>) -> error::Result<Arc<T>, T::Hash> {
+	pub fn import(
+		&mut self,
+		transaction: T,
+		replace: &dyn ShouldReplace<T>,
+	) -> error::Result<Arc<T>, T::Hash, S::Score> {
+		let result = self.import_deferred(transaction, replace, false);
+		self.flush_deferred();
+		result
+	}
+
+	/// Imports a batch of transactions, returning the results in the order of `transactions`.
+	///
+	/// Equivalent to calling `import` for every transaction, but the best and worst transactions
+	/// of every sender (and their readiness, if tracked) are updated once per batch rather than
+	/// after every transaction, unless a limit is reached and a transaction needs to be evicted.
+	///
+	/// The `Listener` is notified in the order of `transactions`, evictions caused by a transaction
+	/// are reported right before that transaction is added.
+	pub fn import_many<I>(
+		&mut self,
+		transactions: I,
+		replace: &dyn ShouldReplace<T>,
+	) -> Vec<error::Result<Arc<T>, T::Hash, S::Score>>
+	where
+		I: IntoIterator<Item = T>,
+	{
+		let results = transactions.into_iter().map(|tx| self.import_deferred(tx, replace, false)).collect();
+		self.flush_deferred();
+		results
+	}
+
+	/// Re-inserts transactions from blocks retracted by a chain reorganization.
+	///
+	/// Retracted transactions are not rejected because of the count, memory or per-sender limits,
+	/// nor by the anti-spam policy, as long as the pool holds fewer than
+	/// `max_count + max_retracted_overflow` transactions. Beyond that they are imported like any
+	/// other transaction. Transactions are placed according to `Scoring`, so their order within
+	/// a sender is the same no matter the order of `transactions`.
+	///
+	/// Afterwards the transactions of affected senders are checked with `ready` and the stale ones
+	/// (e.g. included again in the new chain) are culled and returned in `Reinserted::stale`.
+	pub fn reinsert_retracted<I, R>(
+		&mut self,
+		transactions: I,
+		replace: &dyn ShouldReplace<T>,
+		mut ready: R,
+	) -> Reinserted<T, T::Hash, S::Score>
+	where
+		I: IntoIterator<Item = T>,
+		R: Ready<T>,
+	{
+		let results = transactions.into_iter().map(|tx| self.import_deferred(tx, replace, true)).collect::<Vec<_>>();
+		self.flush_deferred();
+
+		let senders =
+			results.iter().filter_map(|res| res.as_ref().ok()).map(|tx| tx.sender().clone()).collect::<HashSet<_>>();
+		let mut stale = Vec::new();
+		for sender in &senders {
+			self.remove_stalled(sender, &mut ready, Some(&mut stale));
+		}
+
+		Reinserted { results, stale }
+	}
+
+	/// Imports a transaction leaving the best and worst transactions of its sender
+	/// to be updated by `flush_deferred`.
+	///
+	/// `retracted` transactions bypass the limits and anti-spam checks within `max_retracted_overflow`.
+	fn import_deferred(
+		&mut self,
+		transaction: T,
+		replace: &dyn ShouldReplace<T>,
+		retracted: bool,
+	) -> error::Result<Arc<T>, T::Hash, S::Score> {
+		self.apply_rescore();
 		let mem_usage = transaction.mem_usage();
 
 		if self.by_hash.contains_key(transaction.hash()) {
 			return Err(error::Error::AlreadyImported(transaction.hash().clone()));
 		}
 
+		let now = self.clock.now();
 		self.insertion_id += 1;
-		let transaction = Transaction { insertion_id: self.insertion_id, transaction: Arc::new(transaction) };
+		let transaction =
+			Transaction { insertion_id: self.insertion_id, inserted_at: now, transaction: Arc::new(transaction) };
+
+		if !retracted && self.spam.is_banned(transaction.sender(), now) {
+			let error = error::Error::SenderBanned(transaction.hash().clone());
+			self.listener.rejected(&transaction, &error);
+			return Err(error);
+		}
+
+		let replaced =
+			self.transactions.get(transaction.sender()).and_then(|txs| txs.replaced_by(&transaction, &self.scoring));
+		if let (false, Some(old)) = (retracted, replaced) {
+			let error = if !self.spam.is_bumped_enough(old, &transaction) {
+				Some(error::Error::InsufficientScoreBump(old.hash().clone(), transaction.hash().clone()))
+			} else if self.spam.replacement_limit_reached(transaction.sender(), now) {
+				Some(error::Error::TooManyReplacements(transaction.hash().clone()))
+			} else {
+				None
+			};
+			if let Some(error) = error {
+				self.listener.rejected(&transaction, &error);
+				return Err(error);
+			}
+		}
+
+		let bypass_limits = retracted
+			&& self.by_hash.len() < self.options.max_count.saturating_add(self.options.max_retracted_overflow);
+		let over_limit =
+			self.by_hash.len() + 1 > self.options.max_count || self.mem_usage + mem_usage > self.options.max_mem_usage;
+		// Eviction relies on up-to-date worst transactions.
+		if over_limit && !bypass_limits {
+			self.flush_deferred();
+		}
 
 		// TODO [ToDr] Most likely move this after the transaction is inserted.
 		// Avoid using should_replace, but rather use scoring for that.
 		{
-			let remove_worst = |s: &mut Self, transaction| match s.remove_worst(transaction, replace) {
+			let remove_worst = |s: &mut Self, transaction, limit| match s.remove_worst(transaction, replace, limit) {
 				Err(err) => {
 					s.listener.rejected(transaction, &err);
 					Err(err)
@@ -153,41 +417,48 @@ where
 				}
 			};
 
-			while self.by_hash.len() + 1 > self.options.max_count {
+			while !bypass_limits && self.by_hash.len() + 1 > self.options.max_count {
 				trace!("Count limit reached: {} > {}", self.by_hash.len() + 1, self.options.max_count);
-				if !remove_worst(self, &transaction)? {
+				if !remove_worst(self, &transaction, Limit::Count)? {
 					break;
 				}
 			}
 
-			while self.mem_usage + mem_usage > self.options.max_mem_usage {
+			while !bypass_limits && self.mem_usage + mem_usage > self.options.max_mem_usage {
 				trace!("Mem limit reached: {} > {}", self.mem_usage + mem_usage, self.options.max_mem_usage);
-				if !remove_worst(self, &transaction)? {
+				if !remove_worst(self, &transaction, Limit::Memory)? {
 					break;
 				}
 			}
 		}
 
-		let (result, prev_state, current_state) = {
-			let transactions =
-				self.transactions.entry(transaction.sender().clone()).or_insert_with(Transactions::default);
-			// get worst and best transactions for comparison
-			let prev = transactions.worst_and_best();
-			let result = transactions.add(transaction, &self.scoring, self.options.max_per_sender);
-			let current = transactions.worst_and_best();
-			(result, prev, current)
+		let transaction_sender = transaction.sender().clone();
+		let result = {
+			let transactions = self.transactions.entry(transaction_sender.clone()).or_default();
+			// remember worst and best transactions for comparison when flushing
+			self.deferred
+				.entry(transaction_sender.clone())
+				.or_insert_with(|| Deferred { prev: transactions.worst_and_best(), changed: false });
+			let max_per_sender = if bypass_limits { usize::MAX } else { self.options.max_per_sender };
+			transactions.add(transaction, &self.scoring, max_per_sender)
 		};
 
-		// update best and worst transactions from this sender (if required)
-		self.update_senders_worst_and_best(prev_state, current_state);
-
 		match result {
 			AddResult::Ok(tx) => {
+				self.defer_changed(&transaction_sender);
 				self.listener.added(&tx, None);
 				self.finalize_insert(&tx, None);
 				Ok(tx.transaction)
 			}
-			AddResult::PushedOut { new, old } | AddResult::Replaced { new, old } => {
+			AddResult::Replaced { new, old } => {
+				self.spam.note_replacement(&transaction_sender, now);
+				self.defer_changed(&transaction_sender);
+				self.listener.added(&new, Some(&old));
+				self.finalize_insert(&new, Some(&old));
+				Ok(new.transaction)
+			}
+			AddResult::PushedOut { new, old } => {
+				self.defer_changed(&transaction_sender);
 				self.listener.added(&new, Some(&old));
 				self.finalize_insert(&new, Some(&old));
 				Ok(new.transaction)
@@ -198,17 +469,36 @@ where
 				return Err(error);
 			}
 			AddResult::TooCheapToEnter(new, score) => {
-				let error = error::Error::TooCheapToEnter(new.hash().clone(), format!("{:#x}", score));
+				let error = error::Error::SenderLimitReached(new.hash().clone(), score);
 				self.listener.rejected(&new, &error);
 				return Err(error);
 			}
 		}
 	}
 
+	/// Verifies a transaction with `verifier` and imports it to the pool.
+	///
+	/// Verification failures are returned as `Error::Verification`, see `import` for the rest.
+	pub fn verify_and_import<U, V>(
+		&mut self,
+		transaction: U,
+		verifier: &V,
+		replace: &dyn ShouldReplace<T>,
+	) -> error::Result<Arc<T>, T::Hash, S::Score>
+	where
+		V: Verifier<U, VerifiedTransaction = T>,
+		V::Error: fmt::Display,
+	{
+		let transaction =
+			verifier.verify_transaction(transaction).map_err(|err| error::Error::Verification(err.to_string()))?;
+		self.import(transaction, replace)
+	}
+
 	/// Updates state of the pool statistics if the transaction was added to a set.
 	fn finalize_insert(&mut self, new: &Transaction<T>, old: Option<&Transaction<T>>) {
 		self.mem_usage += new.mem_usage();
 		self.by_hash.insert(new.hash().clone(), new.clone());
+		self.history.record(new.hash(), true);
 
 		if let Some(old) = old {
 			self.finalize_remove(old.hash());
@@ -219,6 +509,7 @@ where
 	fn finalize_remove(&mut self, hash: &T::Hash) -> Option<Arc<T>> {
 		self.by_hash.remove(hash).map(|old| {
 			self.mem_usage -= old.transaction.mem_usage();
+			self.history.record(hash, false);
 			old.transaction
 		})
 	}
@@ -226,8 +517,8 @@ where
 	/// Updates best and worst transactions from a sender.
 	fn update_senders_worst_and_best(
 		&mut self,
-		previous: Option<((S::Score, Transaction<T>), (S::Score, Transaction<T>))>,
-		current: Option<((S::Score, Transaction<T>), (S::Score, Transaction<T>))>,
+		previous: WorstAndBest<T, S::Score>,
+		current: WorstAndBest<T, S::Score>,
 	) {
 		let worst_collection = &mut self.worst_transactions;
 		let best_collection = &mut self.best_transactions;
@@ -269,64 +560,103 @@ where
 		}
 	}
 
-	/// Attempts to remove the worst transaction from the pool if it's worse than the given one.
+	/// Attempts to remove the transaction chosen by the eviction policy of `limit`
+	/// from the pool if it's worse than the given one.
 	///
-	/// Returns `None` in case we couldn't decide if the transaction should replace the worst transaction or not.
+	/// Returns `None` in case we couldn't decide if the transaction should replace the chosen transaction or not.
 	/// In such case we will accept the transaction even though it is going to exceed the limit.
+	/// If the transaction is worse the `limit` error is returned with the score of the chosen transaction.
 	fn remove_worst(
 		&mut self,
 		transaction: &Transaction<T>,
 		replace: &dyn ShouldReplace<T>,
-	) -> error::Result<Option<Transaction<T>>, T::Hash> {
-		let to_remove = match self.worst_transactions.iter().next_back() {
+		limit: Limit,
+	) -> error::Result<Option<Transaction<T>>, T::Hash, S::Score> {
+		let spam = &self.spam;
+		let txs = &self.transactions;
+		let policy = match limit {
+			Limit::Count => &self.count_eviction,
+			Limit::Memory => &self.memory_eviction,
+		};
+		// Local transactions are never evicted.
+		let mut candidates =
+			self.worst_transactions.iter().rev().filter(|tx| !spam.is_local(&tx.transaction.transaction)).map(|tx| {
+				Candidate {
+					transaction: &tx.transaction,
+					score: &tx.score,
+					sender_count: txs.get(tx.transaction.sender()).map_or(0, |txs| txs.len()),
+				}
+			});
+		let to_remove = match policy.select(&mut candidates) {
 			// No elements to remove? and the pool is still full?
 			None => {
 				warn!("The pool is full but there are no transactions to remove.");
-				return Err(error::Error::TooCheapToEnter(transaction.hash().clone(), "unknown".into()));
+				return Err(error::Error::PoolFull(transaction.hash().clone()));
 			}
 			Some(old) => {
-				let txs = &self.transactions;
 				let get_replace_tx = |tx| {
 					let sender_txs = txs.get(transaction.sender()).map(|txs| txs.iter().as_slice());
 					ReplaceTransaction::new(tx, sender_txs)
 				};
-				let old_replace = get_replace_tx(&old.transaction);
+				let old_replace = get_replace_tx(old.transaction);
 				let new_replace = get_replace_tx(transaction);
 
 				match replace.should_replace(&old_replace, &new_replace) {
 					// We can't decide which of them should be removed, so accept both.
 					scoring::Choice::InsertNew => None,
 					// New transaction is better than the worst one so we can replace it.
-					scoring::Choice::ReplaceOld => Some(old.clone()),
-					// otherwise fail
-					scoring::Choice::RejectNew => {
-						return Err(error::Error::TooCheapToEnter(
+					scoring::Choice::ReplaceOld if !spam.is_bumped_enough(old.transaction, transaction) => {
+						return Err(error::Error::InsufficientScoreBump(
+							old.transaction.hash().clone(),
 							transaction.hash().clone(),
-							format!("{:#x}", old.score),
 						))
 					}
+					scoring::Choice::ReplaceOld => Some(old.transaction.clone()),
+					// otherwise fail
+					scoring::Choice::RejectNew => {
+						return Err(limit.error(transaction.hash().clone(), old.score.clone()))
+					}
 				}
 			}
 		};
 
 		if let Some(to_remove) = to_remove {
 			// Remove from transaction set
-			self.remove_from_set(to_remove.transaction.sender(), |set, scoring| {
-				set.remove(&to_remove.transaction, scoring)
-			});
+			self.remove_from_set(to_remove.sender(), |set, scoring| set.remove(&to_remove, scoring));
 
-			Ok(Some(to_remove.transaction))
+			Ok(Some(to_remove))
 		} else {
 			Ok(None)
 		}
 	}
 
+	fn defer_changed(&mut self, sender: &T::Sender) {
+		if let Some(deferred) = self.deferred.get_mut(sender) {
+			deferred.changed = true;
+		}
+	}
+
+	/// Updates best and worst transactions of senders modified by deferred imports and removals.
+	fn flush_deferred(&mut self) {
+		let mut deferred = mem::take(&mut self.deferred);
+		for (sender, Deferred { prev, changed }) in deferred.drain() {
+			let current = self.transactions.get(&sender).and_then(|txs| txs.worst_and_best());
+			self.update_senders_worst_and_best(prev, current);
+			if changed {
+				self.mark_changed(sender);
+			}
+		}
+		// keep the allocation for the next batch
+		self.deferred = deferred;
+	}
+
 	/// Removes transaction from sender's transaction `HashMap`.
 	fn remove_from_set<R, F: FnOnce(&mut Transactions<T, S>, &S) -> R>(
 		&mut self,
 		sender: &T::Sender,
 		f: F,
 	) -> Option<R> {
+		self.apply_rescore();
 		let (prev, next, result) = if let Some(set) = self.transactions.get_mut(sender) {
 			let prev = set.worst_and_best();
 			let result = f(set, &self.scoring);
@@ -336,19 +666,55 @@ where
 		};
 
 		self.update_senders_worst_and_best(prev, next);
+		self.mark_changed(sender.clone());
 		Some(result)
 	}
 
+	/// Records that transactions from `sender` changed, if change tracking is enabled,
+	/// and updates their readiness, if readiness tracking is enabled.
+	fn mark_changed(&mut self, sender: T::Sender) {
+		if let Some(readiness) = self.readiness.as_mut() {
+			readiness.update(&sender, self.transactions.get(&sender).into_iter().flat_map(|txs| txs.iter()));
+		}
+		if let Some(changed) = self.changed_senders.as_mut() {
+			changed.insert(sender);
+		}
+	}
+
+	/// Starts recording senders whose transactions change.
+	pub(crate) fn track_changes(&mut self) {
+		self.changed_senders.get_or_insert_with(HashSet::new);
+	}
+
+	/// Returns senders whose transactions changed since the previous call.
+	pub(crate) fn take_changed_senders(&mut self) -> HashSet<T::Sender> {
+		self.apply_rescore();
+		self.changed_senders.as_mut().map(std::mem::take).unwrap_or_default()
+	}
+
+	/// Returns all transactions from given sender ordered by `Scoring`.
+	pub(crate) fn sender_transactions(&self, sender: &T::Sender) -> Option<&Transactions<T, S>> {
+		self.transactions.get(sender)
+	}
+
 	/// Clears pool from all transactions.
 	/// This causes a listener notification that all transactions were dropped.
 	/// NOTE: the drop-notification order will be arbitrary.
 	pub fn clear(&mut self) {
+		if let Some(changed) = self.changed_senders.as_mut() {
+			changed.extend(self.transactions.keys().cloned());
+		}
+		if let Some(readiness) = self.readiness.as_mut() {
+			readiness.clear();
+		}
 		self.mem_usage = 0;
+		self.rescore = None;
 		self.transactions.clear();
 		self.best_transactions.clear();
 		self.worst_transactions.clear();
 
-		for (_hash, tx) in self.by_hash.drain() {
+		for (hash, tx) in self.by_hash.drain() {
+			self.history.record(&hash, false);
 			self.listener.dropped(&tx.transaction, None)
 		}
 	}
@@ -370,8 +736,61 @@ where
 		}
 	}
 
+	/// Removes a transaction included in a block from the pool.
+	///
+	/// The listener gets a `mined` notification.
+	pub fn remove_mined(&mut self, hash: &T::Hash) -> Option<Arc<T>> {
+		let tx = self.finalize_remove(hash)?;
+		self.remove_from_set(tx.sender(), |set, scoring| set.remove(&tx, scoring));
+		self.listener.mined(&tx);
+		Some(tx)
+	}
+
+	/// Removes a batch of transactions from the pool, returning the results in the order of `hashes`.
+	///
+	/// Equivalent to calling `remove` for every hash, but the best and worst transactions
+	/// of every sender are updated once per batch. The listener is notified in the order of `hashes`.
+	pub fn remove_many<'a, I>(&mut self, hashes: I, is_invalid: bool) -> Vec<Option<Arc<T>>>
+	where
+		I: IntoIterator<Item = &'a T::Hash>,
+		T::Hash: 'a,
+	{
+		self.apply_rescore();
+		let mut removed = Vec::new();
+		for hash in hashes {
+			let tx = match self.finalize_remove(hash) {
+				Some(tx) => tx,
+				None => {
+					removed.push(None);
+					continue;
+				}
+			};
+			if let Some(transactions) = self.transactions.get_mut(tx.sender()) {
+				self.deferred
+					.entry(tx.sender().clone())
+					.or_insert_with(|| Deferred { prev: transactions.worst_and_best(), changed: false })
+					.changed = true;
+				transactions.remove(&tx, &self.scoring);
+			}
+			if is_invalid {
+				self.listener.invalid(&tx);
+			} else {
+				self.listener.canceled(&tx);
+			}
+			removed.push(Some(tx));
+		}
+		self.flush_deferred();
+		removed
+	}
+
 	/// Removes all stalled transactions from given sender.
-	fn remove_stalled<R: Ready<T>>(&mut self, sender: &T::Sender, ready: &mut R) -> usize {
+	/// Removed transactions are pushed to `stale` if given.
+	fn remove_stalled<R: Ready<T>>(
+		&mut self,
+		sender: &T::Sender,
+		ready: &mut R,
+		mut stale: Option<&mut Vec<Arc<T>>>,
+	) -> usize {
 		let removed_from_set = self.remove_from_set(sender, |transactions, scoring| transactions.cull(ready, scoring));
 
 		match removed_from_set {
@@ -380,6 +799,9 @@ where
 				for tx in removed {
 					self.finalize_remove(tx.hash());
 					self.listener.culled(&tx);
+					if let Some(stale) = stale.as_mut() {
+						stale.push(tx.transaction);
+					}
 				}
 				len
 			}
@@ -393,13 +815,13 @@ where
 		match senders {
 			Some(senders) => {
 				for sender in senders {
-					removed += self.remove_stalled(sender, &mut ready);
+					removed += self.remove_stalled(sender, &mut ready, None);
 				}
 			}
 			None => {
 				let senders = self.transactions.keys().cloned().collect::<Vec<_>>();
 				for sender in senders {
-					removed += self.remove_stalled(&sender, &mut ready);
+					removed += self.remove_stalled(&sender, &mut ready, None);
 				}
 			}
 		}
@@ -407,6 +829,43 @@ where
 		removed
 	}
 
+	/// Removes all transactions which spent more than `Options::max_age` in the pool.
+	///
+	/// The `Listener` is notified about every removed transaction with `expired`.
+	/// Does nothing if `max_age` is not set.
+	pub fn expire(&mut self) -> usize {
+		let max_age = match self.options.max_age {
+			Some(max_age) => max_age,
+			None => return 0,
+		};
+		let now = self.clock.now();
+		let expired = self
+			.by_hash
+			.values()
+			.filter(|tx| now.saturating_duration_since(tx.inserted_at) > max_age)
+			.map(|tx| tx.hash().clone())
+			.collect::<Vec<_>>();
+
+		for hash in &expired {
+			if let Some(tx) = self.finalize_remove(hash) {
+				self.remove_from_set(tx.sender(), |set, scoring| set.remove(&tx, scoring));
+				self.listener.expired(&tx);
+			}
+		}
+
+		expired.len()
+	}
+
+	/// Re-checks readiness of transactions from given sender.
+	///
+	/// Should be called whenever the state the `Ready` implementation depends on changes
+	/// for that sender. Does nothing unless readiness tracking is enabled with `with_readiness`.
+	pub fn notify_state_change(&mut self, sender: &T::Sender) {
+		if self.readiness.is_some() && self.transactions.contains_key(sender) {
+			self.mark_changed(sender.clone());
+		}
+	}
+
 	/// Returns a transaction if it's part of the pool or `None` otherwise.
 	pub fn find(&self, hash: &T::Hash) -> Option<Arc<T>> {
 		self.by_hash.get(hash).map(|t| t.transaction.clone())
@@ -414,7 +873,23 @@ where
 
 	/// Returns worst transaction in the queue (if any).
 	pub fn worst_transaction(&self) -> Option<Arc<T>> {
-		self.worst_transactions.iter().next_back().map(|x| x.transaction.transaction.clone())
+		let rescored = self.rescored(self.transactions.values());
+		let worst_transactions = rescored.as_ref().map_or(&self.worst_transactions, |rescored| &rescored.worst);
+		worst_transactions.iter().next_back().map(|x| x.transaction.transaction.clone())
+	}
+
+	/// Returns the version of the pool contents, incremented whenever a transaction is added or removed.
+	pub fn version(&self) -> u64 {
+		self.history.version()
+	}
+
+	/// Returns transactions added to and removed from the pool since `version`.
+	///
+	/// Transactions both added and removed since `version` are not reported.
+	/// Returns `None` if `version` is older than `Options::history_window` changes (or newer than the current one),
+	/// the caller should then resynchronize with the full contents of the pool.
+	pub fn changes_since(&self, version: u64) -> Option<Changes<T::Hash>> {
+		self.history.changes_since(version)
 	}
 
 	/// Returns true if the pool is at it's capacity.
@@ -424,19 +899,54 @@ where
 
 	/// Returns senders ordered by priority of their transactions.
 	pub fn senders(&self) -> impl Iterator<Item = &T::Sender> {
-		self.best_transactions.iter().map(|tx| tx.transaction.sender())
+		let rescored = self.rescored(self.transactions.values()).map(|rescored| {
+			rescored
+				.best
+				.iter()
+				.filter_map(|tx| self.transactions.get_key_value(tx.transaction.sender()))
+				.map(|(sender, _)| sender)
+				.collect::<Vec<_>>()
+		});
+		let best = match rescored {
+			Some(_) => None,
+			None => Some(self.best_transactions.iter().map(|tx| tx.transaction.sender())),
+		};
+		rescored.into_iter().flatten().chain(best.into_iter().flatten())
 	}
 
 	/// Returns an iterator of pending (ready) transactions.
 	pub fn pending<R: Ready<T>>(&self, ready: R) -> PendingIterator<'_, T, R, S, L> {
-		PendingIterator { ready, best_transactions: self.best_transactions.clone(), pool: self }
+		match self.rescored(self.transactions.values()) {
+			Some(rescored) => {
+				PendingIterator { ready, best_transactions: rescored.best, scores: Some(rescored.scores), pool: self }
+			}
+			None => {
+				PendingIterator { ready, best_transactions: self.best_transactions.clone(), scores: None, pool: self }
+			}
+		}
+	}
+
+	/// Returns an iterator of pending (ready) transactions according to tracked readiness.
+	///
+	/// Returns `None` unless readiness tracking is enabled with `with_readiness`.
+	pub fn tracked_pending(&self) -> Option<impl Iterator<Item = Arc<T>> + '_> {
+		let readiness = self.readiness.as_ref()?;
+		Some(self.pending(move |tx: &T| readiness.readiness(tx.hash())))
 	}
 
 	/// Returns pending (ready) transactions from given sender.
 	pub fn pending_from_sender<R: Ready<T>>(&self, ready: R, sender: &T::Sender) -> PendingIterator<'_, T, R, S, L> {
-		let best_transactions = self
-			.transactions
-			.get(sender)
+		let transactions = self.transactions.get(sender);
+		if let Some(rescored) = self.rescored(transactions) {
+			return PendingIterator {
+				ready,
+				best_transactions: rescored.best,
+				scores: Some(rescored.scores),
+				pool: self,
+			};
+		}
+
+		let best_transactions = transactions
 			.and_then(|transactions| transactions.worst_and_best())
 			.map(|(_, best)| ScoreWithRef::new(best.0, best.1))
 			.map(|s| {
@@ -446,7 +956,47 @@ where
 			})
 			.unwrap_or_default();
 
-		PendingIterator { ready, best_transactions, pool: self }
+		PendingIterator { ready, best_transactions, scores: None, pool: self }
+	}
+
+	/// Selects the best pending (ready) transactions fitting within `limits`.
+	///
+	/// Transactions are considered in the same order as `pending`. A transaction which doesn't
+	/// fit within the remaining budget is skipped along with all the following transactions
+	/// from its sender, since they can't be included without it. Smaller transactions from
+	/// other senders may still fill the remaining space.
+	pub fn block_template<R: Ready<T>>(&self, mut ready: R, limits: &BlockLimits) -> BlockTemplate<T>
+	where
+		T: BlockResources,
+	{
+		let mut template = BlockTemplate::default();
+		let (mut best_transactions, scores) = match self.rescored(self.transactions.values()) {
+			Some(rescored) => (rescored.best, Some(rescored.scores)),
+			None => (self.best_transactions.clone(), None),
+		};
+
+		while let Some(best) = best_transactions.iter().next().cloned() {
+			best_transactions.remove(&best);
+
+			match ready.is_ready(&best.transaction) {
+				Readiness::Ready => {
+					if !template.push(&best.transaction, limits) {
+						trace!("[{:?}] Skipping sender, transaction doesn't fit.", best.transaction.hash());
+						continue;
+					}
+				}
+				Readiness::Stale => trace!("[{:?}] Ignoring stale transaction.", best.transaction.hash()),
+				// the following transactions from this sender are not ready either.
+				Readiness::Future => continue,
+			}
+
+			// retrieve next one from the same sender.
+			if let Some((score, tx)) = self.find_next(&best.transaction, scores.as_ref()) {
+				best_transactions.insert(ScoreWithRef::new(score, tx));
+			}
+		}
+
+		template
 	}
 
 	/// Returns unprioritized list of ready transactions.
@@ -456,6 +1006,7 @@ where
 
 	/// Update score of transactions of a particular sender.
 	pub fn update_scores(&mut self, sender: &T::Sender, event: S::Event) {
+		self.apply_rescore();
 		let res = if let Some(set) = self.transactions.get_mut(sender) {
 			let prev = set.worst_and_best();
 			set.update_scores(&self.scoring, event);
@@ -467,6 +1018,50 @@ where
 
 		if let Some((prev, current)) = res {
 			self.update_senders_worst_and_best(prev, current);
+			self.mark_changed(sender.clone());
+		}
+	}
+
+	/// Sets the external parameter of the scoring (e.g. the base fee).
+	///
+	/// The pool is re-sorted lazily: transactions are rescored by the next call modifying the pool,
+	/// and only senders reported as changed by `ParametrizedScoring::rescore` are re-sorted and marked
+	/// as changed. Until then methods depending on the order (e.g. `pending` or `worst_transaction`)
+	/// compute the new scores on the fly.
+	/// Nothing is recomputed when `ParametrizedScoring::set_parameter` reports the scores can't have changed.
+	pub fn set_scoring_parameter(&mut self, parameter: S::Parameter)
+	where
+		S: ParametrizedScoring<T>,
+	{
+		if self.scoring.set_parameter(parameter) && !self.transactions.is_empty() {
+			self.rescore = Some(|scoring, transactions, scores| scoring.rescore(transactions, scores));
+		}
+	}
+
+	/// Rescores transactions if deferred by `set_scoring_parameter`.
+	fn apply_rescore(&mut self) {
+		let rescore = match self.rescore.take() {
+			Some(rescore) => rescore,
+			None => return,
+		};
+
+		let scoring = &self.scoring;
+		let changed = self
+			.transactions
+			.iter_mut()
+			.filter_map(|(sender, set)| {
+				let prev = set.worst_and_best();
+				if set.rescore(scoring, rescore) {
+					Some((sender.clone(), prev, set.worst_and_best()))
+				} else {
+					None
+				}
+			})
+			.collect::<Vec<_>>();
+
+		for (sender, prev, current) in changed {
+			self.update_senders_worst_and_best(prev, current);
+			self.mark_changed(sender);
 		}
 	}
 
@@ -491,6 +1086,13 @@ where
 		status
 	}
 
+	/// Returns the full status of the pool according to tracked readiness.
+	///
+	/// Returns `None` unless readiness tracking is enabled with `with_readiness`.
+	pub fn tracked_status(&self) -> Option<Status> {
+		self.readiness.as_ref().map(ReadinessTracker::status)
+	}
+
 	/// Returns light status of the pool.
 	pub fn light_status(&self) -> LightStatus {
 		LightStatus {
@@ -500,6 +1102,123 @@ where
 		}
 	}
 
+	/// Returns a snapshot of the pool state.
+	///
+	/// Transactions are counted into buckets delimited by `score_buckets` (sorted in ascending order),
+	/// at most `top_senders` senders with the most transactions are reported.
+	pub fn metrics(&self, score_buckets: &[S::Score], top_senders: usize) -> Metrics<T::Sender, S::Score> {
+		let rescored = self.rescored(self.transactions.values());
+		let mut score_histogram = vec![0; score_buckets.len() + 1];
+		let mut depths = Vec::with_capacity(self.transactions.len());
+		for (sender, transactions) in &self.transactions {
+			for (tx, score) in transactions.iter().zip(transactions.scores()) {
+				let score = rescored.as_ref().and_then(|rescored| rescored.scores.get(tx.hash())).unwrap_or(score);
+				score_histogram[metrics::bucket(score_buckets, score)] += 1;
+			}
+			depths.push((sender.clone(), transactions.len()));
+		}
+		depths.sort_by_key(|(_, depth)| cmp::Reverse(*depth));
+		depths.truncate(top_senders);
+
+		Metrics {
+			status: self.light_status(),
+			score_histogram,
+			top_senders: depths,
+			eviction_threshold: rescored
+				.as_ref()
+				.map_or(&self.worst_transactions, |rescored| &rescored.worst)
+				.iter()
+				.next_back()
+				.map(|worst| worst.score.clone()),
+			is_full: self.is_full(),
+		}
+	}
+
+	/// Returns all transactions in the pool along with their scores, best first.
+	///
+	/// Unlike `pending` this ignores readiness and the ordering of transactions from a single sender.
+	/// Meant for debugging.
+	pub fn dump(&self) -> impl Iterator<Item = (S::Score, Arc<T>)> {
+		let rescored = self.rescored(self.transactions.values());
+		let mut transactions = self
+			.transactions
+			.values()
+			.flat_map(|txs| txs.iter().zip(txs.scores()))
+			.map(|(tx, score)| {
+				let score = rescored.as_ref().and_then(|rescored| rescored.scores.get(tx.hash())).unwrap_or(score);
+				ScoreWithRef::new(score.clone(), tx.clone())
+			})
+			.collect::<Vec<_>>();
+		transactions.sort();
+		transactions.into_iter().map(|tx| (tx.score, tx.transaction.transaction))
+	}
+
+	/// Returns the number of senders tracked by the anti-spam policy.
+	#[cfg(test)]
+	pub(crate) fn spam_tracked_senders(&self) -> usize {
+		self.spam.tracked_senders()
+	}
+
+	/// Checks the consistency of internal structures of the pool.
+	///
+	/// Returns the description of the first violated invariant.
+	#[cfg(any(test, feature = "simulation"))]
+	pub(crate) fn check_invariants(&self) -> Result<(), String> {
+		let mut count = 0;
+		let mut mem_usage = 0;
+		for (sender, set) in &self.transactions {
+			if set.is_empty() {
+				return Err(format!("Empty set of transactions of {:?}", sender));
+			}
+			let ignores_limit = set.iter().any(|tx| self.scoring.should_ignore_sender_limit(tx));
+			if set.len() > self.options.max_per_sender && !ignores_limit {
+				return Err(format!("{:?} has {} transactions above the limit", sender, set.len()));
+			}
+			for tx in set.iter() {
+				if tx.sender() != sender {
+					return Err(format!("{:?} stored as a transaction of {:?}", tx.hash(), sender));
+				}
+				match self.by_hash.get(tx.hash()) {
+					Some(stored) if stored.insertion_id == tx.insertion_id => {}
+					_ => return Err(format!("{:?} missing in by_hash", tx.hash())),
+				}
+				mem_usage += tx.mem_usage();
+			}
+			for pair in set.iter().as_slice().windows(2) {
+				if self.scoring.compare(&pair[0], &pair[1]) != cmp::Ordering::Less {
+					return Err(format!("Transactions of {:?} are not ordered: {:?}", sender, pair[1].hash()));
+				}
+			}
+			count += set.len();
+
+			let (worst, best) = set.worst_and_best().expect("set is not empty; qed");
+			if !self.worst_transactions.contains(&ScoreWithRef::new(worst.0, worst.1)) {
+				return Err(format!("Worst transaction of {:?} is outdated", sender));
+			}
+			if !self.best_transactions.contains(&ScoreWithRef::new(best.0, best.1)) {
+				return Err(format!("Best transaction of {:?} is outdated", sender));
+			}
+		}
+
+		if count != self.by_hash.len() {
+			return Err(format!("by_hash holds {} transactions, senders {}", self.by_hash.len(), count));
+		}
+		if mem_usage != self.mem_usage {
+			return Err(format!("Memory usage is {}, transactions use {}", self.mem_usage, mem_usage));
+		}
+		let senders = self.transactions.len();
+		if self.best_transactions.len() != senders || self.worst_transactions.len() != senders {
+			return Err(format!(
+				"{} senders, {} best and {} worst transactions",
+				senders,
+				self.best_transactions.len(),
+				self.worst_transactions.len()
+			));
+		}
+
+		Ok(())
+	}
+
 	/// Returns current pool options.
 	pub fn options(&self) -> Options {
 		self.options.clone()
@@ -521,6 +1240,15 @@ where
 	}
 }
 
+/// Outcome of `Pool::reinsert_retracted`.
+#[derive(Debug)]
+pub struct Reinserted<T, H: fmt::Debug + fmt::LowerHex, S: fmt::Debug + fmt::LowerHex> {
+	/// Import results in the order of the retracted transactions.
+	pub results: Vec<error::Result<Arc<T>, H, S>>,
+	/// Transactions of the affected senders culled as stale after the re-insertion.
+	pub stale: Vec<Arc<T>>,
+}
+
 /// An iterator over all pending (ready) transactions in unoredered fashion.
 ///
 /// NOTE: Current implementation will iterate over all transactions from particular sender
@@ -566,6 +1294,45 @@ where
 	}
 }
 
+impl<T, S, L> Pool<T, S, L>
+where
+	T: VerifiedTransaction,
+	S: Scoring<T>,
+{
+	/// Returns the transaction following `tx` from the same sender along with its score,
+	/// taken from `scores` if given.
+	fn find_next(
+		&self,
+		tx: &Transaction<T>,
+		scores: Option<&HashMap<T::Hash, S::Score>>,
+	) -> Option<(S::Score, Transaction<T>)> {
+		let (score, next) = self.transactions.get(tx.sender())?.find_next(tx, &self.scoring)?;
+		match scores.and_then(|scores| scores.get(next.hash())) {
+			Some(rescored) => Some((rescored.clone(), next)),
+			None => Some((score, next)),
+		}
+	}
+
+	/// Computes the scores of transactions from `senders` if rescoring is deferred.
+	fn rescored<'a, I>(&'a self, senders: I) -> Option<Rescored<T, S::Score>>
+	where
+		I: IntoIterator<Item = &'a Transactions<T, S>>,
+	{
+		let rescore = self.rescore?;
+		let mut rescored = Rescored { scores: HashMap::new(), best: BTreeSet::new(), worst: BTreeSet::new() };
+		for transactions in senders {
+			let scores = transactions.rescored(&self.scoring, rescore);
+			let txs = transactions.iter().as_slice();
+			if let (Some(best), Some(worst)) = (txs.first(), txs.last()) {
+				rescored.best.insert(ScoreWithRef::new(scores[0].clone(), best.clone()));
+				rescored.worst.insert(ScoreWithRef::new(scores[scores.len() - 1].clone(), worst.clone()));
+			}
+			rescored.scores.extend(transactions.iter().map(|tx| tx.hash().clone()).zip(scores));
+		}
+		Some(rescored)
+	}
+}
+
 /// An iterator over all pending (ready) transactions.
 /// NOTE: the transactions are not removed from the queue.
 /// You might remove them later by calling `cull`.
@@ -577,6 +1344,8 @@ where
 {
 	ready: R,
 	best_transactions: BTreeSet<ScoreWithRef<T, S::Score>>,
+	/// Scores computed on the fly while rescoring is deferred.
+	scores: Option<HashMap<T::Hash, S::Score>>,
 	pool: &'a Pool<T, S, L>,
 }
 
@@ -600,12 +1369,7 @@ where
 			match tx_state {
 				Readiness::Ready | Readiness::Stale => {
 					// retrieve next one from the same sender.
-					let next = self
-						.pool
-						.transactions
-						.get(best.transaction.sender())
-						.and_then(|s| s.find_next(&best.transaction, &self.pool.scoring));
-					if let Some((score, tx)) = next {
+					if let Some((score, tx)) = self.pool.find_next(&best.transaction, self.scores.as_ref()) {
 						self.best_transactions.insert(ScoreWithRef::new(score, tx));
 					}
 				}
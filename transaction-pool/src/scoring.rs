@@ -97,6 +97,26 @@ pub trait Scoring<T>: fmt::Debug {
 	}
 }
 
+/// A `Scoring` whose scores depend on an external parameter, e.g. the base fee of the network.
+///
+/// See `Pool::set_scoring_parameter`.
+pub trait ParametrizedScoring<T>: Scoring<T> {
+	/// The external parameter.
+	type Parameter;
+
+	/// Sets the parameter used to compute the scores of transactions.
+	///
+	/// Returns `false` if the scores can't have changed (e.g. the parameter is the same), so that
+	/// the pool can skip recomputing them.
+	fn set_parameter(&mut self, parameter: Self::Parameter) -> bool;
+
+	/// Recomputes the scores of transactions from a particular sender after the parameter changed.
+	///
+	/// Returns `true` if any score changed, so that the pool re-sorts only the affected senders.
+	/// NOTE: you can safely assume that both slices have the same length.
+	fn rescore(&self, txs: &[Transaction<T>], scores: &mut [Self::Score]) -> bool;
+}
+
 /// A score with a reference to the transaction.
 #[derive(Debug)]
 pub struct ScoreWithRef<T, S> {
@@ -142,9 +162,13 @@ impl<S: cmp::Ord, T> Eq for ScoreWithRef<T, S> {}
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use std::time::Instant;
 
 	fn score(score: u64, insertion_id: u64) -> ScoreWithRef<(), u64> {
-		ScoreWithRef { score, transaction: Transaction { insertion_id, transaction: Default::default() } }
+		ScoreWithRef {
+			score,
+			transaction: Transaction { insertion_id, inserted_at: Instant::now(), transaction: Default::default() },
+		}
 	}
 
 	#[test]
@@ -0,0 +1,374 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A transaction pool that can be shared between threads.
+//!
+//! Mutations are serialized on a single lock, but readers never take it: after every mutation
+//! the writer publishes an immutable `PoolView` of the pool, tagged with an increasing epoch.
+//! Publishing only rebuilds the entries of senders whose transactions changed, the view is split
+//! into shards and the shards without changes are shared with the previous view.
+
+use std::borrow::Borrow;
+use std::collections::{hash_map::RandomState, BTreeSet, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+use log::trace;
+use parking_lot::{Mutex, RwLock};
+
+use crate::{
+	error,
+	listener::{Listener, NoopListener},
+	pool::{Pool, Reinserted, Transaction},
+	ready::{Readiness, Ready},
+	replace::ShouldReplace,
+	scoring::{ParametrizedScoring, ScoreWithRef, Scoring},
+	status::LightStatus,
+	VerifiedTransaction,
+};
+
+/// Number of shards of a `ShardedMap`.
+const SHARDS: usize = 64;
+
+/// A map split into shards which are shared between clones until they are modified.
+#[derive(Debug)]
+struct ShardedMap<K, V> {
+	hasher: RandomState,
+	shards: Vec<Arc<HashMap<K, V>>>,
+}
+
+impl<K, V> Clone for ShardedMap<K, V> {
+	fn clone(&self) -> Self {
+		ShardedMap { hasher: self.hasher.clone(), shards: self.shards.clone() }
+	}
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> ShardedMap<K, V> {
+	fn new() -> Self {
+		ShardedMap { hasher: RandomState::new(), shards: (0..SHARDS).map(|_| Default::default()).collect() }
+	}
+
+	fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> usize {
+		self.hasher.hash_one(key) as usize % SHARDS
+	}
+
+	fn get<Q: Eq + Hash + ?Sized>(&self, key: &Q) -> Option<&V>
+	where
+		K: Borrow<Q>,
+	{
+		self.shards[self.shard(key)].get(key)
+	}
+
+	/// Inserts a value, copying the shard if it's shared with other maps.
+	fn insert(&mut self, key: K, value: V) {
+		let shard = self.shard(&key);
+		Arc::make_mut(&mut self.shards[shard]).insert(key, value);
+	}
+
+	/// Removes a value, copying the shard if it's shared with other maps.
+	fn remove(&mut self, key: &K) -> Option<V> {
+		let shard = self.shard(key);
+		if !self.shards[shard].contains_key(key) {
+			return None;
+		}
+		Arc::make_mut(&mut self.shards[shard]).remove(key)
+	}
+
+	fn values(&self) -> impl Iterator<Item = &V> {
+		self.shards.iter().flat_map(|shard| shard.values())
+	}
+}
+
+/// Transactions from a single sender ordered by `Scoring`, along with their scores.
+#[derive(Debug)]
+struct SenderView<T, S> {
+	transactions: Vec<Transaction<T>>,
+	scores: Vec<S>,
+}
+
+/// An immutable view of the pool at a particular epoch.
+#[derive(Debug)]
+pub struct PoolView<T: VerifiedTransaction, S: Scoring<T>> {
+	epoch: u64,
+	status: LightStatus,
+	senders: ShardedMap<T::Sender, Arc<SenderView<T, S::Score>>>,
+	by_hash: ShardedMap<T::Hash, Arc<T>>,
+}
+
+impl<T: VerifiedTransaction, S: Scoring<T>> PoolView<T, S> {
+	fn empty() -> Self {
+		PoolView { epoch: 0, status: LightStatus::default(), senders: ShardedMap::new(), by_hash: ShardedMap::new() }
+	}
+
+	/// Returns the epoch of this view.
+	///
+	/// The epoch is incremented every time a modified pool is published.
+	pub fn epoch(&self) -> u64 {
+		self.epoch
+	}
+
+	/// Returns light status of the pool at this epoch.
+	pub fn light_status(&self) -> LightStatus {
+		self.status.clone()
+	}
+
+	/// Returns a transaction if it was part of the pool at this epoch or `None` otherwise.
+	pub fn find(&self, hash: &T::Hash) -> Option<Arc<T>> {
+		self.by_hash.get(hash).cloned()
+	}
+
+	/// Returns an iterator of pending (ready) transactions.
+	pub fn pending<R: Ready<T>>(&self, ready: R) -> ViewPendingIterator<'_, T, R, S> {
+		let best_transactions = self.senders.values().filter_map(|view| Self::best_of(view)).collect();
+		ViewPendingIterator { ready, best_transactions, view: self }
+	}
+
+	/// Returns pending (ready) transactions from given sender.
+	pub fn pending_from_sender<R: Ready<T>>(&self, ready: R, sender: &T::Sender) -> ViewPendingIterator<'_, T, R, S> {
+		let mut best_transactions = BTreeSet::new();
+		if let Some(best) = self.senders.get(sender).and_then(|view| Self::best_of(view)) {
+			best_transactions.insert(best);
+		}
+		ViewPendingIterator { ready, best_transactions, view: self }
+	}
+
+	fn best_of(view: &SenderView<T, S::Score>) -> Option<(ScoreWithRef<T, S::Score>, usize)> {
+		let score = view.scores.first()?.clone();
+		Some((ScoreWithRef::new(score, view.transactions[0].clone()), 0))
+	}
+
+	/// Builds the view of the next epoch, rebuilding the entries of `changed` senders only.
+	fn next<L, I>(&self, pool: &Pool<T, S, L>, changed: I) -> Self
+	where
+		L: Listener<T>,
+		I: IntoIterator<Item = T::Sender>,
+	{
+		let mut senders = self.senders.clone();
+		let mut by_hash = self.by_hash.clone();
+
+		for sender in changed {
+			if let Some(old) = senders.remove(&sender) {
+				for tx in &old.transactions {
+					by_hash.remove(tx.hash());
+				}
+			}
+
+			let transactions = match pool.sender_transactions(&sender) {
+				Some(transactions) if !transactions.is_empty() => transactions,
+				_ => continue,
+			};
+			let view = SenderView {
+				transactions: transactions.iter().cloned().collect(),
+				scores: transactions.scores().to_vec(),
+			};
+			for tx in &view.transactions {
+				by_hash.insert(tx.hash().clone(), tx.transaction.clone());
+			}
+			senders.insert(sender, Arc::new(view));
+		}
+
+		PoolView { epoch: self.epoch + 1, status: pool.light_status(), senders, by_hash }
+	}
+}
+
+/// An iterator over pending (ready) transactions of a `PoolView`.
+pub struct ViewPendingIterator<'a, T, R, S>
+where
+	T: VerifiedTransaction + 'a,
+	S: Scoring<T> + 'a,
+{
+	ready: R,
+	best_transactions: BTreeSet<(ScoreWithRef<T, S::Score>, usize)>,
+	view: &'a PoolView<T, S>,
+}
+
+impl<'a, T, R, S> Iterator for ViewPendingIterator<'a, T, R, S>
+where
+	T: VerifiedTransaction,
+	R: Ready<T>,
+	S: Scoring<T>,
+{
+	type Item = Arc<T>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while !self.best_transactions.is_empty() {
+			let (best, index) = {
+				let best = self.best_transactions.iter().next().expect("current_best is not empty; qed").clone();
+				self.best_transactions.take(&best).expect("Just taken from iterator; qed")
+			};
+
+			let tx_state = self.ready.is_ready(&best.transaction);
+			// Add the next best sender's transaction when applicable
+			if let Readiness::Ready | Readiness::Stale = tx_state {
+				let next = index + 1;
+				if let Some(sender) = self.view.senders.get(best.transaction.sender()) {
+					if next < sender.transactions.len() {
+						let score = sender.scores[next].clone();
+						let tx = sender.transactions[next].clone();
+						self.best_transactions.insert((ScoreWithRef::new(score, tx), next));
+					}
+				}
+			}
+
+			if tx_state == Readiness::Ready {
+				return Some(best.transaction.transaction);
+			}
+
+			trace!("[{:?}] Ignoring {:?} transaction.", best.transaction.hash(), tx_state);
+		}
+
+		None
+	}
+}
+
+/// A transaction pool that can be shared between threads.
+///
+/// All the mutating methods take `&self`. Readers use `view` to get a consistent snapshot
+/// of the pool which doesn't block (and isn't blocked by) concurrent imports.
+#[derive(Debug)]
+pub struct SharedPool<T: VerifiedTransaction, S: Scoring<T>, L = NoopListener> {
+	pool: Mutex<Pool<T, S, L>>,
+	view: RwLock<Arc<PoolView<T, S>>>,
+}
+
+impl<T, S, L> SharedPool<T, S, L>
+where
+	T: VerifiedTransaction,
+	S: Scoring<T>,
+	L: Listener<T>,
+{
+	/// Creates a new `SharedPool` wrapping given `pool`.
+	pub fn new(mut pool: Pool<T, S, L>) -> Self {
+		pool.track_changes();
+		let senders = pool.senders().cloned().collect::<Vec<_>>();
+		let view = PoolView { epoch: 0, ..PoolView::empty().next(&pool, senders) };
+		SharedPool { pool: Mutex::new(pool), view: RwLock::new(Arc::new(view)) }
+	}
+
+	/// Returns the most recently published view of the pool.
+	pub fn view(&self) -> Arc<PoolView<T, S>> {
+		self.view.read().clone()
+	}
+
+	/// Attempts to import new transaction to the pool.
+	///
+	/// See `Pool::import` for details.
+	pub fn import(&self, transaction: T, replace: &dyn ShouldReplace<T>) -> error::Result<Arc<T>, T::Hash, S::Score> {
+		self.with_pool(|pool| pool.import(transaction, replace))
+	}
+
+	/// Imports a batch of transactions, publishing a new view only once all of them are imported.
+	///
+	/// Results are returned in the order of `transactions`.
+	pub fn import_batch<I>(
+		&self,
+		transactions: I,
+		replace: &dyn ShouldReplace<T>,
+	) -> Vec<error::Result<Arc<T>, T::Hash, S::Score>>
+	where
+		I: IntoIterator<Item = T>,
+	{
+		self.with_pool(|pool| pool.import_many(transactions, replace))
+	}
+
+	/// Re-inserts transactions from retracted blocks.
+	///
+	/// See `Pool::reinsert_retracted` for details.
+	pub fn reinsert_retracted<I, R>(
+		&self,
+		transactions: I,
+		replace: &dyn ShouldReplace<T>,
+		ready: R,
+	) -> Reinserted<T, T::Hash, S::Score>
+	where
+		I: IntoIterator<Item = T>,
+		R: Ready<T>,
+	{
+		self.with_pool(|pool| pool.reinsert_retracted(transactions, replace, ready))
+	}
+
+	/// Removes single transaction from the pool.
+	///
+	/// See `Pool::remove` for details.
+	pub fn remove(&self, hash: &T::Hash, is_invalid: bool) -> Option<Arc<T>> {
+		self.with_pool(|pool| pool.remove(hash, is_invalid))
+	}
+
+	/// Removes a batch of transactions from the pool.
+	///
+	/// See `Pool::remove_many` for details.
+	pub fn remove_many<'a, I>(&self, hashes: I, is_invalid: bool) -> Vec<Option<Arc<T>>>
+	where
+		I: IntoIterator<Item = &'a T::Hash>,
+		T::Hash: 'a,
+	{
+		self.with_pool(|pool| pool.remove_many(hashes, is_invalid))
+	}
+
+	/// Removes a transaction included in a block from the pool.
+	///
+	/// See `Pool::remove_mined` for details.
+	pub fn remove_mined(&self, hash: &T::Hash) -> Option<Arc<T>> {
+		self.with_pool(|pool| pool.remove_mined(hash))
+	}
+
+	/// Removes all stalled transactions from given sender list (or from all senders).
+	pub fn cull<R: Ready<T>>(&self, senders: Option<&[T::Sender]>, ready: R) -> usize {
+		self.with_pool(|pool| pool.cull(senders, ready))
+	}
+
+	/// Update score of transactions of a particular sender.
+	pub fn update_scores(&self, sender: &T::Sender, event: S::Event) {
+		self.with_pool(|pool| pool.update_scores(sender, event))
+	}
+
+	/// Sets the external parameter of the scoring.
+	///
+	/// See `Pool::set_scoring_parameter` for details. Transactions are rescored right away to publish the new view.
+	pub fn set_scoring_parameter(&self, parameter: S::Parameter)
+	where
+		S: ParametrizedScoring<T>,
+	{
+		self.with_pool(|pool| pool.set_scoring_parameter(parameter))
+	}
+
+	/// Removes all transactions which spent more than `Options::max_age` in the pool.
+	pub fn expire(&self) -> usize {
+		self.with_pool(|pool| pool.expire())
+	}
+
+	/// Clears pool from all transactions.
+	pub fn clear(&self) {
+		self.with_pool(|pool| pool.clear())
+	}
+
+	/// Returns a transaction if it's part of the pool or `None` otherwise.
+	///
+	/// Served from the most recently published view, without taking the pool lock.
+	pub fn find(&self, hash: &T::Hash) -> Option<Arc<T>> {
+		self.view.read().find(hash)
+	}
+
+	/// Runs `f` with exclusive access to the underlying pool and publishes a new view
+	/// if any transaction changed.
+	pub fn with_pool<F, R>(&self, f: F) -> R
+	where
+		F: FnOnce(&mut Pool<T, S, L>) -> R,
+	{
+		let mut pool = self.pool.lock();
+		let result = f(&mut pool);
+		let changed = pool.take_changed_senders();
+		if !changed.is_empty() {
+			// Only writers replace the view and they are serialized by the pool lock,
+			// so the current view can't change under our feet.
+			let next = self.view().next(&pool, changed);
+			*self.view.write() = Arc::new(next);
+		}
+		result
+	}
+}
@@ -0,0 +1,424 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Deterministic simulation of randomized workloads with invariant checks.
+//!
+//! `Simulation` drives a `Pool` with imports, batch imports, replacements, removals and
+//! mined nonces generated from a seed, and checks the consistency of the pool after every step.
+//! Transactions are described by `TransactionSpec`s and built by a closure, so the harness can be
+//! used with any transaction type and `Scoring` which orders transactions of a sender by nonce.
+//!
+//! Available with the `simulation` feature.
+
+use std::collections::{BTreeMap, HashMap};
+use std::{cmp, fmt};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+	listener::Listener,
+	pool::Pool,
+	ready::{Readiness, Ready},
+	replace::ShouldReplace,
+	scoring::Scoring,
+	VerifiedTransaction,
+};
+
+/// Parameters of a generated transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSpec {
+	/// Unique id of the transaction, can be used to derive its hash.
+	pub id: u64,
+	/// Index of the sender, below `Config::senders`.
+	pub sender: u64,
+	/// Nonce of the transaction.
+	pub nonce: u64,
+	/// Fee of the transaction, between `1` and `Config::max_fee` for new transactions
+	/// and above the fee of the replaced transaction for replacements.
+	pub fee: u64,
+	/// Memory usage, between `1` and `Config::max_mem_usage`.
+	pub mem_usage: usize,
+}
+
+/// Relative frequency of actions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Weights {
+	/// Import of a new transaction.
+	pub import: u32,
+	/// Import of `Config::batch_size` new transactions with `Pool::import_many`.
+	pub batch: u32,
+	/// Replacement of a pooled transaction with a higher fee.
+	pub replace: u32,
+	/// Removal of a pooled transaction.
+	pub remove: u32,
+	/// Mining of transactions of a sender followed by a cull.
+	pub mine: u32,
+}
+
+impl Default for Weights {
+	fn default() -> Self {
+		Weights { import: 50, batch: 10, replace: 15, remove: 10, mine: 15 }
+	}
+}
+
+/// Configuration of a `Simulation`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+	/// Seed of the workload, runs with the same seed perform the same actions.
+	pub seed: u64,
+	/// Number of senders, must not be zero.
+	pub senders: u64,
+	/// Maximal fee of a new transaction, must not be zero.
+	pub max_fee: u64,
+	/// Maximal memory usage of a transaction, must not be zero.
+	pub max_mem_usage: usize,
+	/// Probability in percent that a new transaction skips a nonce.
+	pub gap_percent: u64,
+	/// Number of transactions imported by a batch.
+	pub batch_size: usize,
+	/// Relative frequency of actions.
+	pub weights: Weights,
+	/// Whether to check the count and memory limits of the pool,
+	/// which may be exceeded if `ShouldReplace` returns `Choice::InsertNew`.
+	pub check_limits: bool,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			seed: 0,
+			senders: 16,
+			max_fee: 100,
+			max_mem_usage: 64,
+			gap_percent: 5,
+			batch_size: 8,
+			weights: Weights::default(),
+			check_limits: true,
+		}
+	}
+}
+
+/// An action performed by a step of the simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+	/// A new transaction was imported.
+	Import(TransactionSpec),
+	/// New transactions were imported as a batch.
+	ImportBatch(Vec<TransactionSpec>),
+	/// A transaction with the same sender and nonce as a pooled one was imported.
+	Replace(TransactionSpec),
+	/// A pooled transaction was removed.
+	Remove(TransactionSpec),
+	/// Transactions of `sender` below `nonce` were mined and the pool was culled.
+	Mine {
+		/// Index of the sender.
+		sender: u64,
+		/// The next nonce of the sender.
+		nonce: u64,
+	},
+}
+
+/// A violated invariant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+	/// Step which violated the invariant, starting at `1`.
+	pub step: usize,
+	/// Action performed by the step.
+	pub action: Action,
+	/// Description of the invariant.
+	pub reason: String,
+}
+
+impl fmt::Display for Violation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Step {} ({:?}): {}", self.step, self.action, self.reason)
+	}
+}
+
+impl std::error::Error for Violation {}
+
+/// Readiness by nonce according to the mined nonces of senders.
+struct NonceReady<'a, H> {
+	specs: &'a HashMap<H, TransactionSpec>,
+	mined: &'a [u64],
+	expected: HashMap<u64, u64>,
+}
+
+impl<'a, H> NonceReady<'a, H> {
+	fn new(specs: &'a HashMap<H, TransactionSpec>, mined: &'a [u64]) -> Self {
+		NonceReady { specs, mined, expected: HashMap::new() }
+	}
+}
+
+impl<'a, T: VerifiedTransaction> Ready<T> for NonceReady<'a, T::Hash> {
+	fn is_ready(&mut self, tx: &T) -> Readiness {
+		let spec = match self.specs.get(tx.hash()) {
+			Some(spec) => spec,
+			None => return Readiness::Future,
+		};
+		let mined = self.mined;
+		let expected = self.expected.entry(spec.sender).or_insert_with(|| mined[spec.sender as usize]);
+		match spec.nonce.cmp(expected) {
+			cmp::Ordering::Less => Readiness::Stale,
+			cmp::Ordering::Equal => {
+				*expected += 1;
+				Readiness::Ready
+			}
+			cmp::Ordering::Greater => Readiness::Future,
+		}
+	}
+}
+
+/// Drives a `Pool` with a randomized workload and checks its invariants after every step.
+///
+/// The invariants are:
+/// - `by_hash`, the senders' transactions and the memory usage agree with each other,
+/// - the best and worst transactions of every sender are up to date,
+/// - transactions of a sender are ordered and within the per-sender limit,
+/// - the count and memory limits are respected (see `Config::check_limits`),
+/// - no pooled transaction is below the mined nonce of its sender,
+/// - pending transactions of a sender have consecutive nonces starting at the mined nonce.
+pub struct Simulation<T: VerifiedTransaction, S: Scoring<T>, L, F> {
+	pool: Pool<T, S, L>,
+	replace: Box<dyn ShouldReplace<T>>,
+	build: F,
+	config: Config,
+	rng: StdRng,
+	step: usize,
+	next_id: u64,
+	next_nonce: Vec<u64>,
+	mined: Vec<u64>,
+	/// Imported transactions by id, some of them might have been removed from the pool since.
+	imported: BTreeMap<u64, T::Hash>,
+	specs: HashMap<T::Hash, TransactionSpec>,
+}
+
+impl<T: VerifiedTransaction, S: Scoring<T>, L, F> fmt::Debug for Simulation<T, S, L, F> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Simulation")
+			.field("config", &self.config)
+			.field("step", &self.step)
+			.field("imported", &self.imported.len())
+			.finish()
+	}
+}
+
+impl<T, S, L, F> Simulation<T, S, L, F>
+where
+	T: VerifiedTransaction,
+	S: Scoring<T>,
+	L: Listener<T>,
+	F: FnMut(&TransactionSpec) -> T,
+{
+	/// Creates a new simulation of `pool`, building transactions with `build`.
+	///
+	/// Transactions built from specs with different ids must have different hashes.
+	///
+	/// Panics if `config.senders`, `config.max_fee` or `config.max_mem_usage` is zero.
+	pub fn new<R: ShouldReplace<T> + 'static>(pool: Pool<T, S, L>, replace: R, config: Config, build: F) -> Self {
+		assert!(config.senders > 0, "Config::senders must not be zero");
+		assert!(config.max_fee > 0, "Config::max_fee must not be zero");
+		assert!(config.max_mem_usage > 0, "Config::max_mem_usage must not be zero");
+		let senders = config.senders as usize;
+		Simulation {
+			pool,
+			replace: Box::new(replace),
+			build,
+			rng: StdRng::seed_from_u64(config.seed),
+			config,
+			step: 0,
+			next_id: 0,
+			next_nonce: vec![0; senders],
+			mined: vec![0; senders],
+			imported: BTreeMap::new(),
+			specs: HashMap::new(),
+		}
+	}
+
+	/// Returns the simulated pool.
+	pub fn pool(&self) -> &Pool<T, S, L> {
+		&self.pool
+	}
+
+	/// Returns the number of steps performed so far.
+	pub fn steps(&self) -> usize {
+		self.step
+	}
+
+	/// Performs `steps` steps, stopping at the first violated invariant.
+	pub fn run(&mut self, steps: usize) -> Result<(), Violation> {
+		for _ in 0..steps {
+			self.step()?;
+		}
+		Ok(())
+	}
+
+	/// Performs a single random action and checks the invariants.
+	pub fn step(&mut self) -> Result<Action, Violation> {
+		self.step += 1;
+		let action = self.next_action();
+		self.apply(&action);
+		match self.check(&action) {
+			Ok(()) => Ok(action),
+			Err(reason) => Err(Violation { step: self.step, action, reason }),
+		}
+	}
+
+	fn next_action(&mut self) -> Action {
+		let weights = self.config.weights.clone();
+		let total = weights.import + weights.batch + weights.replace + weights.remove + weights.mine;
+		let mut roll = self.rng.gen_range(0, cmp::max(total, 1));
+		let mut pick = |weight: u32| {
+			let picked = roll < weight;
+			roll = roll.saturating_sub(weight);
+			picked
+		};
+
+		if pick(weights.import) {
+			Action::Import(self.new_spec())
+		} else if pick(weights.batch) {
+			Action::ImportBatch((0..self.config.batch_size).map(|_| self.new_spec()).collect())
+		} else if pick(weights.replace) {
+			match self.pooled_spec() {
+				Some(old) => {
+					let mut spec = self.new_spec_of(old.sender, old.nonce);
+					spec.fee = old.fee + 1 + self.rng.gen_range(0, self.config.max_fee);
+					Action::Replace(spec)
+				}
+				None => Action::Import(self.new_spec()),
+			}
+		} else if pick(weights.remove) {
+			match self.pooled_spec() {
+				Some(spec) => Action::Remove(spec),
+				None => Action::Import(self.new_spec()),
+			}
+		} else {
+			let sender = self.rng.gen_range(0, self.config.senders);
+			let mined = self.mined[sender as usize];
+			let nonce = cmp::min(mined + 1 + self.rng.gen_range(0, 3), self.next_nonce[sender as usize]);
+			Action::Mine { sender, nonce }
+		}
+	}
+
+	fn new_spec(&mut self) -> TransactionSpec {
+		let sender = self.rng.gen_range(0, self.config.senders);
+		let mut nonce = self.next_nonce[sender as usize];
+		if self.rng.gen_range(0, 100) < self.config.gap_percent {
+			nonce += 1;
+		}
+		self.next_nonce[sender as usize] = nonce + 1;
+		self.new_spec_of(sender, nonce)
+	}
+
+	fn new_spec_of(&mut self, sender: u64, nonce: u64) -> TransactionSpec {
+		let id = self.next_id;
+		self.next_id += 1;
+		TransactionSpec {
+			id,
+			sender,
+			nonce,
+			fee: 1 + self.rng.gen_range(0, self.config.max_fee),
+			mem_usage: 1 + self.rng.gen_range(0, self.config.max_mem_usage),
+		}
+	}
+
+	/// Picks a random transaction which is still in the pool.
+	fn pooled_spec(&mut self) -> Option<TransactionSpec> {
+		let pool = &self.pool;
+		let specs = &mut self.specs;
+		self.imported.retain(|_, hash| match pool.find(hash) {
+			Some(_) => true,
+			None => {
+				specs.remove(hash);
+				false
+			}
+		});
+		if self.imported.is_empty() {
+			return None;
+		}
+		let index = self.rng.gen_range(0, self.imported.len());
+		self.imported.values().nth(index).and_then(|hash| self.specs.get(hash)).cloned()
+	}
+
+	fn import(&mut self, specs: &[TransactionSpec]) {
+		let transactions = specs
+			.iter()
+			.map(|spec| {
+				let tx = (self.build)(spec);
+				self.imported.insert(spec.id, tx.hash().clone());
+				self.specs.insert(tx.hash().clone(), spec.clone());
+				tx
+			})
+			.collect::<Vec<_>>();
+		match transactions.len() {
+			1 => {
+				let tx = transactions.into_iter().next().expect("one transaction; qed");
+				let _ = self.pool.import(tx, &*self.replace);
+			}
+			_ => {
+				self.pool.import_many(transactions, &*self.replace);
+			}
+		}
+	}
+
+	fn apply(&mut self, action: &Action) {
+		match action {
+			Action::Import(spec) | Action::Replace(spec) => self.import(std::slice::from_ref(spec)),
+			Action::ImportBatch(specs) => self.import(specs),
+			Action::Remove(spec) => {
+				if let Some(hash) = self.imported.get(&spec.id) {
+					self.pool.remove(hash, false);
+				}
+			}
+			Action::Mine { sender, nonce } => {
+				self.mined[*sender as usize] = *nonce;
+				self.pool.cull(None, NonceReady::new(&self.specs, &self.mined));
+			}
+		}
+	}
+
+	fn check(&self, action: &Action) -> Result<(), String> {
+		self.pool.check_invariants()?;
+
+		if self.config.check_limits {
+			let status = self.pool.light_status();
+			let options = self.pool.options();
+			if status.transaction_count > options.max_count {
+				return Err(format!("{} transactions above the limit", status.transaction_count));
+			}
+			if status.mem_usage > options.max_mem_usage {
+				return Err(format!("Memory usage {} above the limit", status.mem_usage));
+			}
+		}
+
+		if let Action::Mine { sender, nonce } = action {
+			let stale = self.imported.values().filter(|hash| self.pool.find(hash).is_some()).find(|hash| {
+				let spec = &self.specs[*hash];
+				spec.sender == *sender && spec.nonce < *nonce
+			});
+			if let Some(hash) = stale {
+				return Err(format!("Mined transaction {:?} was not culled", hash));
+			}
+		}
+
+		let mut last_nonces = HashMap::new();
+		for tx in self.pool.pending(NonceReady::new(&self.specs, &self.mined)) {
+			let spec = &self.specs[tx.hash()];
+			let expected = match last_nonces.get(&spec.sender) {
+				Some(last) => last + 1,
+				None => self.mined[spec.sender as usize],
+			};
+			if spec.nonce != expected {
+				return Err(format!("Pending {:?} has nonce {}, expected {}", tx.hash(), spec.nonce, expected));
+			}
+			last_nonces.insert(spec.sender, spec.nonce);
+		}
+
+		Ok(())
+	}
+}
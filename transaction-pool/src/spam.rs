@@ -0,0 +1,197 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Anti-spam policy protecting the pool from churn.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use std::{cmp, fmt};
+
+use crate::VerifiedTransaction;
+
+/// Limits the number of replacements from a single sender within a time window.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplacementLimit {
+	/// Maximal number of replacements within `window`.
+	pub max_replacements: usize,
+	/// Length of the window.
+	pub window: Duration,
+}
+
+type IsLocal<T> = Box<dyn Fn(&T) -> bool + Send>;
+
+/// Minimal bump of a transaction value required to replace or evict another transaction.
+pub struct ScoreBump<T> {
+	/// Minimal bump in percent.
+	pub percent: u32,
+	/// Returns the value of a transaction to compare (e.g. gas price).
+	pub value: Box<dyn Fn(&T) -> u128 + Send>,
+}
+
+impl<T> fmt::Debug for ScoreBump<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ScoreBump").field("percent", &self.percent).finish()
+	}
+}
+
+/// Anti-spam policy of the pool.
+///
+/// By default nothing is limited.
+pub struct AntiSpam<T> {
+	/// Limits how often a sender can replace its own transactions.
+	pub replacement_limit: Option<ReplacementLimit>,
+	/// Minimal bump required to replace a transaction of the same sender, or to evict a transaction
+	/// chosen by `ShouldReplace` when the pool is full.
+	pub min_score_bump: Option<ScoreBump<T>>,
+	/// Decides if a transaction is local. Local transactions are never evicted to make room
+	/// for other transactions.
+	pub is_local: Option<IsLocal<T>>,
+}
+
+impl<T> Default for AntiSpam<T> {
+	fn default() -> Self {
+		AntiSpam { replacement_limit: None, min_score_bump: None, is_local: None }
+	}
+}
+
+impl<T> fmt::Debug for AntiSpam<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("AntiSpam")
+			.field("replacement_limit", &self.replacement_limit)
+			.field("min_score_bump", &self.min_score_bump)
+			.field("is_local", &self.is_local.is_some())
+			.finish()
+	}
+}
+
+/// Number of tracked senders below which expired entries are not pruned.
+const MIN_PRUNE_LEN: usize = 64;
+
+/// Anti-spam policy along with the state needed to enforce it.
+///
+/// Expired entries are dropped when their sender shows up again, and all of them are pruned
+/// whenever the number of tracked senders doubles, so that one-off senders don't accumulate.
+#[derive(Debug)]
+pub(crate) struct SpamGuard<T: VerifiedTransaction> {
+	policy: AntiSpam<T>,
+	replacements: HashMap<T::Sender, VecDeque<Instant>>,
+	banned: HashMap<T::Sender, Instant>,
+	/// Number of tracked senders triggering the next pruning.
+	prune_at: usize,
+}
+
+impl<T: VerifiedTransaction> Default for SpamGuard<T> {
+	fn default() -> Self {
+		SpamGuard {
+			policy: AntiSpam::default(),
+			replacements: HashMap::new(),
+			banned: HashMap::new(),
+			prune_at: MIN_PRUNE_LEN,
+		}
+	}
+}
+
+impl<T: VerifiedTransaction> SpamGuard<T> {
+	pub(crate) fn set_policy(&mut self, policy: AntiSpam<T>) {
+		self.policy = policy;
+	}
+
+	pub(crate) fn is_local(&self, tx: &T) -> bool {
+		match self.policy.is_local {
+			Some(ref is_local) => is_local(tx),
+			None => false,
+		}
+	}
+
+	/// Returns true if `new` is valuable enough to evict `old`.
+	pub(crate) fn is_bumped_enough(&self, old: &T, new: &T) -> bool {
+		match self.policy.min_score_bump {
+			Some(ScoreBump { percent, ref value }) => {
+				value(new).saturating_mul(100) >= value(old).saturating_mul(100 + u128::from(percent))
+			}
+			None => true,
+		}
+	}
+
+	pub(crate) fn ban(&mut self, sender: T::Sender, until: Instant, now: Instant) {
+		self.banned.insert(sender, until);
+		self.maybe_prune(now);
+	}
+
+	pub(crate) fn unban(&mut self, sender: &T::Sender) -> bool {
+		self.banned.remove(sender).is_some()
+	}
+
+	pub(crate) fn is_banned(&mut self, sender: &T::Sender, now: Instant) -> bool {
+		match self.banned.get(sender) {
+			Some(until) if *until > now => true,
+			Some(_) => {
+				self.banned.remove(sender);
+				false
+			}
+			None => false,
+		}
+	}
+
+	/// Returns true if `sender` is not allowed to replace any more transactions at the moment.
+	pub(crate) fn replacement_limit_reached(&mut self, sender: &T::Sender, now: Instant) -> bool {
+		let limit = match self.policy.replacement_limit {
+			Some(ref limit) => limit,
+			None => return false,
+		};
+		let recent_len = match self.replacements.get_mut(sender) {
+			Some(recent) => {
+				expire_replacements(recent, limit.window, now);
+				recent.len()
+			}
+			None => 0,
+		};
+		if recent_len == 0 {
+			self.replacements.remove(sender);
+		}
+		recent_len >= limit.max_replacements
+	}
+
+	pub(crate) fn note_replacement(&mut self, sender: &T::Sender, now: Instant) {
+		if self.policy.replacement_limit.is_some() {
+			self.replacements.entry(sender.clone()).or_default().push_back(now);
+			self.maybe_prune(now);
+		}
+	}
+
+	/// Drops expired bans and replacements if the number of tracked senders doubled since the last pruning.
+	fn maybe_prune(&mut self, now: Instant) {
+		if self.replacements.len() + self.banned.len() < self.prune_at {
+			return;
+		}
+		self.banned.retain(|_, until| *until > now);
+		match self.policy.replacement_limit {
+			Some(ref limit) => self.replacements.retain(|_, recent| {
+				expire_replacements(recent, limit.window, now);
+				!recent.is_empty()
+			}),
+			None => self.replacements.clear(),
+		}
+		self.prune_at = cmp::max(MIN_PRUNE_LEN, 2 * (self.replacements.len() + self.banned.len()));
+	}
+
+	#[cfg(test)]
+	pub(crate) fn tracked_senders(&self) -> usize {
+		self.replacements.len() + self.banned.len()
+	}
+}
+
+/// Drops replacements which happened more than `window` ago.
+fn expire_replacements(recent: &mut VecDeque<Instant>, window: Duration, now: Instant) {
+	while let Some(&at) = recent.front() {
+		if now.saturating_duration_since(at) < window {
+			break;
+		}
+		recent.pop_front();
+	}
+}
@@ -0,0 +1,435 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Dependency-aware readiness based on tags.
+//!
+//! Instead of relying on an external `Ready` implementation, transactions declare the tags
+//! they `provide` and `require`. A transaction is ready when each of its required tags is
+//! either provided externally (e.g. by transactions already included in a block) or by
+//! another ready transaction in the pool.
+//!
+//! `TaggedPool` keeps the dependency graph in sync with the underlying `Pool`:
+//! - transactions are promoted from future to ready as soon as their requirements are met,
+//! - when a provider leaves the pool (other than by being culled), the ready transactions
+//!   depending on it are removed as well (and reported to the `Listener` as `invalid`),
+//! - culled transactions are assumed to be included, so their tags become provided externally.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::mem;
+use std::sync::Arc;
+
+use crate::{
+	error,
+	listener::{Listener, NoopListener},
+	options::Options,
+	pool::Pool,
+	ready::{Readiness, Ready},
+	replace::ShouldReplace,
+	scoring::Scoring,
+	status::{LightStatus, Status},
+	VerifiedTransaction,
+};
+
+/// A transaction declaring its dependencies with tags.
+pub trait Tagged: VerifiedTransaction {
+	/// Tag type.
+	type Tag: fmt::Debug + Eq + Clone + Hash;
+
+	/// Tags provided by this transaction.
+	fn provides(&self) -> &[Self::Tag];
+
+	/// Tags that need to be provided before this transaction is ready.
+	fn requires(&self) -> &[Self::Tag];
+}
+
+#[derive(Debug)]
+struct Node<T> {
+	transaction: Arc<T>,
+	ready: bool,
+}
+
+/// Dependencies between transactions in the pool.
+#[derive(Debug)]
+struct DependencyGraph<T: Tagged> {
+	/// Tags provided outside of the pool.
+	external: HashSet<T::Tag>,
+	/// Number of ready transactions providing a tag.
+	ready_providers: HashMap<T::Tag, usize>,
+	/// Transactions requiring a tag.
+	dependents: HashMap<T::Tag, HashSet<T::Hash>>,
+	nodes: HashMap<T::Hash, Node<T>>,
+}
+
+impl<T: Tagged> Default for DependencyGraph<T> {
+	fn default() -> Self {
+		DependencyGraph {
+			external: Default::default(),
+			ready_providers: Default::default(),
+			dependents: Default::default(),
+			nodes: Default::default(),
+		}
+	}
+}
+
+impl<T: Tagged> DependencyGraph<T> {
+	fn is_provided(&self, tag: &T::Tag) -> bool {
+		self.external.contains(tag) || self.ready_providers.contains_key(tag)
+	}
+
+	fn is_ready(&self, hash: &T::Hash) -> bool {
+		matches!(self.nodes.get(hash), Some(node) if node.ready)
+	}
+
+	fn insert(&mut self, transaction: Arc<T>) {
+		let hash = transaction.hash().clone();
+		for tag in transaction.requires() {
+			self.dependents.entry(tag.clone()).or_default().insert(hash.clone());
+		}
+		self.nodes.insert(hash.clone(), Node { transaction, ready: false });
+		self.promote(vec![hash]);
+	}
+
+	/// Marks `tags` as provided externally, promoting transactions which depend on them.
+	fn provide(&mut self, tags: &[T::Tag]) {
+		let mut candidates = Vec::new();
+		for tag in tags {
+			if self.external.insert(tag.clone()) {
+				candidates.extend(self.dependents.get(tag).into_iter().flatten().cloned());
+			}
+		}
+		self.promote(candidates);
+	}
+
+	/// Stops treating `tags` as provided externally.
+	///
+	/// Returns the ready transactions which are no longer ready because of the retraction.
+	fn retract(&mut self, tags: &[T::Tag]) -> Vec<Arc<T>> {
+		let mut lost = Vec::new();
+		for tag in tags {
+			if self.external.remove(tag) && !self.ready_providers.contains_key(tag) {
+				lost.push(tag.clone());
+			}
+		}
+		self.demote(lost)
+	}
+
+	/// Promotes `candidates` (and transitively their dependents) to ready if their requirements are met.
+	fn promote(&mut self, mut candidates: Vec<T::Hash>) {
+		while let Some(hash) = candidates.pop() {
+			let transaction = match self.nodes.get(&hash) {
+				Some(node) if !node.ready => node.transaction.clone(),
+				_ => continue,
+			};
+			if !transaction.requires().iter().all(|tag| self.is_provided(tag)) {
+				continue;
+			}
+
+			self.nodes.get_mut(&hash).expect("node exists, checked above; qed").ready = true;
+			for tag in transaction.provides() {
+				let count = self.ready_providers.entry(tag.clone()).or_insert(0);
+				*count += 1;
+				if *count == 1 && !self.external.contains(tag) {
+					candidates.extend(self.dependents.get(tag).into_iter().flatten().cloned());
+				}
+			}
+		}
+	}
+
+	/// Removes a transaction from the graph.
+	///
+	/// Returns the ready transactions which are no longer ready because of the removal.
+	fn remove(&mut self, hash: &T::Hash) -> Vec<Arc<T>> {
+		let node = match self.nodes.remove(hash) {
+			Some(node) => node,
+			None => return Vec::new(),
+		};
+		for tag in node.transaction.requires() {
+			if let Some(dependents) = self.dependents.get_mut(tag) {
+				dependents.remove(hash);
+				if dependents.is_empty() {
+					self.dependents.remove(tag);
+				}
+			}
+		}
+		if !node.ready {
+			return Vec::new();
+		}
+
+		let mut lost = Vec::new();
+		self.withdraw(&node.transaction, &mut lost);
+		self.demote(lost)
+	}
+
+	/// Demotes ready transactions which depend (transitively) on `lost` tags, returning them.
+	fn demote(&mut self, mut lost: Vec<T::Tag>) -> Vec<Arc<T>> {
+		let mut demoted = Vec::new();
+		while let Some(tag) = lost.pop() {
+			let dependents = self.dependents.get(&tag).into_iter().flatten().cloned().collect::<Vec<_>>();
+			for hash in dependents {
+				let transaction = match self.nodes.get_mut(&hash) {
+					Some(node) if node.ready => {
+						node.ready = false;
+						node.transaction.clone()
+					}
+					_ => continue,
+				};
+				self.withdraw(&transaction, &mut lost);
+				demoted.push(transaction);
+			}
+		}
+		demoted
+	}
+
+	/// Withdraws tags provided by a ready transaction, collecting tags which are no longer provided.
+	fn withdraw(&mut self, transaction: &T, lost: &mut Vec<T::Tag>) {
+		for tag in transaction.provides() {
+			if let Some(count) = self.ready_providers.get_mut(tag) {
+				*count -= 1;
+				if *count == 0 {
+					self.ready_providers.remove(tag);
+					if !self.external.contains(tag) {
+						lost.push(tag.clone());
+					}
+				}
+			}
+		}
+	}
+
+	/// Returns ready transactions ordered by `rank`, such that a provider of each required tag
+	/// comes before the transaction requiring it.
+	fn ready<F: Fn(&T::Hash) -> usize>(&self, rank: F) -> Vec<Arc<T>> {
+		let mut missing = HashMap::new();
+		let mut available = BTreeMap::new();
+		for (hash, node) in self.nodes.iter().filter(|(_, node)| node.ready) {
+			let required =
+				node.transaction.requires().iter().filter(|tag| !self.external.contains(*tag)).collect::<HashSet<_>>();
+			if required.is_empty() {
+				available.insert(rank(hash), node.transaction.clone());
+			} else {
+				missing.insert(hash.clone(), required.len());
+			}
+		}
+
+		let mut emitted_tags = HashSet::new();
+		let mut result = Vec::with_capacity(available.len() + missing.len());
+		while let Some(&first) = available.keys().next() {
+			let transaction = available.remove(&first).expect("key taken from the map; qed");
+			for tag in transaction.provides() {
+				if self.external.contains(tag) || !emitted_tags.insert(tag.clone()) {
+					continue;
+				}
+				for hash in self.dependents.get(tag).into_iter().flatten() {
+					if let Some(count) = missing.get_mut(hash) {
+						*count -= 1;
+						if *count == 0 {
+							missing.remove(hash);
+							available.insert(rank(hash), self.nodes[hash].transaction.clone());
+						}
+					}
+				}
+			}
+			result.push(transaction);
+		}
+		result
+	}
+}
+
+/// A change of the pool content relevant to the dependency graph.
+#[derive(Debug)]
+enum Change<T> {
+	Added(Arc<T>),
+	Removed(Arc<T>),
+	Culled(Arc<T>),
+}
+
+/// Listener recording changes of the pool content.
+#[derive(Debug)]
+struct ChangeRecorder<T>(Vec<Change<T>>);
+
+impl<T> Listener<T> for ChangeRecorder<T> {
+	fn added(&mut self, tx: &Arc<T>, old: Option<&Arc<T>>) {
+		if let Some(old) = old {
+			self.0.push(Change::Removed(old.clone()));
+		}
+		self.0.push(Change::Added(tx.clone()));
+	}
+
+	fn dropped(&mut self, tx: &Arc<T>, _by: Option<&T>) {
+		self.0.push(Change::Removed(tx.clone()));
+	}
+
+	fn invalid(&mut self, tx: &Arc<T>) {
+		self.0.push(Change::Removed(tx.clone()));
+	}
+
+	fn canceled(&mut self, tx: &Arc<T>) {
+		self.0.push(Change::Removed(tx.clone()));
+	}
+
+	fn culled(&mut self, tx: &Arc<T>) {
+		self.0.push(Change::Culled(tx.clone()));
+	}
+
+	fn expired(&mut self, tx: &Arc<T>) {
+		self.0.push(Change::Removed(tx.clone()));
+	}
+}
+
+/// A transaction pool tracking dependencies between `Tagged` transactions.
+#[derive(Debug)]
+pub struct TaggedPool<T: Tagged, S: Scoring<T>, L = NoopListener> {
+	pool: Pool<T, S, (ChangeRecorder<T>, L)>,
+	graph: DependencyGraph<T>,
+}
+
+impl<T, S, L> TaggedPool<T, S, L>
+where
+	T: Tagged,
+	S: Scoring<T>,
+	L: Listener<T>,
+{
+	/// Creates new `TaggedPool` with given `Scoring`, `Listener` and options.
+	pub fn new(listener: L, scoring: S, options: Options) -> Self {
+		TaggedPool {
+			pool: Pool::new((ChangeRecorder(Vec::new()), listener), scoring, options),
+			graph: DependencyGraph::default(),
+		}
+	}
+
+	/// Attempts to import new transaction to the pool.
+	///
+	/// The transaction is promoted to ready as soon as all the tags it requires are provided.
+	/// See `Pool::import` for details.
+	pub fn import(
+		&mut self,
+		transaction: T,
+		replace: &dyn ShouldReplace<T>,
+	) -> error::Result<Arc<T>, T::Hash, S::Score> {
+		let result = self.pool.import(transaction, replace);
+		self.sync();
+		result
+	}
+
+	/// Removes single transaction from the pool, along with the ready transactions depending on it.
+	pub fn remove(&mut self, hash: &T::Hash, is_invalid: bool) -> Option<Arc<T>> {
+		let result = self.pool.remove(hash, is_invalid);
+		self.sync();
+		result
+	}
+
+	/// Removes all stalled transactions from given sender list (or from all senders).
+	///
+	/// Tags provided by culled transactions are marked as provided externally.
+	pub fn cull<R: Ready<T>>(&mut self, senders: Option<&[T::Sender]>, ready: R) -> usize {
+		let result = self.pool.cull(senders, ready);
+		self.sync();
+		result
+	}
+
+	/// Marks `tags` as provided externally, promoting transactions that require them.
+	///
+	/// Tags stay provided until they are retracted, including the tags of culled transactions.
+	pub fn provide(&mut self, tags: &[T::Tag]) {
+		self.graph.provide(tags);
+	}
+
+	/// Stops treating `tags` as provided externally, e.g. when they are superseded by newer tags
+	/// (like a sender's nonce after a later one is included) or after a reorganization.
+	///
+	/// Returns the transactions which are no longer ready, they stay in the pool as future.
+	pub fn retract(&mut self, tags: &[T::Tag]) -> Vec<Arc<T>> {
+		self.graph.retract(tags)
+	}
+
+	/// Removes all transactions which spent more than `Options::max_age` in the pool.
+	pub fn expire(&mut self) -> usize {
+		let result = self.pool.expire();
+		self.sync();
+		result
+	}
+
+	/// Clears pool from all transactions.
+	pub fn clear(&mut self) {
+		self.pool.clear();
+		self.sync();
+	}
+
+	/// Applies recorded changes of the pool to the dependency graph,
+	/// removing transactions which lost their dependencies.
+	fn sync(&mut self) {
+		loop {
+			let changes = mem::take(&mut (self.pool.listener_mut().0).0);
+			if changes.is_empty() {
+				break;
+			}
+
+			let mut orphans = Vec::new();
+			for change in changes {
+				match change {
+					Change::Added(tx) => self.graph.insert(tx),
+					Change::Removed(tx) => orphans.extend(self.graph.remove(tx.hash())),
+					Change::Culled(tx) => {
+						self.graph.provide(tx.provides());
+						orphans.extend(self.graph.remove(tx.hash()));
+					}
+				}
+			}
+			// orphans may have become ready again by a provider added later in the batch.
+			for tx in orphans {
+				if !self.graph.is_ready(tx.hash()) {
+					self.pool.remove(tx.hash(), true);
+				}
+			}
+		}
+	}
+
+	/// Returns true if the transaction is in the pool and all its requirements are met.
+	pub fn is_ready(&self, hash: &T::Hash) -> bool {
+		self.graph.is_ready(hash)
+	}
+
+	/// Returns ready transactions ordered by priority, such that every transaction
+	/// comes after the transactions providing the tags it requires.
+	pub fn pending(&self) -> Vec<Arc<T>> {
+		let ranks = self
+			.pool
+			.pending(|_: &T| Readiness::Ready)
+			.enumerate()
+			.map(|(rank, tx)| (tx.hash().clone(), rank))
+			.collect::<HashMap<_, _>>();
+		self.graph.ready(|hash| ranks.get(hash).cloned().unwrap_or(usize::MAX))
+	}
+
+	/// Computes the full status of the pool.
+	pub fn status(&self) -> Status {
+		let pending = self.graph.nodes.values().filter(|node| node.ready).count();
+		Status { stalled: 0, pending, future: self.graph.nodes.len() - pending }
+	}
+
+	/// Returns a transaction if it's part of the pool or `None` otherwise.
+	pub fn find(&self, hash: &T::Hash) -> Option<Arc<T>> {
+		self.pool.find(hash)
+	}
+
+	/// Returns light status of the pool.
+	pub fn light_status(&self) -> LightStatus {
+		self.pool.light_status()
+	}
+
+	/// Borrows listener instance.
+	pub fn listener(&self) -> &L {
+		&self.pool.listener().1
+	}
+
+	/// Borrows listener mutably.
+	pub fn listener_mut(&mut self) -> &mut L {
+		&mut self.pool.listener_mut().1
+	}
+}
@@ -8,9 +8,14 @@
 
 use std::cmp;
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use super::Transaction;
-use crate::{pool, scoring, Readiness, Ready, ReplaceTransaction, Scoring, ShouldReplace};
+use crate::{pool, scoring, Clock, Readiness, Ready, ReplaceTransaction, Scoring, ShouldReplace, Verifier};
 use ethereum_types::{H160 as Sender, U256};
 
 #[derive(Debug, Default)]
@@ -109,3 +114,60 @@ impl Ready<Transaction> for NonceReady {
 		}
 	}
 }
+
+pub struct GasPriceVerifier;
+
+impl Verifier<Transaction> for GasPriceVerifier {
+	type Error = &'static str;
+	type VerifiedTransaction = Transaction;
+
+	fn verify_transaction(&self, tx: Transaction) -> Result<Transaction, Self::Error> {
+		if tx.gas_price.is_zero() {
+			Err("zero gas price")
+		} else {
+			Ok(tx)
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ManualClock(Arc<Mutex<Instant>>);
+
+impl Default for ManualClock {
+	fn default() -> Self {
+		ManualClock(Arc::new(Mutex::new(Instant::now())))
+	}
+}
+
+impl ManualClock {
+	pub fn advance(&self, by: Duration) {
+		*self.0.lock().unwrap() += by;
+	}
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> Instant {
+		*self.0.lock().unwrap()
+	}
+}
+
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+	fn wake(self: Arc<Self>) {
+		self.0.unpark();
+	}
+}
+
+/// Runs `future` to completion on the current thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+	let mut future = Box::pin(future);
+	let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+	let mut cx = Context::from_waker(&waker);
+	loop {
+		if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+			return output;
+		}
+		thread::park();
+	}
+}
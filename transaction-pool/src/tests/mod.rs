@@ -9,10 +9,11 @@
 mod helpers;
 mod tx_builder;
 
-use self::helpers::{DummyScoring, NonceReady};
+use self::helpers::{block_on, DummyScoring, GasPriceVerifier, ManualClock, NonceReady};
 use self::tx_builder::TransactionBuilder;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use super::*;
 use ethereum_types::{Address, H256, U256};
@@ -42,12 +43,21 @@ impl VerifiedTransaction for Transaction {
 	}
 }
 
+impl BlockResources for Transaction {
+	fn gas(&self) -> u64 {
+		self.gas.low_u64()
+	}
+	fn size(&self) -> usize {
+		self.mem_usage
+	}
+}
+
 pub type SharedTransaction = Arc<Transaction>;
 
 type TestPool = Pool<Transaction, DummyScoring>;
 
 impl TestPool {
-	pub fn with_limit(max_count: usize) -> Self {
+	pub(crate) fn with_limit(max_count: usize) -> Self {
 		Self::with_options(Options { max_count, ..Default::default() })
 	}
 }
@@ -55,7 +65,7 @@ impl TestPool {
 fn import<S: Scoring<Transaction>, L: Listener<Transaction>>(
 	txq: &mut Pool<Transaction, S, L>,
 	tx: Transaction,
-) -> Result<Arc<Transaction>, Error<<Transaction as VerifiedTransaction>::Hash>> {
+) -> Result<Arc<Transaction>, Error<<Transaction as VerifiedTransaction>::Hash, S::Score>> {
 	txq.import(tx, &mut DummyScoring::default())
 }
 
@@ -122,7 +132,7 @@ fn should_reject_if_above_count() {
 	let tx2 = b.tx().nonce(1).new();
 	let hash = tx2.hash.clone();
 	import(&mut txq, tx1).unwrap();
-	assert_eq!(import(&mut txq, tx2).unwrap_err(), error::Error::TooCheapToEnter(hash, "0x0".into()));
+	assert_eq!(import(&mut txq, tx2).unwrap_err(), error::Error::TooCheapToEnter(hash, 0.into()));
 	assert_eq!(txq.light_status().transaction_count, 1);
 
 	txq.clear();
@@ -145,7 +155,7 @@ fn should_reject_if_above_mem_usage() {
 	let tx2 = b.tx().nonce(2).mem_usage(2).new();
 	let hash = tx2.hash.clone();
 	import(&mut txq, tx1).unwrap();
-	assert_eq!(import(&mut txq, tx2).unwrap_err(), error::Error::TooCheapToEnter(hash, "0x0".into()));
+	assert_eq!(import(&mut txq, tx2).unwrap_err(), error::Error::MemoryLimitReached(hash, 0.into()));
 	assert_eq!(txq.light_status().transaction_count, 1);
 
 	txq.clear();
@@ -168,7 +178,7 @@ fn should_reject_if_above_sender_count() {
 	let tx2 = b.tx().nonce(2).new();
 	let hash = tx2.hash.clone();
 	import(&mut txq, tx1).unwrap();
-	assert_eq!(import(&mut txq, tx2).unwrap_err(), error::Error::TooCheapToEnter(hash, "0x0".into()));
+	assert_eq!(import(&mut txq, tx2).unwrap_err(), error::Error::SenderLimitReached(hash, 0.into()));
 	assert_eq!(txq.light_status().transaction_count, 1);
 
 	txq.clear();
@@ -179,7 +189,7 @@ fn should_reject_if_above_sender_count() {
 	let hash = tx2.hash.clone();
 	import(&mut txq, tx1).unwrap();
 	// This results in error because we also compare nonces
-	assert_eq!(import(&mut txq, tx2).unwrap_err(), error::Error::TooCheapToEnter(hash, "0x0".into()));
+	assert_eq!(import(&mut txq, tx2).unwrap_err(), error::Error::SenderLimitReached(hash, 0.into()));
 	assert_eq!(txq.light_status().transaction_count, 1);
 }
 
@@ -235,6 +245,102 @@ fn should_construct_pending() {
 	assert_eq!(pending.next(), None);
 }
 
+#[test]
+fn should_build_block_template_within_limits() {
+	// given
+	let b = TransactionBuilder::default();
+	let mut txq = TestPool::default();
+
+	let tx0 = import(&mut txq, b.tx().nonce(0).gas_price(10).new()).unwrap();
+	// doesn't fit, so the following transaction from that sender is skipped too
+	import(&mut txq, b.tx().nonce(1).gas_price(10).gas(100_000).new()).unwrap();
+	import(&mut txq, b.tx().nonce(2).gas_price(10).new()).unwrap();
+
+	let tx3 = import(&mut txq, b.tx().sender(1).nonce(0).gas_price(5).new()).unwrap();
+	let tx4 = import(&mut txq, b.tx().sender(1).nonce(1).gas_price(5).new()).unwrap();
+	// exceeds the size limit
+	import(&mut txq, b.tx().sender(1).nonce(2).gas_price(5).mem_usage(100).new()).unwrap();
+
+	let tx6 = import(&mut txq, b.tx().sender(2).nonce(0).gas_price(1).new()).unwrap();
+	// future
+	import(&mut txq, b.tx().sender(3).nonce(1).gas_price(20).new()).unwrap();
+
+	// when
+	let limits = BlockLimits { gas: 21_000 * 5, size: 50, ..Default::default() };
+	let template = txq.block_template(NonceReady::default(), &limits);
+
+	// then
+	assert_eq!(template.transactions, vec![tx0, tx3, tx4, tx6]);
+	assert_eq!(template.gas, 21_000 * 4);
+	assert_eq!(template.size, 0);
+
+	// when
+	let limits = BlockLimits { gas: 21_000 * 2, ..Default::default() };
+	let template = txq.block_template(NonceReady::new(1), &limits);
+
+	// then
+	let included = template.transactions.iter().map(|tx| (tx.sender.to_low_u64_be(), tx.nonce)).collect::<Vec<_>>();
+	assert_eq!(included, vec![(3, 1.into()), (1, 1.into())]);
+}
+
+#[test]
+fn should_track_readiness_incrementally() {
+	// given
+	let b = TransactionBuilder::default();
+	let min_nonce = Arc::new(std::sync::Mutex::new(0));
+	let mut txq = TestPool::default().with_readiness({
+		let min_nonce = min_nonce.clone();
+		move || NonceReady::new(*min_nonce.lock().unwrap())
+	});
+
+	let tx0 = import(&mut txq, b.tx().nonce(0).gas_price(5).new()).unwrap();
+	let tx1 = import(&mut txq, b.tx().nonce(1).gas_price(5).new()).unwrap();
+	import(&mut txq, b.tx().nonce(3).gas_price(5).new()).unwrap();
+	let tx3 = import(&mut txq, b.tx().sender(1).nonce(0).gas_price(4).new()).unwrap();
+	let tx4 = import(&mut txq, b.tx().sender(1).nonce(1).gas_price(4).new()).unwrap();
+
+	// then
+	assert_eq!(txq.tracked_status(), Some(txq.status(NonceReady::default())));
+	assert_eq!(txq.tracked_status(), Some(Status { stalled: 0, pending: 4, future: 1 }));
+	assert_eq!(txq.tracked_pending().unwrap().collect::<Vec<_>>(), vec![tx0, tx1.clone(), tx3, tx4.clone()]);
+
+	// when
+	let tx2 = import(&mut txq, b.tx().nonce(2).gas_price(5).new()).unwrap();
+
+	// then
+	assert_eq!(txq.tracked_status(), Some(Status { stalled: 0, pending: 6, future: 0 }));
+
+	// when
+	*min_nonce.lock().unwrap() = 1;
+	txq.notify_state_change(&Address::zero());
+
+	// then
+	assert_eq!(txq.tracked_status(), Some(Status { stalled: 1, pending: 5, future: 0 }));
+	txq.notify_state_change(&Address::from_low_u64_be(1));
+	assert_eq!(txq.tracked_status(), Some(txq.status(NonceReady::new(1))));
+
+	// when
+	txq.cull(None, NonceReady::new(1));
+	txq.remove(tx2.hash(), false);
+
+	// then
+	assert_eq!(txq.tracked_status(), Some(Status { stalled: 0, pending: 2, future: 1 }));
+	assert_eq!(txq.tracked_pending().unwrap().collect::<Vec<_>>(), vec![tx1, tx4]);
+
+	// when
+	txq.clear();
+
+	// then
+	assert_eq!(txq.tracked_status(), Some(Status::default()));
+}
+
+#[test]
+fn should_not_track_readiness_by_default() {
+	let txq = TestPool::default();
+	assert_eq!(txq.tracked_status(), None);
+	assert!(txq.tracked_pending().is_none());
+}
+
 #[test]
 fn should_skip_staled_pending_transactions() {
 	let b = TransactionBuilder::default();
@@ -450,6 +556,186 @@ fn should_re_insert_after_cull() {
 	assert_eq!(txq.status(NonceReady::new(1)), Status { stalled: 2, pending: 2, future: 0 });
 }
 
+#[test]
+fn should_expire_transactions() {
+	// given
+	let b = TransactionBuilder::default();
+	let clock = ManualClock::default();
+	let mut txq = TestPool::with_options(Options { max_age: Some(Duration::from_secs(60)), ..Default::default() })
+		.with_clock(clock.clone());
+	import(&mut txq, b.tx().nonce(0).new()).unwrap();
+	import(&mut txq, b.tx().sender(1).nonce(0).new()).unwrap();
+	clock.advance(Duration::from_secs(30));
+	import(&mut txq, b.tx().nonce(1).new()).unwrap();
+	assert_eq!(txq.expire(), 0);
+
+	// when
+	clock.advance(Duration::from_secs(31));
+	let expired = txq.expire();
+
+	// then
+	assert_eq!(expired, 2);
+	assert_eq!(txq.light_status(), LightStatus { transaction_count: 1, senders: 1, mem_usage: 0 });
+	assert_eq!(txq.status(NonceReady::new(1)), Status { stalled: 0, pending: 1, future: 0 });
+	clock.advance(Duration::from_secs(30));
+	assert_eq!(txq.expire(), 1);
+	assert_eq!(txq.light_status().transaction_count, 0);
+}
+
+#[test]
+fn should_not_expire_without_max_age() {
+	let b = TransactionBuilder::default();
+	let clock = ManualClock::default();
+	let mut txq = TestPool::default().with_clock(clock.clone());
+	import(&mut txq, b.tx().nonce(0).new()).unwrap();
+
+	// when
+	clock.advance(Duration::from_secs(365 * 24 * 3600));
+
+	// then
+	assert_eq!(txq.expire(), 0);
+	assert_eq!(txq.light_status().transaction_count, 1);
+}
+
+#[test]
+fn should_import_many_like_single_imports() {
+	// given
+	let b = TransactionBuilder::default();
+	let batch = || {
+		vec![
+			b.tx().nonce(0).gas_price(5).new(),
+			b.tx().sender(1).nonce(0).gas_price(3).new(),
+			b.tx().nonce(1).gas_price(4).new(),
+			b.tx().nonce(0).gas_price(5).new(),
+			b.tx().nonce(0).gas_price(6).new(),
+			b.tx().sender(1).nonce(1).gas_price(7).new(),
+			b.tx().nonce(2).gas_price(1).new(),
+		]
+	};
+	let options = Options { max_per_sender: 2, ..Default::default() };
+	let mut single = TestPool::with_options(options.clone());
+	let mut many = TestPool::with_options(options);
+
+	// when
+	let expected = batch().into_iter().map(|tx| import(&mut single, tx)).collect::<Vec<_>>();
+	let results = many.import_many(batch(), &DummyScoring::default());
+
+	// then
+	assert_eq!(results, expected);
+	assert_eq!(results[3].as_ref().unwrap_err().code(), "already_imported");
+	assert_eq!(results[6].as_ref().unwrap_err().code(), "sender_limit_reached");
+	assert_eq!(many.light_status(), single.light_status());
+	assert_eq!(many.worst_transaction(), single.worst_transaction());
+	assert_eq!(
+		many.pending(NonceReady::default()).collect::<Vec<_>>(),
+		single.pending(NonceReady::default()).collect::<Vec<_>>()
+	);
+}
+
+#[test]
+fn should_evict_when_importing_many_above_limit() {
+	// given
+	let b = TransactionBuilder::default();
+	let mut txq = TestPool::with_limit(2);
+	import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap();
+
+	// when
+	let results = txq.import_many(
+		vec![
+			b.tx().sender(1).nonce(0).gas_price(3).new(),
+			b.tx().sender(2).nonce(0).gas_price(4).new(),
+			b.tx().sender(3).nonce(0).gas_price(2).new(),
+		],
+		&DummyScoring::default(),
+	);
+
+	// then
+	assert!(results[0].is_ok());
+	assert!(results[1].is_ok());
+	assert_eq!(results[2].as_ref().unwrap_err().min_score(), Some(&3.into()));
+	assert_eq!(txq.light_status().transaction_count, 2);
+	assert_eq!(txq.worst_transaction().unwrap().gas_price, 3.into());
+	assert_eq!(txq.pending(NonceReady::default()).count(), 2);
+}
+
+#[test]
+fn should_remove_many() {
+	// given
+	let b = TransactionBuilder::default();
+	let mut txq = TestPool::default().with_readiness(NonceReady::default);
+	let tx0 = import(&mut txq, b.tx().nonce(0).gas_price(5).new()).unwrap();
+	let tx1 = import(&mut txq, b.tx().nonce(1).gas_price(5).new()).unwrap();
+	let tx2 = import(&mut txq, b.tx().sender(1).nonce(0).gas_price(2).new()).unwrap();
+	import(&mut txq, b.tx().sender(1).nonce(1).gas_price(2).new()).unwrap();
+
+	// when
+	let removed = txq.remove_many(&[tx1.hash, H256::from_low_u64_be(1), tx0.hash, tx2.hash], true);
+
+	// then
+	assert_eq!(removed, vec![Some(tx1), None, Some(tx0), Some(tx2)]);
+	assert_eq!(txq.light_status(), LightStatus { mem_usage: 0, transaction_count: 1, senders: 1 });
+	assert_eq!(txq.worst_transaction().unwrap().nonce, 1.into());
+	assert_eq!(txq.tracked_status(), Some(Status { stalled: 0, pending: 0, future: 1 }));
+}
+
+#[test]
+fn should_reinsert_retracted_above_limits_within_overflow() {
+	// given
+	let b = TransactionBuilder::default();
+	let mut txq = TestPool::with_options(Options {
+		max_count: 2,
+		max_per_sender: 1,
+		max_retracted_overflow: 2,
+		..Default::default()
+	});
+	import(&mut txq, b.tx().sender(1).nonce(0).gas_price(5).new()).unwrap();
+	import(&mut txq, b.tx().sender(2).nonce(0).gas_price(5).new()).unwrap();
+
+	// when
+	let reinserted = txq.reinsert_retracted(
+		vec![
+			b.tx().nonce(1).gas_price(1).new(),
+			b.tx().nonce(0).gas_price(1).new(),
+			b.tx().sender(3).nonce(0).gas_price(1).new(),
+		],
+		&DummyScoring::default(),
+		NonceReady::default(),
+	);
+
+	// then
+	assert!(reinserted.results[0].is_ok());
+	assert!(reinserted.results[1].is_ok());
+	assert_eq!(reinserted.results[2].as_ref().unwrap_err().code(), "too_cheap_to_enter");
+	assert!(reinserted.stale.is_empty());
+	assert_eq!(txq.light_status().transaction_count, 4);
+	let pending = txq.pending(NonceReady::default()).map(|tx| (tx.sender, tx.nonce)).collect::<Vec<_>>();
+	assert_eq!(pending.len(), 4);
+	let own =
+		pending.iter().filter(|(sender, _)| *sender == Address::zero()).map(|(_, nonce)| *nonce).collect::<Vec<_>>();
+	assert_eq!(own, vec![0.into(), 1.into()]);
+}
+
+#[test]
+fn should_report_stale_retracted_transactions() {
+	// given
+	let b = TransactionBuilder::default();
+	let mut txq = TestPool::with_limit(2);
+	txq.ban(Address::zero(), Duration::from_secs(60));
+
+	// when
+	let reinserted = txq.reinsert_retracted(
+		vec![b.tx().nonce(0).new(), b.tx().nonce(1).new(), b.tx().nonce(2).new()],
+		&DummyScoring::default(),
+		NonceReady::new(1),
+	);
+
+	// then
+	assert!(reinserted.results.iter().all(|res| res.is_ok()));
+	assert_eq!(reinserted.stale.len(), 1);
+	assert_eq!(reinserted.stale[0].nonce, 0.into());
+	assert_eq!(txq.light_status().transaction_count, 2);
+}
+
 #[test]
 fn should_return_worst_transaction() {
 	// given
@@ -515,7 +801,7 @@ fn should_not_import_even_if_limit_is_reached_and_should_replace_returns_false()
 		err,
 		error::Error::TooCheapToEnter(
 			H256::from_str("00000000000000000000000000000000000000000000000000000000000001f5").unwrap(),
-			"0x5".into()
+			5.into()
 		)
 	);
 	assert_eq!(txq.light_status(), LightStatus { transaction_count: 1, senders: 1, mem_usage: 0 });
@@ -539,6 +825,39 @@ fn should_import_even_if_sender_limit_is_reached() {
 	assert_eq!(txq.light_status(), LightStatus { transaction_count: 2, senders: 1, mem_usage: 0 });
 }
 
+#[test]
+fn should_report_structured_rejection_reason() {
+	// given
+	let b = TransactionBuilder::default();
+	let mut txq = TestPool::with_options(Options { max_count: 1, ..Default::default() });
+	import(&mut txq, b.tx().nonce(0).gas_price(5).new()).unwrap();
+
+	// when
+	let err = import(&mut txq, b.tx().sender(1).nonce(0).gas_price(4).new()).unwrap_err();
+
+	// then
+	assert_eq!(err.code(), "too_cheap_to_enter");
+	assert_eq!(err.min_score(), Some(&5.into()));
+	assert!(err.to_string().ends_with("Min score: 0x5"));
+	assert_eq!(Error::<H256, U256>::AlreadyImported(H256::zero()).min_score(), None);
+}
+
+#[test]
+fn should_verify_and_import() {
+	// given
+	let b = TransactionBuilder::default();
+	let mut txq = TestPool::default();
+
+	// when
+	let err = txq.verify_and_import(b.tx().nonce(0).new(), &GasPriceVerifier, &DummyScoring::default()).unwrap_err();
+	txq.verify_and_import(b.tx().nonce(0).gas_price(1).new(), &GasPriceVerifier, &DummyScoring::default()).unwrap();
+
+	// then
+	assert_eq!(err, Error::Verification("zero gas price".into()));
+	assert_eq!(err.code(), "verification_failed");
+	assert_eq!(txq.light_status().transaction_count, 1);
+}
+
 mod listener {
 	use std::cell::RefCell;
 	use std::fmt;
@@ -554,7 +873,11 @@ mod listener {
 			self.0.borrow_mut().push(if old.is_some() { "replaced" } else { "added" });
 		}
 
-		fn rejected<H: fmt::Debug + fmt::LowerHex>(&mut self, _tx: &SharedTransaction, _reason: &error::Error<H>) {
+		fn rejected<H: fmt::Debug + fmt::LowerHex, S: fmt::Debug + fmt::LowerHex>(
+			&mut self,
+			_tx: &SharedTransaction,
+			_reason: &error::Error<H, S>,
+		) {
 			self.0.borrow_mut().push("rejected".into());
 		}
 
@@ -573,6 +896,14 @@ mod listener {
 		fn culled(&mut self, _tx: &SharedTransaction) {
 			self.0.borrow_mut().push("culled".into());
 		}
+
+		fn expired(&mut self, _tx: &SharedTransaction) {
+			self.0.borrow_mut().push("expired");
+		}
+
+		fn restore_failed(&mut self, _reason: &dyn fmt::Debug) {
+			self.0.borrow_mut().push("restore_failed");
+		}
 	}
 
 	#[test]
@@ -632,6 +963,32 @@ mod listener {
 		assert_eq!(txq.light_status().transaction_count, 0);
 	}
 
+	#[test]
+	fn import_and_remove_many() {
+		let b = TransactionBuilder::default();
+		let listener = MyListener::default();
+		let results = listener.0.clone();
+		let mut txq = Pool::new(listener, DummyScoring::default(), Options { max_count: 2, ..Default::default() });
+		import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap();
+		results.borrow_mut().clear();
+
+		// when
+		txq.import_many(
+			vec![
+				b.tx().sender(1).nonce(0).gas_price(2).new(),
+				b.tx().sender(1).nonce(0).gas_price(3).new(),
+				b.tx().sender(2).nonce(0).gas_price(4).new(),
+				b.tx().sender(3).nonce(0).new(),
+			],
+			&DummyScoring::default(),
+		);
+		let hashes = txq.pending(NonceReady::default()).map(|tx| tx.hash).collect::<Vec<_>>();
+		txq.remove_many(&hashes, false);
+
+		// then
+		assert_eq!(*results.borrow(), &["added", "dropped", "replaced", "added", "rejected", "canceled", "canceled"]);
+	}
+
 	#[test]
 	fn clear_queue() {
 		let b = TransactionBuilder::default();
@@ -667,4 +1024,1318 @@ mod listener {
 		// then
 		assert_eq!(*results.borrow(), &["added", "added", "culled", "culled"]);
 	}
+
+	#[test]
+	fn expire_old() {
+		let b = TransactionBuilder::default();
+		let listener = MyListener::default();
+		let results = listener.0.clone();
+		let clock = ManualClock::default();
+		let options = Options { max_age: Some(Duration::from_secs(60)), ..Default::default() };
+		let mut txq = Pool::new(listener, DummyScoring::default(), options).with_clock(clock.clone());
+
+		// insert
+		import(&mut txq, b.tx().nonce(1).new()).unwrap();
+		clock.advance(Duration::from_secs(60));
+		import(&mut txq, b.tx().nonce(2).new()).unwrap();
+
+		// when
+		clock.advance(Duration::from_secs(1));
+		txq.expire();
+
+		// then
+		assert_eq!(*results.borrow(), &["added", "added", "expired"]);
+	}
+
+	#[test]
+	fn restore_failed() {
+		let b = TransactionBuilder::default();
+		let dir = tempdir::TempDir::new("restore_failed").unwrap();
+		let journal = Journal::new(TestCodec, FileStore::new(dir.path().join("journal")));
+		let mut txq = TestPool::default();
+		import(&mut txq, b.tx().nonce(0).gas_price(0).new()).unwrap();
+		import(&mut txq, b.tx().nonce(1).gas_price(1).new()).unwrap();
+		journal.save(&txq).unwrap();
+
+		let listener = MyListener::default();
+		let results = listener.0.clone();
+		let mut txq = Pool::new(listener, DummyScoring::default(), Options::default());
+
+		// when
+		assert_eq!(journal.restore(&mut txq, &GasPriceVerifier, &DummyScoring::default()).unwrap(), 1);
+
+		// then
+		assert_eq!(*results.borrow(), &["restore_failed", "added"]);
+	}
+}
+
+mod shared {
+	use super::*;
+	use std::thread;
+
+	type TestSharedPool = SharedPool<Transaction, DummyScoring>;
+
+	fn pending(view: &PoolView<Transaction, DummyScoring>) -> Vec<U256> {
+		view.pending(NonceReady::default()).map(|tx| tx.nonce).collect()
+	}
+
+	#[test]
+	fn view_should_match_pool() {
+		// given
+		let b = TransactionBuilder::default();
+		let txq = TestSharedPool::new(TestPool::default());
+		let tx0 = txq.import(b.tx().nonce(0).gas_price(5).new(), &DummyScoring::default()).unwrap();
+		txq.import(b.tx().nonce(1).gas_price(5).new(), &DummyScoring::default()).unwrap();
+		txq.import(b.tx().sender(1).nonce(0).gas_price(6).new(), &DummyScoring::default()).unwrap();
+		txq.import(b.tx().sender(1).nonce(2).gas_price(6).new(), &DummyScoring::default()).unwrap();
+
+		// when
+		let view = txq.view();
+
+		// then
+		let expected = txq.with_pool(|pool| pool.pending(NonceReady::default()).collect::<Vec<_>>());
+		assert_eq!(view.pending(NonceReady::default()).collect::<Vec<_>>(), expected);
+		assert_eq!(pending(&view), vec![0.into(), 0.into(), 1.into()]);
+		assert_eq!(view.pending_from_sender(NonceReady::default(), &Address::zero()).count(), 2);
+		assert_eq!(view.light_status(), LightStatus { mem_usage: 0, transaction_count: 4, senders: 2 });
+		assert_eq!(view.epoch(), 4);
+
+		// when
+		txq.remove(tx0.hash(), false);
+		txq.cull(None, NonceReady::new(1));
+
+		// then
+		assert_eq!(pending(&txq.view()), Vec::<U256>::new());
+		assert_eq!(txq.view().pending(NonceReady::new(1)).count(), 1);
+		// the old view is not affected
+		assert_eq!(pending(&view), vec![0.into(), 0.into(), 1.into()]);
+	}
+
+	#[test]
+	fn should_find_in_view_without_locking_pool() {
+		// given
+		let b = TransactionBuilder::default();
+		let txq = TestSharedPool::new(TestPool::default());
+		let tx0 = txq.import(b.tx().nonce(0).new(), &DummyScoring::default()).unwrap();
+		let tx1 = txq.import(b.tx().nonce(1).new(), &DummyScoring::default()).unwrap();
+		let view = txq.view();
+
+		// when
+		txq.remove(tx0.hash(), false);
+
+		// then
+		txq.with_pool(|_| {
+			assert!(txq.find(tx0.hash()).is_none());
+			assert_eq!(txq.find(tx1.hash()), Some(tx1.clone()));
+		});
+		assert_eq!(view.find(tx0.hash()), Some(tx0));
+	}
+
+	#[test]
+	fn should_not_publish_unchanged_pool() {
+		let b = TransactionBuilder::default();
+		let txq = TestSharedPool::new(TestPool::default());
+		txq.import(b.tx().nonce(0).new(), &DummyScoring::default()).unwrap();
+		let epoch = txq.view().epoch();
+
+		// when
+		txq.remove(&H256::from_low_u64_be(1), false);
+		txq.with_pool(|pool| pool.light_status());
+
+		// then
+		assert_eq!(txq.view().epoch(), epoch);
+	}
+
+	#[test]
+	fn should_publish_batch_once() {
+		let b = TransactionBuilder::default();
+		let txq = TestSharedPool::new(TestPool::default());
+
+		// when
+		let results = txq.import_batch(
+			vec![b.tx().nonce(0).new(), b.tx().nonce(0).new(), b.tx().sender(1).nonce(0).new()],
+			&DummyScoring::default(),
+		);
+
+		// then
+		assert!(results[0].is_ok());
+		assert!(results[1].is_err());
+		assert!(results[2].is_ok());
+		assert_eq!(txq.view().epoch(), 1);
+		assert_eq!(txq.view().light_status().transaction_count, 2);
+	}
+
+	#[test]
+	fn should_read_pending_while_importing() {
+		let txq = Arc::new(TestSharedPool::new(TestPool::with_options(Options {
+			max_count: 10_000,
+			max_per_sender: 100,
+			..Default::default()
+		})));
+
+		let writers = (0..4u64)
+			.map(|sender| {
+				let txq = txq.clone();
+				thread::spawn(move || {
+					let b = TransactionBuilder::default().sender(sender);
+					for nonce in 0..100 {
+						txq.import(b.tx().nonce(nonce).gas_price(1).new(), &DummyScoring::default()).unwrap();
+					}
+				})
+			})
+			.collect::<Vec<_>>();
+
+		let readers = (0..4)
+			.map(|_| {
+				let txq = txq.clone();
+				thread::spawn(move || {
+					let mut epoch = 0;
+					while epoch < 400 {
+						let view = txq.view();
+						assert!(view.epoch() >= epoch);
+						epoch = view.epoch();
+						// transactions are imported in nonce order, so all of them are ready.
+						assert_eq!(view.pending(NonceReady::default()).count(), view.light_status().transaction_count);
+					}
+				})
+			})
+			.collect::<Vec<_>>();
+
+		for handle in writers.into_iter().chain(readers) {
+			handle.join().unwrap();
+		}
+		assert_eq!(txq.view().pending(NonceReady::default()).count(), 400);
+	}
+
+	#[test]
+	fn should_verify_and_import_in_background() {
+		let b = TransactionBuilder::default();
+		let txq = Arc::new(TestSharedPool::new(TestPool::default()));
+		let queue = ImportQueue::new(txq.clone(), GasPriceVerifier, DummyScoring::default(), Default::default());
+
+		// when
+		let handles = vec![
+			queue.submit(b.tx().nonce(0).gas_price(1).new()),
+			queue.submit(b.tx().nonce(1).gas_price(0).new()),
+			queue.submit(b.tx().nonce(0).gas_price(1).new()),
+			queue.submit(b.tx().nonce(1).gas_price(1).new()),
+		];
+		let results = handles.into_iter().map(|h| h.wait()).collect::<Vec<_>>();
+
+		// then
+		assert_eq!(results[0].as_ref().unwrap().nonce, 0.into());
+		assert!(matches!(results[1], Err(ImportError::Verification("zero gas price"))));
+		assert!(matches!(results[2], Err(ImportError::Pool(Error::AlreadyImported(_)))));
+		assert_eq!(results[3].as_ref().unwrap().nonce, 1.into());
+		assert_eq!(pending(&txq.view()), vec![0.into(), 1.into()]);
+	}
+
+	#[test]
+	fn should_await_import_results() {
+		let b = TransactionBuilder::default();
+		let txq = Arc::new(TestSharedPool::new(TestPool::default()));
+		let queue = ImportQueue::new(txq.clone(), GasPriceVerifier, DummyScoring::default(), Default::default());
+
+		// when
+		let imported = block_on(queue.submit(b.tx().nonce(0).gas_price(1).new()));
+		let rejected = block_on(queue.submit(b.tx().nonce(1).gas_price(0).new()));
+
+		// then
+		assert_eq!(imported.unwrap().nonce, 0.into());
+		assert!(matches!(rejected, Err(ImportError::Verification("zero gas price"))));
+		assert_eq!(pending(&txq.view()), vec![0.into()]);
+	}
+
+	#[test]
+	fn should_process_submitted_transactions_on_drop() {
+		let b = TransactionBuilder::default();
+		let txq = Arc::new(TestSharedPool::new(TestPool::default()));
+		let queue = ImportQueue::new(
+			txq.clone(),
+			GasPriceVerifier,
+			DummyScoring::default(),
+			ImportQueueOptions { max_batch_size: 1 },
+		);
+		let handles = (0..10).map(|nonce| queue.submit(b.tx().nonce(nonce).gas_price(1).new())).collect::<Vec<_>>();
+
+		// when
+		drop(queue);
+
+		// then
+		for handle in handles {
+			assert!(handle.try_wait().unwrap().is_ok());
+		}
+		assert_eq!(txq.view().light_status().transaction_count, 10);
+	}
+}
+
+struct TestCodec;
+
+impl Codec<Transaction> for TestCodec {
+	type Unverified = Transaction;
+	type Error = &'static str;
+
+	fn encode(&self, tx: &Transaction) -> Vec<u8> {
+		let mut bytes = tx.hash.as_bytes().to_vec();
+		bytes.extend_from_slice(&<[u8; 32]>::from(tx.nonce));
+		bytes.extend_from_slice(&<[u8; 32]>::from(tx.gas_price));
+		bytes.extend_from_slice(tx.sender.as_bytes());
+		bytes
+	}
+
+	fn decode(&self, bytes: &[u8]) -> Result<Transaction, Self::Error> {
+		if bytes.len() != 116 {
+			return Err("invalid length");
+		}
+		Ok(Transaction {
+			hash: H256::from_slice(&bytes[..32]),
+			nonce: U256::from_big_endian(&bytes[32..64]),
+			gas_price: U256::from_big_endian(&bytes[64..96]),
+			gas: 21_000.into(),
+			sender: Address::from_slice(&bytes[96..]),
+			mem_usage: 0,
+		})
+	}
+}
+
+mod journal {
+	use super::*;
+	use std::time::Duration;
+	use tempdir::TempDir;
+
+	fn populated_pool() -> TestPool {
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::default();
+		import(&mut txq, b.tx().nonce(0).gas_price(5).new()).unwrap();
+		import(&mut txq, b.tx().nonce(1).gas_price(1).new()).unwrap();
+		import(&mut txq, b.tx().sender(1).nonce(0).gas_price(3).new()).unwrap();
+		txq
+	}
+
+	fn pending(txq: &TestPool) -> Vec<SharedTransaction> {
+		txq.pending(NonceReady::default()).collect()
+	}
+
+	fn assert_restores<St: JournalStore>(store: St) {
+		// given
+		let txq = populated_pool();
+		let journal = Journal::new(TestCodec, store);
+		assert_eq!(journal.save(&txq).unwrap(), 3);
+
+		// when
+		let mut restored = TestPool::default();
+		assert_eq!(journal.restore(&mut restored, &GasPriceVerifier, &DummyScoring::default()).unwrap(), 3);
+
+		// then
+		assert_eq!(pending(&restored), pending(&txq));
+	}
+
+	#[test]
+	fn should_restore_from_file() {
+		let dir = TempDir::new("should_restore_from_file").unwrap();
+		assert_restores(FileStore::new(dir.path().join("journal")));
+	}
+
+	#[cfg(feature = "kvdb")]
+	#[test]
+	fn should_restore_from_database() {
+		let db = Arc::new(kvdb_memorydb::create(1));
+		assert_restores(DatabaseStore::new(db.clone(), 0));
+
+		// saving again replaces previous records
+		let journal = Journal::new(TestCodec, DatabaseStore::new(db, 0));
+		journal.save(&TestPool::default()).unwrap();
+		let mut restored = TestPool::default();
+		assert_eq!(journal.restore(&mut restored, &GasPriceVerifier, &DummyScoring::default()).unwrap(), 0);
+	}
+
+	#[test]
+	fn should_restore_nothing_without_journal() {
+		let dir = TempDir::new("should_restore_nothing_without_journal").unwrap();
+		let journal = Journal::new(TestCodec, FileStore::new(dir.path().join("journal")));
+		let mut restored = TestPool::default();
+		assert_eq!(journal.restore(&mut restored, &GasPriceVerifier, &DummyScoring::default()).unwrap(), 0);
+	}
+
+	#[test]
+	fn should_save_shared_pool_on_shutdown() {
+		// given
+		let dir = TempDir::new("should_save_shared_pool_on_shutdown").unwrap();
+		let path = dir.path().join("journal");
+		let txq = Arc::new(SharedPool::new(populated_pool()));
+		let writer = JournalWriter::start(
+			Journal::new(TestCodec, FileStore::new(&path)),
+			txq.clone(),
+			Duration::from_secs(3600),
+		);
+
+		// when
+		txq.import(TransactionBuilder::default().sender(2).gas_price(1).new(), &DummyScoring::default()).unwrap();
+		drop(writer);
+
+		// then
+		let journal = Journal::new(TestCodec, FileStore::new(&path));
+		let mut restored = TestPool::default();
+		assert_eq!(journal.restore(&mut restored, &GasPriceVerifier, &DummyScoring::default()).unwrap(), 4);
+	}
+}
+
+mod tags {
+	use super::*;
+	use std::cmp;
+
+	#[derive(Debug, PartialEq)]
+	struct TaggedTransaction {
+		hash: H256,
+		sender: Address,
+		priority: u64,
+		provides: Vec<u8>,
+		requires: Vec<u8>,
+	}
+
+	impl VerifiedTransaction for TaggedTransaction {
+		type Hash = H256;
+		type Sender = Address;
+
+		fn hash(&self) -> &H256 {
+			&self.hash
+		}
+		fn mem_usage(&self) -> usize {
+			0
+		}
+		fn sender(&self) -> &Address {
+			&self.sender
+		}
+	}
+
+	impl Tagged for TaggedTransaction {
+		type Tag = u8;
+
+		fn provides(&self) -> &[u8] {
+			&self.provides
+		}
+		fn requires(&self) -> &[u8] {
+			&self.requires
+		}
+	}
+
+	#[derive(Debug, Default)]
+	struct PriorityScoring;
+
+	impl Scoring<TaggedTransaction> for PriorityScoring {
+		type Score = u64;
+		type Event = ();
+
+		fn compare(&self, old: &TaggedTransaction, other: &TaggedTransaction) -> cmp::Ordering {
+			other.priority.cmp(&old.priority)
+		}
+
+		fn choose(&self, _old: &TaggedTransaction, _new: &TaggedTransaction) -> scoring::Choice {
+			scoring::Choice::InsertNew
+		}
+
+		fn update_scores(
+			&self,
+			txs: &[pool::Transaction<TaggedTransaction>],
+			scores: &mut [u64],
+			_change: scoring::Change,
+		) {
+			for (score, tx) in scores.iter_mut().zip(txs) {
+				*score = tx.priority;
+			}
+		}
+	}
+
+	impl ShouldReplace<TaggedTransaction> for PriorityScoring {
+		fn should_replace(
+			&self,
+			old: &ReplaceTransaction<'_, TaggedTransaction>,
+			new: &ReplaceTransaction<'_, TaggedTransaction>,
+		) -> scoring::Choice {
+			if new.priority > old.priority {
+				scoring::Choice::ReplaceOld
+			} else {
+				scoring::Choice::RejectNew
+			}
+		}
+	}
+
+	fn tx(id: u64, priority: u64, provides: &[u8], requires: &[u8]) -> TaggedTransaction {
+		TaggedTransaction {
+			hash: H256::from_low_u64_be(id),
+			sender: Address::from_low_u64_be(id),
+			priority,
+			provides: provides.to_vec(),
+			requires: requires.to_vec(),
+		}
+	}
+
+	fn pending(txq: &TaggedPool<TaggedTransaction, PriorityScoring>) -> Vec<u64> {
+		txq.pending().iter().map(|tx| tx.hash.to_low_u64_be()).collect()
+	}
+
+	fn new_pool(options: Options) -> TaggedPool<TaggedTransaction, PriorityScoring> {
+		TaggedPool::new(NoopListener, PriorityScoring, options)
+	}
+
+	#[test]
+	fn should_promote_when_requirements_are_met() {
+		// given
+		let mut txq = new_pool(Options::default());
+		txq.import(tx(3, 30, &[3], &[1, 2]), &PriorityScoring).unwrap();
+		txq.import(tx(2, 20, &[2], &[1]), &PriorityScoring).unwrap();
+		assert_eq!(txq.status(), Status { stalled: 0, pending: 0, future: 2 });
+		assert!(pending(&txq).is_empty());
+
+		// when
+		txq.import(tx(1, 10, &[1], &[]), &PriorityScoring).unwrap();
+
+		// then
+		assert_eq!(txq.status(), Status { stalled: 0, pending: 3, future: 0 });
+		// providers come first, despite lower priority
+		assert_eq!(pending(&txq), vec![1, 2, 3]);
+	}
+
+	#[test]
+	fn should_order_independent_transactions_by_priority() {
+		let mut txq = new_pool(Options::default());
+		txq.import(tx(1, 10, &[1], &[]), &PriorityScoring).unwrap();
+		txq.import(tx(2, 50, &[2], &[]), &PriorityScoring).unwrap();
+		txq.import(tx(3, 30, &[3], &[1]), &PriorityScoring).unwrap();
+		txq.import(tx(4, 40, &[4], &[]), &PriorityScoring).unwrap();
+
+		assert_eq!(pending(&txq), vec![2, 4, 1, 3]);
+	}
+
+	#[test]
+	fn should_promote_on_externally_provided_tags() {
+		let mut txq = new_pool(Options::default());
+		txq.import(tx(2, 20, &[2], &[1]), &PriorityScoring).unwrap();
+		assert!(!txq.is_ready(&H256::from_low_u64_be(2)));
+
+		// when
+		txq.provide(&[1]);
+
+		// then
+		assert!(txq.is_ready(&H256::from_low_u64_be(2)));
+		assert_eq!(pending(&txq), vec![2]);
+	}
+
+	#[test]
+	fn should_remove_dependents_of_removed_provider() {
+		// given
+		let mut txq = new_pool(Options::default());
+		txq.import(tx(1, 10, &[1], &[]), &PriorityScoring).unwrap();
+		txq.import(tx(2, 20, &[2], &[1]), &PriorityScoring).unwrap();
+		txq.import(tx(3, 30, &[3], &[2]), &PriorityScoring).unwrap();
+		// another provider of `5` keeps `4` ready
+		txq.import(tx(4, 40, &[4], &[5]), &PriorityScoring).unwrap();
+		txq.import(tx(5, 50, &[5], &[]), &PriorityScoring).unwrap();
+		txq.import(tx(6, 60, &[5], &[]), &PriorityScoring).unwrap();
+		assert_eq!(txq.status().pending, 6);
+
+		// when
+		txq.remove(&H256::from_low_u64_be(1), false);
+		txq.remove(&H256::from_low_u64_be(5), false);
+
+		// then
+		assert_eq!(txq.light_status().transaction_count, 2);
+		assert_eq!(pending(&txq), vec![6, 4]);
+	}
+
+	#[test]
+	fn should_keep_dependents_of_culled_provider() {
+		// given
+		let mut txq = new_pool(Options::default());
+		txq.import(tx(1, 10, &[1], &[]), &PriorityScoring).unwrap();
+		txq.import(tx(2, 20, &[2], &[1]), &PriorityScoring).unwrap();
+
+		// when
+		let stale = H256::from_low_u64_be(1);
+		txq.cull(None, |tx: &TaggedTransaction| if tx.hash == stale { Readiness::Stale } else { Readiness::Ready });
+
+		// then
+		assert_eq!(txq.light_status().transaction_count, 1);
+		assert_eq!(pending(&txq), vec![2]);
+	}
+
+	#[test]
+	fn should_remove_dependents_of_evicted_provider() {
+		// given
+		let mut txq = new_pool(Options { max_count: 2, ..Default::default() });
+		txq.import(tx(1, 10, &[1], &[]), &PriorityScoring).unwrap();
+		txq.import(tx(2, 20, &[2], &[1]), &PriorityScoring).unwrap();
+
+		// when
+		txq.import(tx(3, 30, &[3], &[]), &PriorityScoring).unwrap();
+
+		// then
+		assert_eq!(txq.light_status().transaction_count, 1);
+		assert_eq!(pending(&txq), vec![3]);
+	}
+
+	#[test]
+	fn should_keep_dependents_of_evicted_provider_when_replaced_by_new_provider() {
+		// given
+		let mut txq = new_pool(Options { max_count: 2, ..Default::default() });
+		txq.import(tx(1, 10, &[1], &[]), &PriorityScoring).unwrap();
+		txq.import(tx(2, 20, &[2], &[1]), &PriorityScoring).unwrap();
+
+		// when
+		txq.import(tx(3, 30, &[1], &[]), &PriorityScoring).unwrap();
+
+		// then
+		assert_eq!(txq.light_status().transaction_count, 2);
+		assert!(txq.is_ready(&H256::from_low_u64_be(2)));
+		assert_eq!(pending(&txq), vec![3, 2]);
+	}
+
+	#[test]
+	fn should_demote_dependents_of_retracted_tags() {
+		// given
+		let mut txq = new_pool(Options::default());
+		txq.import(tx(2, 20, &[2], &[1]), &PriorityScoring).unwrap();
+		txq.import(tx(3, 30, &[3], &[2]), &PriorityScoring).unwrap();
+		txq.provide(&[1]);
+		assert_eq!(pending(&txq), vec![2, 3]);
+
+		// when
+		let demoted = txq.retract(&[1]);
+
+		// then
+		assert_eq!(demoted.len(), 2);
+		assert_eq!(txq.status(), Status { stalled: 0, pending: 0, future: 2 });
+		txq.provide(&[1]);
+		assert_eq!(pending(&txq), vec![2, 3]);
+	}
+}
+
+mod anti_spam {
+	use super::*;
+
+	fn gas_price_bump(percent: u32) -> ScoreBump<Transaction> {
+		ScoreBump { percent, value: Box::new(|tx: &Transaction| tx.gas_price.low_u128()) }
+	}
+
+	#[test]
+	fn should_reject_banned_sender_until_timeout() {
+		// given
+		let b = TransactionBuilder::default();
+		let clock = ManualClock::default();
+		let mut txq = TestPool::default().with_clock(clock.clone());
+		txq.ban(Address::zero(), Duration::from_secs(10));
+
+		// when
+		let tx = b.tx().nonce(0).new();
+		let hash = tx.hash;
+
+		// then
+		assert_eq!(import(&mut txq, tx).unwrap_err(), Error::SenderBanned(hash));
+		import(&mut txq, b.tx().sender(1).nonce(0).new()).unwrap();
+		clock.advance(Duration::from_secs(10));
+		assert!(!txq.is_banned(&Address::zero()));
+		import(&mut txq, b.tx().nonce(0).new()).unwrap();
+	}
+
+	#[test]
+	fn should_unban_sender() {
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::default();
+		txq.ban(Address::zero(), Duration::from_secs(10));
+		assert!(txq.is_banned(&Address::zero()));
+
+		// when
+		assert!(txq.unban(&Address::zero()));
+
+		// then
+		import(&mut txq, b.tx().nonce(0).new()).unwrap();
+	}
+
+	#[test]
+	fn should_limit_replacements_per_window() {
+		// given
+		let b = TransactionBuilder::default();
+		let clock = ManualClock::default();
+		let mut txq = TestPool::default().with_clock(clock.clone()).with_anti_spam(AntiSpam {
+			replacement_limit: Some(ReplacementLimit { max_replacements: 2, window: Duration::from_secs(60) }),
+			..Default::default()
+		});
+		import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap();
+		import(&mut txq, b.tx().nonce(0).gas_price(2).new()).unwrap();
+		import(&mut txq, b.tx().nonce(0).gas_price(3).new()).unwrap();
+
+		// when
+		let tx = b.tx().nonce(0).gas_price(4).new();
+		let hash = tx.hash;
+
+		// then
+		assert_eq!(import(&mut txq, tx).unwrap_err(), Error::TooManyReplacements(hash));
+		// new transactions are still accepted
+		import(&mut txq, b.tx().nonce(1).gas_price(1).new()).unwrap();
+		clock.advance(Duration::from_secs(60));
+		import(&mut txq, b.tx().nonce(0).gas_price(4).new()).unwrap();
+		assert_eq!(txq.light_status().transaction_count, 2);
+	}
+
+	#[test]
+	fn should_reject_all_replacements_with_zero_limit() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::default().with_anti_spam(AntiSpam {
+			replacement_limit: Some(ReplacementLimit { max_replacements: 0, window: Duration::from_secs(60) }),
+			..Default::default()
+		});
+		import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap();
+
+		// when
+		let tx = b.tx().nonce(0).gas_price(2).new();
+		let hash = tx.hash;
+
+		// then
+		assert_eq!(import(&mut txq, tx).unwrap_err(), Error::TooManyReplacements(hash));
+	}
+
+	#[test]
+	fn should_prune_expired_bans_and_replacements() {
+		// given
+		let b = TransactionBuilder::default();
+		let clock = ManualClock::default();
+		let mut txq = TestPool::default().with_clock(clock.clone()).with_anti_spam(AntiSpam {
+			replacement_limit: Some(ReplacementLimit { max_replacements: 2, window: Duration::from_secs(60) }),
+			..Default::default()
+		});
+
+		// when
+		for sender in 1..500 {
+			import(&mut txq, b.tx().sender(sender).nonce(0).gas_price(1).new()).unwrap();
+			import(&mut txq, b.tx().sender(sender).nonce(0).gas_price(2).new()).unwrap();
+			txq.ban(Address::from_low_u64_be(sender + 1000), Duration::from_secs(1));
+			clock.advance(Duration::from_secs(60));
+		}
+
+		// then
+		assert!(txq.spam_tracked_senders() <= 64, "{}", txq.spam_tracked_senders());
+	}
+
+	#[test]
+	fn should_require_min_score_bump_to_evict() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::with_limit(1)
+			.with_anti_spam(AntiSpam { min_score_bump: Some(gas_price_bump(10)), ..Default::default() });
+		let tx0 = import(&mut txq, b.tx().nonce(0).gas_price(100).new()).unwrap();
+
+		// when
+		let tx1 = b.tx().sender(1).nonce(0).gas_price(109).new();
+		let hash = tx1.hash;
+
+		// then
+		assert_eq!(import(&mut txq, tx1).unwrap_err(), Error::InsufficientScoreBump(tx0.hash, hash));
+		import(&mut txq, b.tx().sender(1).nonce(0).gas_price(110).new()).unwrap();
+		assert!(txq.find(&tx0.hash).is_none());
+	}
+
+	#[test]
+	fn should_require_min_score_bump_to_replace() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::default()
+			.with_anti_spam(AntiSpam { min_score_bump: Some(gas_price_bump(10)), ..Default::default() });
+		let tx0 = import(&mut txq, b.tx().nonce(0).gas_price(100).new()).unwrap();
+
+		// when
+		let tx1 = b.tx().nonce(0).gas_price(109).new();
+		let hash = tx1.hash;
+
+		// then
+		assert_eq!(import(&mut txq, tx1).unwrap_err(), Error::InsufficientScoreBump(tx0.hash, hash));
+		assert!(txq.find(&tx0.hash).is_some());
+		let tx2 = import(&mut txq, b.tx().nonce(0).gas_price(110).new()).unwrap();
+		assert!(txq.find(&tx0.hash).is_none());
+		assert!(txq.find(&tx2.hash).is_some());
+	}
+
+	#[test]
+	fn should_never_evict_local_transactions() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::with_limit(2).with_anti_spam(AntiSpam {
+			is_local: Some(Box::new(|tx: &Transaction| tx.sender == Address::zero())),
+			..Default::default()
+		});
+		let local = import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap();
+		let remote = import(&mut txq, b.tx().sender(1).nonce(0).gas_price(2).new()).unwrap();
+
+		// when
+		import(&mut txq, b.tx().sender(2).nonce(0).gas_price(3).new()).unwrap();
+
+		// then
+		assert!(txq.find(&local.hash).is_some());
+		assert!(txq.find(&remote.hash).is_none());
+
+		// when the pool is full of local transactions
+		let mut txq = TestPool::with_limit(1).with_anti_spam(AntiSpam {
+			is_local: Some(Box::new(|tx: &Transaction| tx.sender == Address::zero())),
+			..Default::default()
+		});
+		import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap();
+		let tx = b.tx().sender(1).nonce(0).gas_price(3).new();
+		let hash = tx.hash;
+
+		// then
+		assert_eq!(import(&mut txq, tx).unwrap_err(), Error::PoolFull(hash));
+	}
+}
+
+mod eviction {
+	use super::*;
+
+	#[test]
+	fn should_evict_largest_per_score_on_memory_limit_and_worst_on_count_limit() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::with_options(Options { max_count: 3, max_mem_usage: 10, ..Default::default() })
+			.with_memory_eviction(LargestMemoryPerScore::new(|score: &U256| score.low_u128()));
+		let large = import(&mut txq, b.tx().sender(1).nonce(0).gas_price(2).mem_usage(6).new()).unwrap();
+		let cheap = import(&mut txq, b.tx().sender(2).nonce(0).gas_price(1).mem_usage(2).new()).unwrap();
+
+		// when
+		import(&mut txq, b.tx().sender(3).nonce(0).gas_price(5).mem_usage(4).new()).unwrap();
+
+		// then
+		assert!(txq.find(&large.hash).is_none());
+		assert!(txq.find(&cheap.hash).is_some());
+
+		// when
+		import(&mut txq, b.tx().sender(4).nonce(0).gas_price(3).mem_usage(1).new()).unwrap();
+		import(&mut txq, b.tx().sender(5).nonce(0).gas_price(3).mem_usage(1).new()).unwrap();
+
+		// then
+		assert!(txq.find(&cheap.hash).is_none());
+		assert_eq!(txq.light_status(), LightStatus { mem_usage: 6, transaction_count: 3, senders: 3 });
+	}
+
+	#[test]
+	fn should_evict_oldest_first() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::with_limit(2).with_count_eviction(OldestFirst);
+		let oldest = import(&mut txq, b.tx().sender(1).nonce(0).gas_price(5).new()).unwrap();
+		let worst = import(&mut txq, b.tx().sender(2).nonce(0).gas_price(1).new()).unwrap();
+
+		// when
+		let tx = b.tx().sender(3).nonce(0).gas_price(3).new();
+		let hash = tx.hash;
+
+		// then
+		assert_eq!(import(&mut txq, tx).unwrap_err(), Error::TooCheapToEnter(hash, 5.into()));
+		import(&mut txq, b.tx().sender(3).nonce(0).gas_price(6).new()).unwrap();
+		assert!(txq.find(&oldest.hash).is_none());
+		assert!(txq.find(&worst.hash).is_some());
+	}
+
+	#[test]
+	fn should_evict_from_sender_with_most_transactions() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::with_limit(3).with_count_eviction(FairShare);
+		let first = import(&mut txq, b.tx().sender(1).nonce(0).gas_price(5).new()).unwrap();
+		let second = import(&mut txq, b.tx().sender(1).nonce(1).gas_price(5).new()).unwrap();
+		let worst = import(&mut txq, b.tx().sender(2).nonce(0).gas_price(1).new()).unwrap();
+
+		// when
+		import(&mut txq, b.tx().sender(3).nonce(0).gas_price(6).new()).unwrap();
+
+		// then
+		assert!(txq.find(&first.hash).is_some());
+		assert!(txq.find(&second.hash).is_none());
+		assert!(txq.find(&worst.hash).is_some());
+	}
+}
+
+mod fee_market {
+	use super::*;
+
+	#[derive(Debug)]
+	struct Eip1559Transaction {
+		hash: u64,
+		sender: u64,
+		nonce: u64,
+		max_fee: u128,
+		max_priority_fee: u128,
+	}
+
+	impl VerifiedTransaction for Eip1559Transaction {
+		type Hash = u64;
+		type Sender = u64;
+
+		fn hash(&self) -> &u64 {
+			&self.hash
+		}
+		fn mem_usage(&self) -> usize {
+			1
+		}
+		fn sender(&self) -> &u64 {
+			&self.sender
+		}
+	}
+
+	impl FeeMarketTransaction for Eip1559Transaction {
+		fn nonce(&self) -> u64 {
+			self.nonce
+		}
+		fn max_fee_per_gas(&self) -> u128 {
+			self.max_fee
+		}
+		fn max_priority_fee_per_gas(&self) -> u128 {
+			self.max_priority_fee
+		}
+	}
+
+	type FeePool = Pool<Eip1559Transaction, FeeMarketScoring>;
+
+	fn tx(hash: u64, sender: u64, nonce: u64, max_fee: u128, max_priority_fee: u128) -> Eip1559Transaction {
+		Eip1559Transaction { hash, sender, nonce, max_fee, max_priority_fee }
+	}
+
+	fn import(txq: &mut FeePool, tx: Eip1559Transaction) -> Result<Arc<Eip1559Transaction>, Error<u64, FeeScore>> {
+		let replace = txq.scoring().clone();
+		txq.import(tx, &replace)
+	}
+
+	fn pending(txq: &FeePool) -> Vec<u64> {
+		txq.pending(|_: &Eip1559Transaction| Readiness::Ready).map(|tx| tx.hash).collect()
+	}
+
+	#[test]
+	fn should_order_by_effective_tip() {
+		// given
+		let mut txq = FeePool::with_scoring(FeeMarketScoring::new(10), Options::default());
+		import(&mut txq, tx(1, 1, 0, 30, 5)).unwrap();
+		import(&mut txq, tx(2, 2, 0, 12, 10)).unwrap();
+		import(&mut txq, tx(3, 3, 0, 100, 3)).unwrap();
+
+		// then
+		assert_eq!(pending(&txq), vec![1, 3, 2]);
+		assert_eq!(txq.worst_transaction().unwrap().hash, 2);
+	}
+
+	#[test]
+	fn should_resort_when_base_fee_changes() {
+		// given
+		let mut txq = FeePool::with_scoring(FeeMarketScoring::new(10), Options::default());
+		import(&mut txq, tx(1, 1, 0, 30, 5)).unwrap();
+		import(&mut txq, tx(2, 2, 0, 12, 10)).unwrap();
+		import(&mut txq, tx(3, 3, 0, 100, 3)).unwrap();
+		import(&mut txq, tx(4, 3, 1, 100, 8)).unwrap();
+
+		// when
+		txq.set_scoring_parameter(0);
+
+		// then
+		assert_eq!(pending(&txq), vec![2, 1, 3, 4]);
+		assert_eq!(
+			txq.dump().map(|(score, tx)| (tx.hash, score.effective_tip)).collect::<Vec<_>>(),
+			vec![(2, 10), (4, 8), (1, 5), (3, 3)]
+		);
+
+		// when
+		txq.set_scoring_parameter(20);
+
+		// then
+		assert_eq!(pending(&txq), vec![1, 3, 4, 2]);
+		assert_eq!(txq.worst_transaction().unwrap().hash, 2);
+	}
+
+	#[test]
+	fn should_rescore_lazily_on_next_modification() {
+		// given
+		let mut txq = FeePool::with_scoring(FeeMarketScoring::new(10), Options::default());
+		import(&mut txq, tx(1, 1, 0, 30, 5)).unwrap();
+		import(&mut txq, tx(2, 2, 0, 12, 10)).unwrap();
+		import(&mut txq, tx(3, 3, 0, 100, 3)).unwrap();
+
+		// when
+		txq.set_scoring_parameter(0);
+
+		// then
+		assert_eq!(txq.senders().cloned().collect::<Vec<_>>(), vec![2, 1, 3]);
+		assert_eq!(txq.metrics(&[], 0).eviction_threshold.unwrap().effective_tip, 3);
+
+		// when
+		txq.remove(&1, false);
+
+		// then
+		txq.check_invariants().unwrap();
+		assert_eq!(txq.senders().cloned().collect::<Vec<_>>(), vec![2, 3]);
+		assert_eq!(pending(&txq), vec![2, 3]);
+	}
+
+	#[test]
+	fn should_evict_transactions_unable_to_pay_the_base_fee() {
+		// given
+		let mut txq = FeePool::with_scoring(FeeMarketScoring::new(10), Options { max_count: 2, ..Default::default() });
+		import(&mut txq, tx(1, 1, 0, 30, 5)).unwrap();
+		import(&mut txq, tx(2, 2, 0, 12, 2)).unwrap();
+		assert_eq!(import(&mut txq, tx(3, 3, 0, 40, 1)).unwrap_err().code(), "too_cheap_to_enter");
+
+		// when
+		txq.set_scoring_parameter(20);
+		import(&mut txq, tx(3, 3, 0, 40, 1)).unwrap();
+
+		// then
+		assert!(txq.find(&2).is_none());
+		assert_eq!(pending(&txq), vec![1, 3]);
+	}
+
+	#[test]
+	fn should_replace_only_if_both_fees_are_bumped() {
+		// given
+		let mut txq = FeePool::with_scoring(FeeMarketScoring::new(10), Options::default());
+		import(&mut txq, tx(1, 1, 0, 30, 5)).unwrap();
+
+		// then
+		assert_eq!(import(&mut txq, tx(2, 1, 0, 40, 5)).unwrap_err(), Error::TooCheapToReplace(1, 2));
+		assert_eq!(import(&mut txq, tx(3, 1, 0, 30, 6)).unwrap_err(), Error::TooCheapToReplace(1, 3));
+		import(&mut txq, tx(4, 1, 0, 31, 6)).unwrap();
+		assert_eq!(pending(&txq), vec![4]);
+	}
+}
+
+mod history {
+	use super::*;
+
+	fn pool(history_window: usize) -> TestPool {
+		TestPool::with_options(Options { history_window, ..Default::default() })
+	}
+
+	#[test]
+	fn should_report_net_changes_since_version() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = pool(16);
+		let v0 = txq.version();
+		let tx0 = import(&mut txq, b.tx().nonce(0).new()).unwrap();
+		let tx1 = import(&mut txq, b.tx().sender(1).nonce(0).new()).unwrap();
+		let v1 = txq.version();
+
+		// when
+		txq.remove(&tx0.hash, false);
+		let tx2 = import(&mut txq, b.tx().sender(2).nonce(0).new()).unwrap();
+		txq.remove(&tx2.hash, true);
+
+		// then
+		assert_eq!(txq.changes_since(v0), Some(Changes { version: 5, added: vec![tx1.hash], removed: vec![] }));
+		assert_eq!(txq.changes_since(v1), Some(Changes { version: 5, added: vec![], removed: vec![tx0.hash] }));
+		assert_eq!(txq.changes_since(txq.version()), Some(Changes { version: 5, added: vec![], removed: vec![] }));
+	}
+
+	#[test]
+	fn should_report_replaced_and_cleared_transactions() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = pool(16);
+		let old = import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap();
+		let v0 = txq.version();
+
+		// when
+		let new = import(&mut txq, b.tx().nonce(0).gas_price(2).new()).unwrap();
+
+		// then
+		let changes = txq.changes_since(v0).unwrap();
+		assert_eq!((changes.added, changes.removed), (vec![new.hash], vec![old.hash]));
+
+		// when
+		let v1 = txq.version();
+		txq.clear();
+
+		// then
+		assert_eq!(txq.changes_since(v1).unwrap().removed, vec![new.hash]);
+	}
+
+	#[test]
+	fn should_not_report_changes_outside_of_window() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = pool(2);
+
+		// when
+		for nonce in 0..3 {
+			import(&mut txq, b.tx().nonce(nonce).new()).unwrap();
+		}
+
+		// then
+		assert_eq!(txq.version(), 3);
+		assert_eq!(txq.changes_since(0), None);
+		assert_eq!(txq.changes_since(1).unwrap().added.len(), 2);
+		assert_eq!(txq.changes_since(4), None);
+		assert_eq!(pool(0).changes_since(0), Some(Changes { version: 0, added: vec![], removed: vec![] }));
+	}
+}
+
+mod simulation {
+	use super::*;
+	use crate::simulation::{Config, Simulation, TransactionSpec};
+
+	fn transaction(spec: &TransactionSpec) -> Transaction {
+		Transaction {
+			hash: H256::from_low_u64_be(spec.id),
+			nonce: spec.nonce.into(),
+			gas_price: spec.fee.into(),
+			gas: 21_000.into(),
+			sender: Address::from_low_u64_be(spec.sender),
+			mem_usage: spec.mem_usage,
+		}
+	}
+
+	fn options() -> Options {
+		Options { max_count: 32, max_per_sender: 4, max_mem_usage: 640, ..Default::default() }
+	}
+
+	#[test]
+	fn should_keep_invariants_under_random_workload() {
+		for seed in 0..4 {
+			let config = Config { seed, ..Default::default() };
+			let mut simulation =
+				Simulation::new(TestPool::with_options(options()), DummyScoring::default(), config, transaction);
+			simulation.run(1000).unwrap_or_else(|violation| panic!("{}", violation));
+			assert_eq!(simulation.steps(), 1000);
+		}
+	}
+
+	#[test]
+	fn should_keep_invariants_with_eviction_policies() {
+		let pool = TestPool::with_options(options())
+			.with_count_eviction(FairShare)
+			.with_memory_eviction(LargestMemoryPerScore::new(|score: &U256| score.low_u128()));
+		let config = Config { seed: 7, senders: 4, ..Default::default() };
+		let mut simulation = Simulation::new(pool, DummyScoring::default(), config, transaction);
+		simulation.run(1000).unwrap_or_else(|violation| panic!("{}", violation));
+	}
+
+	#[test]
+	fn should_be_deterministic() {
+		let run = |seed| {
+			let config = Config { seed, ..Default::default() };
+			let mut simulation =
+				Simulation::new(TestPool::with_options(options()), DummyScoring::default(), config, transaction);
+			simulation.run(200).unwrap();
+			simulation.pool().dump().map(|(_, tx)| tx.hash).collect::<Vec<_>>()
+		};
+
+		assert_eq!(run(3), run(3));
+		assert_ne!(run(3), run(4));
+	}
+
+	#[test]
+	#[should_panic(expected = "Config::senders must not be zero")]
+	fn should_reject_config_without_senders() {
+		let config = Config { senders: 0, ..Default::default() };
+		Simulation::new(TestPool::with_options(options()), DummyScoring::default(), config, transaction);
+	}
+}
+
+mod metrics {
+	use super::*;
+
+	#[test]
+	fn should_count_events() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = Pool::new(
+			MetricsListener::default(),
+			DummyScoring::default(),
+			Options { max_count: 2, ..Default::default() },
+		);
+
+		// when
+		import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap();
+		import(&mut txq, b.tx().nonce(0).gas_price(2).new()).unwrap();
+		import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap_err();
+		import(&mut txq, b.tx().sender(1).nonce(0).gas_price(3).new()).unwrap();
+		let tx = import(&mut txq, b.tx().sender(2).nonce(0).gas_price(4).new()).unwrap();
+		import(&mut txq, b.tx().sender(3).nonce(0).gas_price(1).new()).unwrap_err();
+		txq.remove(&tx.hash, true).unwrap();
+		txq.clear();
+
+		// then
+		let counters = txq.listener_mut().take_counters();
+		assert_eq!(counters.imported, 3);
+		assert_eq!(counters.replaced, 1);
+		assert_eq!(counters.evicted, 1);
+		assert_eq!(counters.invalid, 1);
+		assert_eq!(counters.cleared, 1);
+		assert_eq!(counters.rejected.get("too_cheap_to_replace"), Some(&1));
+		assert_eq!(counters.rejected.get("too_cheap_to_enter"), Some(&1));
+		assert_eq!(counters.rejected_total(), 2);
+		assert_eq!(txq.listener().counters(), &Counters::default());
+	}
+
+	#[test]
+	fn should_report_pool_metrics() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::with_limit(5);
+		import(&mut txq, b.tx().nonce(0).gas_price(5).new()).unwrap();
+		import(&mut txq, b.tx().nonce(1).gas_price(15).new()).unwrap();
+		import(&mut txq, b.tx().nonce(2).gas_price(25).new()).unwrap();
+		import(&mut txq, b.tx().sender(1).nonce(0).gas_price(10).new()).unwrap();
+		import(&mut txq, b.tx().sender(1).nonce(1).gas_price(12).new()).unwrap();
+
+		// when
+		let metrics = txq.metrics(&[10.into(), 20.into()], 1);
+
+		// then
+		assert_eq!(metrics.status, txq.light_status());
+		assert_eq!(metrics.score_histogram, vec![1, 3, 1]);
+		assert_eq!(metrics.top_senders, vec![(Address::zero(), 3)]);
+		assert_eq!(metrics.eviction_threshold, Some(12.into()));
+		assert!(metrics.is_full);
+
+		let empty = TestPool::default().metrics(&[], 10);
+		assert_eq!(empty.score_histogram, vec![0]);
+		assert!(empty.top_senders.is_empty());
+		assert_eq!(empty.eviction_threshold, None);
+	}
+
+	#[test]
+	fn should_dump_transactions_by_score() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = TestPool::default();
+		import(&mut txq, b.tx().nonce(0).gas_price(5).new()).unwrap();
+		import(&mut txq, b.tx().nonce(1).gas_price(15).new()).unwrap();
+		import(&mut txq, b.tx().sender(1).nonce(0).gas_price(10).new()).unwrap();
+
+		// when
+		let dump = txq.dump().map(|(score, tx)| (score, tx.nonce)).collect::<Vec<_>>();
+
+		// then
+		assert_eq!(dump, vec![(15.into(), 1.into()), (10.into(), 0.into()), (5.into(), 0.into())]);
+	}
+}
+
+mod events {
+	use super::*;
+	use std::thread;
+
+	type EventPool = Pool<Transaction, DummyScoring, EventStream<Transaction>>;
+
+	fn pool(capacity: usize) -> EventPool {
+		Pool::new(EventStream::new(capacity), DummyScoring::default(), Options { max_count: 2, ..Default::default() })
+	}
+
+	fn kinds(subscription: &mut Subscription<Transaction>) -> Vec<&'static str> {
+		let mut kinds = Vec::new();
+		while let Some(event) = subscription.try_next() {
+			kinds.push(match event {
+				Ok(Event::Added(_)) => "added",
+				Ok(Event::Replaced { .. }) => "replaced",
+				Ok(Event::Dropped { .. }) => "dropped",
+				Ok(Event::Rejected { .. }) => "rejected",
+				Ok(Event::Invalid(_)) => "invalid",
+				Ok(Event::Canceled(_)) => "canceled",
+				Ok(Event::Culled(_)) => "culled",
+				Ok(Event::Expired(_)) => "expired",
+				Ok(Event::Mined(_)) => "mined",
+				Err(_) => "lagged",
+			});
+		}
+		kinds
+	}
+
+	#[test]
+	fn should_publish_events_to_all_subscribers() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = pool(16);
+		let mut first = txq.listener().subscribe();
+		let mut second = txq.listener().subscribe();
+
+		// when
+		import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap();
+		import(&mut txq, b.tx().nonce(0).gas_price(2).new()).unwrap();
+		import(&mut txq, b.tx().nonce(0).gas_price(1).new()).unwrap_err();
+		let tx = import(&mut txq, b.tx().sender(1).nonce(0).gas_price(3).new()).unwrap();
+		import(&mut txq, b.tx().sender(2).nonce(0).gas_price(4).new()).unwrap();
+		txq.remove_mined(&tx.hash).unwrap();
+		txq.cull(None, NonceReady::new(1));
+
+		// then
+		let expected = vec!["added", "replaced", "rejected", "added", "dropped", "added", "mined", "culled"];
+		assert_eq!(kinds(&mut first), expected);
+		assert_eq!(kinds(&mut second), expected);
+	}
+
+	#[test]
+	fn should_report_lagging_subscriber() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = Pool::new(EventStream::new(2), DummyScoring::default(), Options::default());
+		let mut subscription = txq.listener().subscribe();
+
+		// when
+		import(&mut txq, b.tx().nonce(0).new()).unwrap();
+		import(&mut txq, b.tx().nonce(1).new()).unwrap();
+		let tx = b.tx().sender(1).nonce(0).gas_price(1).new();
+		let hash = tx.hash;
+		import(&mut txq, tx).unwrap();
+
+		// then
+		assert_eq!(subscription.try_next().unwrap().unwrap_err(), Lagged(1));
+		subscription.try_next().unwrap().unwrap();
+		assert_eq!(subscription.try_next().unwrap().unwrap().transaction().hash, hash);
+		assert!(subscription.try_next().is_none());
+	}
+
+	#[test]
+	fn should_filter_events_by_hash() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = pool(16);
+		let tx = b.tx().nonce(0).gas_price(1).new();
+		let mut subscription = txq.listener().subscribe_hash(tx.hash);
+
+		// when
+		import(&mut txq, tx).unwrap();
+		import(&mut txq, b.tx().sender(1).nonce(0).new()).unwrap();
+		let replacement = import(&mut txq, b.tx().nonce(0).gas_price(2).new()).unwrap();
+		txq.remove(&replacement.hash, true).unwrap();
+
+		// then
+		assert_eq!(kinds(&mut subscription), vec!["added", "replaced"]);
+	}
+
+	#[test]
+	fn should_wake_async_subscriber_and_close_with_pool() {
+		// given
+		let b = TransactionBuilder::default();
+		let mut txq = pool(16);
+		let mut subscription = txq.listener().subscribe();
+		let subscriber = thread::spawn(move || {
+			let mut hashes = Vec::new();
+			while let Some(event) = block_on(subscription.recv()) {
+				hashes.push(event.unwrap().transaction().hash);
+			}
+			hashes
+		});
+
+		// when
+		let tx0 = import(&mut txq, b.tx().nonce(0).new()).unwrap();
+		let tx1 = import(&mut txq, b.tx().nonce(1).new()).unwrap();
+		drop(txq);
+
+		// then
+		assert_eq!(subscriber.join().unwrap(), vec![tx0.hash, tx1.hash]);
+	}
+
+	#[test]
+	fn should_unsubscribe_on_drop() {
+		let txq = pool(16);
+		let subscription = txq.listener().subscribe_hash(H256::zero());
+		assert_eq!(txq.listener().subscribers(), 1);
+
+		drop(subscription);
+		assert_eq!(txq.listener().subscribers(), 0);
+	}
 }
@@ -33,6 +33,11 @@ impl TransactionBuilder {
 		self
 	}
 
+	pub fn gas(mut self, gas: usize) -> Self {
+		self.gas = U256::from(gas);
+		self
+	}
+
 	pub fn sender(mut self, sender: u64) -> Self {
 		self.sender = Address::from_low_u64_be(sender);
 		self
@@ -51,7 +56,7 @@ impl TransactionBuilder {
 			hash: H256::from_uint(&hash),
 			nonce: self.nonce,
 			gas_price: self.gas_price,
-			gas: 21_000.into(),
+			gas: if self.gas.is_zero() { 21_000.into() } else { self.gas },
 			sender: self.sender,
 			mem_usage: self.mem_usage,
 		}
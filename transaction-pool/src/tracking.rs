@@ -0,0 +1,106 @@
+// Copyright 2020 Parity Technologies
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Incremental tracking of ready and future transactions.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{
+	pool::Transaction,
+	ready::{Readiness, Ready},
+	status::Status,
+	VerifiedTransaction,
+};
+
+type ReadyFactory<T> = Box<dyn Fn() -> Box<dyn Ready<T>> + Send>;
+
+/// Readiness of every transaction in the pool, updated one sender at a time.
+pub(crate) struct ReadinessTracker<T: VerifiedTransaction> {
+	factory: ReadyFactory<T>,
+	by_hash: HashMap<T::Hash, Readiness>,
+	by_sender: HashMap<T::Sender, (Vec<T::Hash>, Status)>,
+	status: Status,
+}
+
+impl<T: VerifiedTransaction> fmt::Debug for ReadinessTracker<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ReadinessTracker").field("status", &self.status).finish()
+	}
+}
+
+impl<T: VerifiedTransaction> ReadinessTracker<T> {
+	pub(crate) fn new<F, R>(factory: F) -> Self
+	where
+		F: Fn() -> R + Send + 'static,
+		R: Ready<T> + 'static,
+	{
+		ReadinessTracker {
+			factory: Box::new(move || Box::new(factory())),
+			by_hash: HashMap::new(),
+			by_sender: HashMap::new(),
+			status: Status::default(),
+		}
+	}
+
+	/// Returns readiness of a transaction, transactions not known to the tracker are `Future`.
+	pub(crate) fn readiness(&self, hash: &T::Hash) -> Readiness {
+		self.by_hash.get(hash).cloned().unwrap_or(Readiness::Future)
+	}
+
+	pub(crate) fn status(&self) -> Status {
+		self.status.clone()
+	}
+
+	/// Re-checks readiness of all `transactions` from `sender`, ordered by `Scoring`.
+	pub(crate) fn update<'a, I>(&mut self, sender: &T::Sender, transactions: I)
+	where
+		T: 'a,
+		I: IntoIterator<Item = &'a Transaction<T>>,
+	{
+		if let Some((hashes, status)) = self.by_sender.remove(sender) {
+			for hash in hashes {
+				self.by_hash.remove(&hash);
+			}
+			self.status.stalled -= status.stalled;
+			self.status.pending -= status.pending;
+			self.status.future -= status.future;
+		}
+
+		let mut ready = (self.factory)();
+		let mut hashes = Vec::new();
+		let mut status = Status::default();
+		let mut readiness = Readiness::Ready;
+		for tx in transactions {
+			// Once a transaction is not ready, none of the following ones can be.
+			if readiness != Readiness::Future {
+				readiness = ready.is_ready(tx);
+			}
+			match readiness {
+				Readiness::Stale => status.stalled += 1,
+				Readiness::Ready => status.pending += 1,
+				Readiness::Future => status.future += 1,
+			}
+			self.by_hash.insert(tx.hash().clone(), readiness);
+			hashes.push(tx.hash().clone());
+		}
+
+		if !hashes.is_empty() {
+			self.status.stalled += status.stalled;
+			self.status.pending += status.pending;
+			self.status.future += status.future;
+			self.by_sender.insert(sender.clone(), (hashes, status));
+		}
+	}
+
+	pub(crate) fn clear(&mut self) {
+		self.by_hash.clear();
+		self.by_sender.clear();
+		self.status = Status::default();
+	}
+}
@@ -17,6 +17,9 @@ use crate::{
 	scoring::{self, Scoring},
 };
 
+/// Recomputes the scores of transactions from a sender, see `ParametrizedScoring::rescore`.
+pub type Rescore<T, S> = fn(&S, &[Transaction<T>], &mut [<S as Scoring<T>>::Score]) -> bool;
+
 #[derive(Debug)]
 pub enum AddResult<T, S> {
 	Ok(T),
@@ -54,6 +57,10 @@ impl<T: fmt::Debug, S: Scoring<T>> Transactions<T, S> {
 		self.transactions.iter()
 	}
 
+	pub fn scores(&self) -> &[S::Score] {
+		&self.scores
+	}
+
 	pub fn worst_and_best(&self) -> Option<((S::Score, Transaction<T>), (S::Score, Transaction<T>))> {
 		let len = self.scores.len();
 		self.scores.get(0).cloned().map(|best| {
@@ -99,6 +106,28 @@ impl<T: fmt::Debug, S: Scoring<T>> Transactions<T, S> {
 		scoring.update_scores(&self.transactions, &mut self.scores, scoring::Change::Event(event));
 	}
 
+	/// Recomputes the scores after a change of the scoring parameter, returns `true` if any score changed.
+	pub fn rescore(&mut self, scoring: &S, rescore: Rescore<T, S>) -> bool {
+		rescore(scoring, &self.transactions, &mut self.scores)
+	}
+
+	/// Returns the scores recomputed after a change of the scoring parameter, leaving the set intact.
+	pub fn rescored(&self, scoring: &S, rescore: Rescore<T, S>) -> Vec<S::Score> {
+		let mut scores = self.scores.to_vec();
+		rescore(scoring, &self.transactions, &mut scores);
+		scores
+	}
+
+	/// Returns the transaction already in the set which adding `new` would replace.
+	pub fn replaced_by(&self, new: &Transaction<T>, scoring: &S) -> Option<&Transaction<T>> {
+		match self.transactions.binary_search_by(|old| scoring.compare(old, new)) {
+			Ok(index) if scoring.choose(&self.transactions[index], new) == scoring::Choice::ReplaceOld => {
+				Some(&self.transactions[index])
+			}
+			_ => None,
+		}
+	}
+
 	pub fn add(&mut self, new: Transaction<T>, scoring: &S, max_count: usize) -> AddResult<Transaction<T>, S::Score> {
 		let index = match self.transactions.binary_search_by(|old| scoring.compare(old, &new)) {
 			Ok(index) => index,